@@ -0,0 +1,57 @@
+use std::{fmt::Debug, fs};
+
+use stellar_core::{
+    analysis::{
+        duration::analyze, fold::fold, recursion::find_cycles, resolve::resolve, types::check,
+    },
+    syntax::{location::Spanned, parse::parse, scan::scan},
+};
+
+pub fn run(filepath: &str) {
+    let contents = fs::read_to_string(filepath).expect("Failed to read the file");
+
+    let token_stream = scan(&contents).expect("Error scanning");
+    let statements = parse(token_stream).expect("Error parsing");
+
+    let resolution = resolve(&statements);
+
+    for error in &resolution.errors {
+        report(error);
+    }
+
+    for error in check(&statements, &resolution) {
+        report(&error);
+    }
+
+    for error in &fold(&statements, &resolution).errors {
+        report(error);
+    }
+
+    let cycles = find_cycles(&statements, &resolution);
+
+    for error in &cycles.errors {
+        report(error);
+    }
+
+    for warning in &cycles.warnings {
+        report(warning);
+    }
+
+    let durations = analyze(&statements, &resolution);
+
+    for warning in &durations.warnings {
+        report(warning);
+    }
+
+    for (id, symbol) in resolution.symbols() {
+        if let Some(length) = durations.sequence(id) {
+            println!("sequence {}: {}", symbol.name.name().resolve(), length);
+        }
+    }
+}
+
+fn report(error: &(impl Spanned + Debug)) {
+    let start = error.span().start();
+
+    println!("{}:{}: {:?}", start.line(), start.column(), error);
+}
@@ -0,0 +1,16 @@
+use std::fs;
+
+use stellar_core::{
+    analysis::resolve::resolve,
+    syntax::{parse::parse, scan::scan},
+    vm::compile::compile,
+};
+
+pub fn run(filepath: &str) {
+    let contents = fs::read_to_string(filepath).expect("Failed to read the file");
+
+    let token_stream = scan(&contents).expect("Error scanning");
+    let statements = parse(token_stream).expect("Error parsing");
+
+    print!("{}", compile(&statements, &resolve(&statements)));
+}
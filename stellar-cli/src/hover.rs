@@ -0,0 +1,17 @@
+use std::fs;
+
+use stellar_core::{
+    analysis::{hover::hover, resolve::resolve},
+    syntax::{parse::parse, scan::scan},
+};
+
+pub fn run(filepath: &str, line: u32, column: u32) {
+    let contents = fs::read_to_string(filepath).expect("Failed to read the file");
+
+    let token_stream = scan(&contents).expect("Error scanning");
+    let statements = parse(token_stream).expect("Error parsing");
+
+    if let Some(text) = hover(&statements, &resolve(&statements), line, column) {
+        println!("{text}");
+    }
+}
@@ -0,0 +1,31 @@
+use std::{fs, path::Path};
+
+use stellar_core::{
+    analysis::resolve::resolve,
+    ctx::interpreter::{interpret, Options},
+    syntax::{parse::parse, scan::scan},
+};
+
+pub fn run(filepath: &str) {
+    let contents = fs::read_to_string(filepath).expect("Failed to read the file");
+
+    let token_stream = scan(&contents).expect("Error scanning");
+    let statements = parse(token_stream).expect("Error parsing");
+
+    // Tuning and groove files are read from the directory of the script.
+    let directory = Path::new(filepath)
+        .parent()
+        .filter(|directory| !directory.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let options = Options {
+        files: Some(directory.to_path_buf()),
+        ..Options::default()
+    };
+
+    let resolution = resolve(&statements);
+
+    match interpret(&statements, &resolution, &options) {
+        Ok(ir) => print!("{ir}"),
+        Err(error) => println!("{error:?}"),
+    }
+}
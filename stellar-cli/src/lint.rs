@@ -0,0 +1,57 @@
+use std::{fs, path::Path, process};
+
+use stellar_core::{
+    analysis::resolve::resolve,
+    ctx::interpreter::Options,
+    lint::{config::LintConfig, lint, Level, Rule},
+    syntax::{location::Spanned, parse::parse, scan::scan},
+};
+
+pub fn run(filepath: &str, theory: bool) {
+    let contents = fs::read_to_string(filepath).expect("Failed to read the file");
+
+    let token_stream = scan(&contents).expect("Error scanning");
+    let statements = parse(token_stream).expect("Error parsing");
+
+    let directory = Path::new(filepath)
+        .parent()
+        .filter(|directory| !directory.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut config = LintConfig::discover(directory).expect("Error reading the config file");
+
+    // Theory rules are raised, unless the config file sets their level.
+    if theory {
+        for rule in Rule::THEORY {
+            config.set_default(rule, Level::Warn);
+        }
+    }
+
+    // Tuning and groove files are read from the directory of the script.
+    let options = Options {
+        files: Some(directory.to_path_buf()),
+        ..Options::default()
+    };
+
+    let lints = lint(&statements, &resolve(&statements), &config, &options);
+
+    for lint in &lints {
+        let start = lint.span().start();
+        let severity = match lint.level {
+            Level::Deny => "error",
+            _ => "warning",
+        };
+
+        println!(
+            "{}:{}: {}[{}]: {}",
+            start.line(),
+            start.column(),
+            severity,
+            lint.kind.rule().id(),
+            lint.kind
+        );
+    }
+
+    if lints.iter().any(|lint| lint.level == Level::Deny) {
+        process::exit(1);
+    }
+}
@@ -1,7 +1,13 @@
 use clap::{Parser, Subcommand};
 
-mod scan;
+mod check;
+mod disasm;
+mod hover;
+mod ir;
+mod lint;
 mod parse;
+mod run;
+mod scan;
 
 #[derive(Parser)]
 #[command(name = "Stellar", about = "Programming language for creating music.")]
@@ -20,6 +26,49 @@ enum Command {
         #[arg(value_name = "FILE")]
         filepath: String,
     },
+    /// Reports errors found by static analysis without running the file.
+    Check {
+        #[arg(value_name = "FILE")]
+        filepath: String,
+    },
+    /// Reports code, which is valid but likely to be a mistake.
+    Lint {
+        #[arg(value_name = "FILE")]
+        filepath: String,
+        /// Also checks voice leading and keys of the played notes.
+        #[arg(long)]
+        theory: bool,
+    },
+    /// Prints events of every channel the file plays.
+    Ir {
+        #[arg(value_name = "FILE")]
+        filepath: String,
+    },
+    /// Runs the file and prints the events it plays.
+    Run {
+        #[arg(value_name = "FILE")]
+        filepath: String,
+        /// Seed of random functions, such as `degrade`.
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Runs the compiled bytecode instead of evaluating the syntax tree.
+        #[arg(long)]
+        vm: bool,
+    },
+    /// Prints the bytecode the file compiles to.
+    Disasm {
+        #[arg(value_name = "FILE")]
+        filepath: String,
+    },
+    /// Prints the notes and chord names of the statement at a location,
+    /// for hover text of editors.
+    Hover {
+        #[arg(value_name = "FILE")]
+        filepath: String,
+        line: u32,
+        /// Column, counted from 0.
+        column: u32,
+    },
 }
 
 fn main() {
@@ -30,5 +79,15 @@ fn main() {
             scan::run(&filepath);
         }
         Command::Parse { filepath } => parse::run(&filepath),
+        Command::Check { filepath } => check::run(&filepath),
+        Command::Lint { filepath, theory } => lint::run(&filepath, theory),
+        Command::Ir { filepath } => ir::run(&filepath),
+        Command::Run { filepath, seed, vm } => run::run(&filepath, seed, vm),
+        Command::Disasm { filepath } => disasm::run(&filepath),
+        Command::Hover {
+            filepath,
+            line,
+            column,
+        } => hover::run(&filepath, line, column),
     }
 }
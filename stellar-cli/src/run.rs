@@ -0,0 +1,38 @@
+use std::{fs, path::Path};
+
+use stellar_core::{
+    analysis::resolve::resolve,
+    ctx::interpreter::{interpret, Options},
+    syntax::{parse::parse, scan::scan},
+    vm::{compile::compile, machine},
+};
+
+pub fn run(filepath: &str, seed: u64, vm: bool) {
+    let contents = fs::read_to_string(filepath).expect("Failed to read the file");
+
+    let token_stream = scan(&contents).expect("Error scanning");
+    let statements = parse(token_stream).expect("Error parsing");
+
+    // Tuning and groove files are read from the directory of the script.
+    let directory = Path::new(filepath)
+        .parent()
+        .filter(|directory| !directory.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let options = Options {
+        seed,
+        files: Some(directory.to_path_buf()),
+        ..Options::default()
+    };
+
+    let resolution = resolve(&statements);
+    let result = if vm {
+        machine::run(&compile(&statements, &resolution), &options)
+    } else {
+        interpret(&statements, &resolution, &options)
+    };
+
+    match result {
+        Ok(ir) => print!("{ir}"),
+        Err(error) => println!("{error:?}"),
+    }
+}
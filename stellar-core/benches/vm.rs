@@ -0,0 +1,70 @@
+//! Compares the bytecode machine with the tree-walking interpreter on a
+//! generated piece: `cargo bench -p stellar-core --bench vm`.
+
+use std::time::{Duration, Instant};
+
+use stellar_core::{
+    analysis::resolve::resolve,
+    ctx::interpreter::{interpret, Options},
+    rational::Rational,
+    syntax::{parse::parse, scan::scan},
+    vm::{compile::compile, machine::run},
+};
+
+const RUNS: u32 = 10;
+
+/// Generates a piece with many channels playing arithmetic-heavy loops.
+fn source(channels: usize) -> String {
+    let mut source = String::from("let root = c4\n");
+
+    for channel in 0..channels {
+        source += &format!(
+            "sequence voice{channel} {{\n let step = {step}\n let note = root\n repeat {{\n  repeat 8 {{\n   note = note + step * 2 - step\n   play note\n   wait 1/4\n  }}\n  note = root + {channel} - {channel}\n  play [note, note + 7]\n  wait 1/2\n }}\n}}\nplay! voice{channel}\n",
+            step = channel % 5 + 1,
+        );
+    }
+
+    source
+}
+
+fn measure(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        f();
+    }
+
+    start.elapsed() / RUNS
+}
+
+fn main() {
+    let source = source(16);
+    let statements = parse(scan(&source).unwrap()).unwrap();
+    let resolution = resolve(&statements);
+    let options = Options {
+        horizon: Rational::integer(256),
+        ..Options::default()
+    };
+
+    let program = compile(&statements, &resolution);
+    assert_eq!(
+        run(&program, &options).unwrap(),
+        interpret(&statements, &resolution, &options).unwrap()
+    );
+
+    let interpreter = measure(|| {
+        interpret(&statements, &resolution, &options).unwrap();
+    });
+    let machine = measure(|| {
+        run(&program, &options).unwrap();
+    });
+    let compilation = measure(|| {
+        compile(&statements, &resolution);
+    });
+
+    println!("interpreter: {interpreter:?}");
+    println!("vm:          {machine:?} (compiled in {compilation:?})");
+    println!(
+        "speedup:     {:.2}x",
+        interpreter.as_secs_f64() / machine.as_secs_f64()
+    );
+}
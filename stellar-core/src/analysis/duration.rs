@@ -0,0 +1,552 @@
+//! Static duration analysis: computes how long every sequence plays
+//! without running the program.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use crate::{
+    ctx::properties::{literal_rational, Properties},
+    pattern::rhythm::DEFAULT_POLY_SPAN,
+    rational::Rational,
+    syntax::{
+        ast::{BinaryOperatorKind, Block, Expression, Statement},
+        location::{Span, Spanned},
+        token::Identifier,
+    },
+    theory::{parse_name, Name},
+};
+
+use super::resolve::{Resolution, SymbolId, SymbolKind, Target};
+
+/// Length of a piece of music in beats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Length {
+    Exact(Rational),
+    /// Length depending on runtime values: `wait 250ms` (the tempo may
+    /// change), `play fast(x, drums)`.
+    Unknown,
+    /// Length of an endless `repeat`.
+    Infinite,
+}
+
+impl Length {
+    pub const ZERO: Self = Self::Exact(Rational::ZERO);
+
+    fn then(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Infinite, _) | (_, Self::Infinite) => Self::Infinite,
+            (Self::Exact(left), Self::Exact(right)) => {
+                left.checked_add(right).map_or(Self::Unknown, Self::Exact)
+            }
+            _ => Self::Unknown,
+        }
+    }
+
+    fn max(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Infinite, _) | (_, Self::Infinite) => Self::Infinite,
+            (Self::Exact(left), Self::Exact(right)) => Self::Exact(left.max(right)),
+            _ => Self::Unknown,
+        }
+    }
+
+    fn times(self, count: i64) -> Self {
+        match self {
+            _ if count == 0 => Self::ZERO,
+            Self::Exact(length) => length
+                .checked_mul(Rational::integer(count))
+                .map_or(Self::Unknown, Self::Exact),
+            other => other,
+        }
+    }
+}
+
+impl Display for Length {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exact(beats) => write!(f, "{beats} beats"),
+            Self::Unknown => write!(f, "unknown"),
+            Self::Infinite => write!(f, "infinite"),
+        }
+    }
+}
+
+/// Position of the playhead while walking through a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Timing {
+    /// Beat the next statement starts at.
+    cursor: Length,
+    /// Beat the last sounding note or channel ends at.
+    end: Length,
+}
+
+impl Timing {
+    const START: Self = Self {
+        cursor: Length::ZERO,
+        end: Length::ZERO,
+    };
+
+    /// Appends a block played after the current position.
+    fn then(&mut self, block: Timing) {
+        self.end = self.end.max(self.cursor.then(block.end));
+        self.cursor = self.cursor.then(block.cursor);
+    }
+
+    /// Moves the cursor without playing anything.
+    fn advance(&mut self, length: Length) {
+        self.then(Timing {
+            cursor: length,
+            end: length,
+        });
+    }
+
+    /// Extends the end to cover something played at the cursor.
+    fn sound(&mut self, length: Length) {
+        self.end = self.end.max(self.cursor.then(length));
+    }
+
+    /// Returns the timing of a block repeated `count` times, or forever.
+    fn repeat(self, count: Option<i64>) -> Timing {
+        match count {
+            None => Timing {
+                cursor: Length::Infinite,
+                end: Length::Infinite,
+            },
+            Some(0) => Timing::START,
+            Some(count) => Timing {
+                cursor: self.cursor.times(count),
+                end: self.cursor.times(count - 1).then(self.end),
+            },
+        }
+    }
+}
+
+/// What a `play` statement plays.
+enum Sound {
+    Sequence {
+        name: Identifier,
+        length: Length,
+    },
+    /// Note or chord, sounding for the `length` of the enclosing block.
+    Note,
+    /// Sample played to the end, whatever its length is.
+    Sample,
+    Unknown,
+}
+
+/// Lengths of all sequences and the whole program.
+#[derive(Debug)]
+pub struct Durations {
+    sequences: HashMap<SymbolId, Length>,
+    program: Length,
+    pub warnings: Vec<DurationWarning>,
+}
+
+impl Durations {
+    /// Returns the length of a sequence, from its first statement until
+    /// every note and channel it started ends.
+    pub fn sequence(&self, id: SymbolId) -> Option<Length> {
+        self.sequences.get(&id).copied()
+    }
+
+    /// Returns the length of the whole program.
+    pub fn program(&self) -> Length {
+        self.program
+    }
+}
+
+/// Computes lengths of every sequence in a resolved program.
+pub fn analyze(statements: &[Statement], resolution: &Resolution) -> Durations {
+    let mut analyzer = Analyzer {
+        resolution,
+        definitions: HashMap::new(),
+        variables: HashMap::new(),
+        lengths: HashMap::new(),
+        in_progress: HashSet::new(),
+        warnings: Vec::new(),
+    };
+
+    analyzer.collect(statements);
+
+    // Definition order keeps warnings stable.
+    for (id, _) in resolution.symbols() {
+        if analyzer.definitions.contains_key(&id) {
+            analyzer.sequence_length(id);
+        }
+    }
+
+    let program = analyzer.block(statements, &Properties::default()).end;
+
+    Durations {
+        sequences: analyzer.lengths,
+        program,
+        warnings: analyzer.warnings,
+    }
+}
+
+struct Analyzer<'a> {
+    resolution: &'a Resolution,
+    /// Bodies of sequences with properties of blocks they're defined in.
+    definitions: HashMap<SymbolId, (&'a Block, Properties)>,
+    /// Values bound with `let`.
+    variables: HashMap<SymbolId, &'a Expression>,
+    lengths: HashMap<SymbolId, Length>,
+    /// Sequences, which lengths are being computed, to stop on recursion.
+    in_progress: HashSet<SymbolId>,
+    warnings: Vec<DurationWarning>,
+}
+
+impl<'a> Analyzer<'a> {
+    /// Finds definitions of sequences and variables.
+    fn collect(&mut self, statements: &'a [Statement]) {
+        Statement::walk(statements, &mut |statement, enclosing| match statement {
+            Statement::Sequence { name, block } => {
+                if let Some(id) = self.resolution.definition(name) {
+                    let properties = enclosing
+                        .iter()
+                        .fold(Properties::default(), |properties, changes| {
+                            properties.with(changes).unwrap_or(properties)
+                        });
+
+                    self.definitions.insert(id, (block, properties));
+                }
+            }
+            Statement::Let { name, value } => {
+                if let Some(id) = self.resolution.definition(name) {
+                    self.variables.insert(id, value);
+                }
+            }
+            _ => {}
+        });
+    }
+
+    fn sequence_length(&mut self, id: SymbolId) -> Length {
+        if let Some(length) = self.lengths.get(&id) {
+            return *length;
+        }
+
+        let Some((block, properties)) = self.definitions.get(&id).cloned() else {
+            return Length::Unknown;
+        };
+        // Length of a recursive sequence can't be computed statically.
+        if !self.in_progress.insert(id) {
+            return Length::Unknown;
+        }
+
+        let length = self.block(&block.statements, &properties).end;
+
+        self.in_progress.remove(&id);
+        self.lengths.insert(id, length);
+
+        length
+    }
+
+    fn block(&mut self, statements: &[Statement], properties: &Properties) -> Timing {
+        let mut timing = Timing::START;
+        // Sequences started at the current cursor position.
+        let mut started = Vec::new();
+
+        for statement in statements {
+            let cursor = timing.cursor;
+
+            match statement.unattributed() {
+                Statement::Wait { expression } => timing.advance(self.wait_length(expression)),
+                Statement::Play {
+                    expression,
+                    parallel,
+                } => match self.sound(expression) {
+                    Sound::Sequence { name, length } => {
+                        started.push((name, length));
+
+                        if *parallel {
+                            timing.sound(length);
+                        } else {
+                            timing.advance(length);
+                        }
+                    }
+                    Sound::Note => timing.sound(Length::Exact(properties.length)),
+                    Sound::Sample => {}
+                    Sound::Unknown if *parallel => timing.sound(Length::Unknown),
+                    Sound::Unknown => timing.advance(Length::Unknown),
+                },
+                Statement::With {
+                    properties: changes,
+                    block,
+                } => {
+                    let nested = properties
+                        .with(changes)
+                        .unwrap_or_else(|_| properties.clone());
+
+                    timing.then(self.block(&block.statements, &nested));
+                }
+                Statement::Repeat { count, block } => {
+                    let count = match count {
+                        // Negative counts fail when the program is run.
+                        Some(count) => match literal_rational(count) {
+                            Some(count) if count.is_integer() && count >= Rational::ZERO => {
+                                Some(count.numerator())
+                            }
+                            _ => {
+                                timing.advance(Length::Unknown);
+                                continue;
+                            }
+                        },
+                        None => None,
+                    };
+
+                    timing.then(self.block(&block.statements, properties).repeat(count));
+                }
+                // Patterns of a poly block are fitted into the same span.
+                Statement::Poly { .. } => timing.advance(Length::Exact(DEFAULT_POLY_SPAN)),
+                Statement::Sequence { .. }
+                | Statement::Let { .. }
+                | Statement::Tempo { .. }
+                | Statement::Tuning { .. }
+                | Statement::Expression(_)
+                | Statement::Attributed { .. } => {}
+            }
+
+            if timing.cursor != cursor {
+                self.check_channels(&started);
+                started.clear();
+            }
+        }
+
+        self.check_channels(&started);
+
+        timing
+    }
+
+    fn wait_length(&self, expression: &Expression) -> Length {
+        if let Expression::Identifier(identifier) = expression {
+            return match self.variable(identifier) {
+                Some(value) => self.wait_length(value),
+                None => Length::Unknown,
+            };
+        }
+
+        // Plain numbers are beats, durations in seconds depend on tempo.
+        literal_rational(expression).map_or(Length::Unknown, Length::Exact)
+    }
+
+    fn variable(&self, identifier: &Identifier) -> Option<&'a Expression> {
+        self.variables
+            .get(&self.resolution.symbol_of(identifier)?)
+            .copied()
+    }
+
+    fn sound(&mut self, expression: &Expression) -> Sound {
+        match expression {
+            Expression::Identifier(identifier) => match self.resolution.target(identifier) {
+                Some(Target::Symbol(id)) => match self.resolution.symbol(id).kind {
+                    SymbolKind::Sequence => Sound::Sequence {
+                        name: *identifier,
+                        length: self.sequence_length(id),
+                    },
+                    SymbolKind::Let => match self.variable(identifier) {
+                        Some(value) => self.sound(value),
+                        None => Sound::Unknown,
+                    },
+                },
+                Some(Target::Builtin) => {
+                    let name = identifier.name().resolve();
+
+                    match parse_name(&name) {
+                        Some(Name::Note(_) | Name::Chord(_)) => Sound::Note,
+                        _ => Sound::Unknown,
+                    }
+                }
+                None => Sound::Unknown,
+            },
+            Expression::Degree { .. } => Sound::Note,
+            Expression::List { expressions, .. } => {
+                let all_notes = expressions
+                    .iter()
+                    .all(|expression| matches!(self.sound(expression), Sound::Note));
+
+                if all_notes {
+                    Sound::Note
+                } else {
+                    Sound::Unknown
+                }
+            }
+            // Transposed notes: `c4 + 12`.
+            Expression::Binary { operator, left, .. }
+                if matches!(
+                    operator.kind,
+                    BinaryOperatorKind::Plus | BinaryOperatorKind::Minus
+                ) =>
+            {
+                match self.sound(left) {
+                    Sound::Note => Sound::Note,
+                    _ => Sound::Unknown,
+                }
+            }
+            Expression::LoadSample { .. } => Sound::Sample,
+            _ => Sound::Unknown,
+        }
+    }
+
+    /// Warns about sequences started together, which end at different
+    /// times: loops built this way drift apart when repeated.
+    fn check_channels(&mut self, started: &[(Identifier, Length)]) {
+        let mut exact = started.iter().filter_map(|(name, length)| match length {
+            Length::Exact(length) => Some((*name, *length)),
+            _ => None,
+        });
+
+        let Some((first, first_length)) = exact.next() else {
+            return;
+        };
+
+        for (name, length) in exact {
+            if length != first_length {
+                self.warnings.push(DurationWarning::MismatchedChannels {
+                    first,
+                    first_length,
+                    name,
+                    length,
+                });
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DurationWarning {
+    MismatchedChannels {
+        first: Identifier,
+        first_length: Rational,
+        name: Identifier,
+        length: Rational,
+    },
+}
+
+impl Spanned for DurationWarning {
+    fn span(&self) -> Span {
+        match self {
+            Self::MismatchedChannels { name, .. } => name.span(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::resolve::resolve,
+        rational::Rational,
+        syntax::{parse::parse, scan::scan},
+    };
+
+    use super::{analyze, DurationWarning, Durations, Length};
+
+    fn durations(source: &str) -> (Vec<(String, Length)>, Durations) {
+        let statements = parse(scan(source).unwrap()).unwrap();
+        let resolution = resolve(&statements);
+        let durations = analyze(&statements, &resolution);
+
+        let lengths = resolution
+            .symbols()
+            .filter_map(|(id, symbol)| {
+                Some((symbol.name.name().resolve(), durations.sequence(id)?))
+            })
+            .collect();
+
+        (lengths, durations)
+    }
+
+    fn beats(numerator: i64, denominator: i64) -> Length {
+        Length::Exact(Rational::new(numerator, denominator))
+    }
+
+    #[test]
+    fn waits_and_notes() {
+        let (lengths, _) = durations(
+            "
+            sequence a {
+                play c4
+                wait 1/2
+                play e4
+            }
+            sequence b {
+                with length: 1/8 {
+                    play [c4, e4]
+                    wait 1
+                }
+                play a
+            }
+            ",
+        );
+
+        assert_eq!(
+            lengths,
+            [("a".to_owned(), beats(3, 2)), ("b".to_owned(), beats(5, 2))]
+        );
+    }
+
+    #[test]
+    fn repeats() {
+        let (lengths, durations) = durations(
+            "
+            sequence melody {
+                repeat 4 {
+                    play cmaj
+                    wait 1
+                    play g7
+                }
+            }
+            sequence drone {
+                repeat {
+                    play c2
+                    wait 4
+                }
+            }
+            sequence swell {
+                wait 250ms
+            }
+            sequence invalid {
+                repeat -2 {
+                    wait 1
+                }
+            }
+            play melody
+            ",
+        );
+
+        assert_eq!(
+            lengths,
+            [
+                ("melody".to_owned(), beats(5, 1)),
+                ("drone".to_owned(), Length::Infinite),
+                ("swell".to_owned(), Length::Unknown),
+                ("invalid".to_owned(), Length::Unknown),
+            ]
+        );
+        assert_eq!(durations.program(), beats(5, 1));
+    }
+
+    #[test]
+    fn mismatched_channels() {
+        let (_, durations) = durations(
+            "
+            sequence melody {
+                play c4
+                wait 3
+            }
+            sequence drums {
+                wait 4
+            }
+            play! melody
+            play drums
+            play! melody
+            ",
+        );
+
+        assert!(matches!(
+            durations.warnings.as_slice(),
+            [DurationWarning::MismatchedChannels { length, .. }] if *length == Rational::integer(4)
+        ));
+    }
+}
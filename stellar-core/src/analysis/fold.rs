@@ -0,0 +1,462 @@
+//! Constant folding: evaluates pure expressions before the program is
+//! played, so that the scheduler doesn't do arithmetic on the audio path,
+//! and errors like division by zero are reported ahead of time.
+//!
+//! Expressions are pure if they only use literals, notes, chords and
+//! `let` variables holding constants. Calls and samples are left to the
+//! runtime, and so are scale degrees, which depend on the enclosing key.
+
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    rational::{Rational, MAX_DENOMINATOR},
+    syntax::{
+        ast::{BinaryOperatorKind, Expression, PrefixOperatorKind, Statement},
+        location::{Span, Spanned},
+        string_id::StringId,
+        token::{Identifier, Unit},
+    },
+    theory::{chord::Chord, note::Note, parse_name, Name},
+};
+
+use super::resolve::{Resolution, SymbolId, SymbolKind, Target};
+
+/// Value of a pure expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Number(Rational),
+    /// Time in seconds.
+    Duration(Rational),
+    String(StringId),
+    Bool(bool),
+    Note(Note),
+    Chord(Chord),
+    List(Vec<Constant>),
+}
+
+impl Display for Constant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "{number}"),
+            Self::Duration(seconds) => write!(f, "{seconds}s"),
+            Self::String(string) => write!(f, "{:?}", string.resolve()),
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Note(note) => write!(f, "{note}"),
+            Self::Chord(chord) => write!(f, "{chord}"),
+            Self::List(elements) => {
+                write!(f, "[")?;
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// Folded expressions of a program.
+#[derive(Debug, Default)]
+pub struct Constants {
+    /// Values keyed by start and end index of their expressions.
+    values: HashMap<(u32, u32), Constant>,
+    variables: HashMap<SymbolId, Constant>,
+    pub errors: Vec<FoldError>,
+}
+
+impl Constants {
+    /// Returns the value of an expression, if it is pure.
+    pub fn get(&self, expression: &Expression) -> Option<&Constant> {
+        self.values.get(&key(expression.span()))
+    }
+
+    /// Returns the value of a `let` variable, if it is a constant.
+    pub fn variable(&self, id: SymbolId) -> Option<&Constant> {
+        self.variables.get(&id)
+    }
+}
+
+/// Folds every pure expression of a resolved program.
+pub fn fold(statements: &[Statement], resolution: &Resolution) -> Constants {
+    let mut folder = Folder {
+        resolution,
+        constants: Constants::default(),
+    };
+
+    folder.fold_statements(statements);
+
+    folder.constants
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FoldError {
+    DivisionByZero {
+        /// Span of the whole division.
+        span: Span,
+        divisor: Span,
+    },
+}
+
+impl Spanned for FoldError {
+    fn span(&self) -> Span {
+        match self {
+            Self::DivisionByZero { span, .. } => *span,
+        }
+    }
+}
+
+fn key(span: Span) -> (u32, u32) {
+    (span.start().index(), span.end().index())
+}
+
+struct Folder<'r> {
+    resolution: &'r Resolution,
+    constants: Constants,
+}
+
+impl Folder<'_> {
+    fn fold_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.fold_statement(statement);
+        }
+    }
+
+    fn fold_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Wait { expression }
+            | Statement::Play { expression, .. }
+            | Statement::Expression(expression) => {
+                self.fold_expression(expression);
+            }
+            Statement::Let { name, value } => {
+                let constant = self.fold_expression(value);
+
+                // Variables changed with `=` are only known at runtime.
+                let id = self
+                    .resolution
+                    .definition(name)
+                    .filter(|id| !self.resolution.is_assigned(*id));
+
+                if let (Some(constant), Some(id)) = (constant, id) {
+                    self.constants.variables.insert(id, constant);
+                }
+            }
+            Statement::Sequence { block, .. } | Statement::Repeat { count: None, block } => {
+                self.fold_statements(&block.statements)
+            }
+            Statement::Repeat {
+                count: Some(count),
+                block,
+            } => {
+                self.fold_expression(count);
+                self.fold_statements(&block.statements);
+            }
+            Statement::With { properties, block } => {
+                for property in properties {
+                    self.fold_expression(&property.value);
+                }
+
+                self.fold_statements(&block.statements);
+            }
+            Statement::Poly { arguments, block } => {
+                for argument in arguments {
+                    self.fold_expression(&argument.value);
+                }
+
+                self.fold_statements(&block.statements);
+            }
+            Statement::Tempo { bpm, ramp } => {
+                self.fold_expression(bpm);
+
+                if let Some(ramp) = ramp {
+                    self.fold_expression(&ramp.target);
+                    self.fold_expression(&ramp.length);
+                }
+            }
+            Statement::Tuning { tuning } => {
+                self.fold_expression(tuning);
+            }
+            Statement::Attributed { statement, .. } => self.fold_statement(statement),
+        }
+    }
+
+    /// Folds an expression and its subexpressions, and records the value
+    /// of every pure one.
+    fn fold_expression(&mut self, expression: &Expression) -> Option<Constant> {
+        let constant = self.evaluate(expression)?;
+
+        self.constants
+            .values
+            .insert(key(expression.span()), constant.clone());
+
+        Some(constant)
+    }
+
+    fn evaluate(&mut self, expression: &Expression) -> Option<Constant> {
+        match expression {
+            Expression::Integer { value, .. } => Some(Constant::Number(Rational::integer(*value))),
+            Expression::Float { value, .. } => Some(Constant::Number(Rational::approximate(
+                *value,
+                MAX_DENOMINATOR,
+            ))),
+            Expression::Quantity { value, unit, .. } => {
+                let value = Rational::approximate(*value, MAX_DENOMINATOR);
+
+                Some(match unit {
+                    Unit::Percent => Constant::Number(value / Rational::integer(100)),
+                    Unit::Milliseconds => Constant::Duration(value / Rational::integer(1000)),
+                    Unit::Seconds => Constant::Duration(value),
+                })
+            }
+            Expression::String { value, .. } => Some(Constant::String(*value)),
+            Expression::Bool { value, .. } => Some(Constant::Bool(*value)),
+            Expression::Identifier(identifier) => self.evaluate_identifier(identifier),
+            Expression::List { expressions, .. } => {
+                // Every element is folded, even if some of them are not
+                // pure.
+                let elements: Vec<_> = expressions
+                    .iter()
+                    .map(|expression| self.fold_expression(expression))
+                    .collect();
+
+                elements
+                    .into_iter()
+                    .collect::<Option<_>>()
+                    .map(Constant::List)
+            }
+            Expression::Prefix { operator, operand } => {
+                match (operator.kind, self.fold_expression(operand)?) {
+                    (PrefixOperatorKind::Minus, Constant::Number(number)) => {
+                        number.checked_neg().map(Constant::Number)
+                    }
+                    (PrefixOperatorKind::Minus, Constant::Duration(seconds)) => {
+                        seconds.checked_neg().map(Constant::Duration)
+                    }
+                    (PrefixOperatorKind::Exclamation, Constant::Bool(value)) => {
+                        Some(Constant::Bool(!value))
+                    }
+                    _ => None,
+                }
+            }
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let left_value = self.fold_expression(left);
+                let right_value = self.fold_expression(right);
+
+                self.evaluate_binary(operator.kind, left_value?, right_value?, expression, right)
+            }
+            Expression::LoadSample { sample, .. } => {
+                self.fold_expression(sample);
+                None
+            }
+            Expression::Call {
+                callee, arguments, ..
+            } => {
+                self.fold_expression(callee);
+
+                for argument in arguments {
+                    self.fold_expression(&argument.value);
+                }
+
+                None
+            }
+            Expression::Index { target, index, .. } => {
+                self.fold_expression(target);
+                self.fold_expression(index);
+
+                None
+            }
+            Expression::Degree { .. } => None,
+        }
+    }
+
+    fn evaluate_identifier(&self, identifier: &Identifier) -> Option<Constant> {
+        match self.resolution.target(identifier)? {
+            Target::Symbol(id) => match self.resolution.symbol(id).kind {
+                SymbolKind::Let => self.constants.variables.get(&id).cloned(),
+                SymbolKind::Sequence => None,
+            },
+            Target::Builtin => {
+                let name = identifier.name().resolve();
+
+                match parse_name(&name)? {
+                    Name::Note(note) => Some(Constant::Note(note)),
+                    Name::Chord(chord) => Some(Constant::Chord(chord)),
+                    Name::Key(_) => None,
+                }
+            }
+        }
+    }
+
+    fn evaluate_binary(
+        &mut self,
+        operator: BinaryOperatorKind,
+        left: Constant,
+        right: Constant,
+        expression: &Expression,
+        divisor: &Expression,
+    ) -> Option<Constant> {
+        use BinaryOperatorKind::{Minus, Plus, Slash, Star};
+
+        let divides_by_zero = match (&left, &right) {
+            (Constant::Number(_) | Constant::Duration(_), Constant::Number(divisor)) => {
+                operator == Slash && divisor.is_zero()
+            }
+            _ => false,
+        };
+        if divides_by_zero {
+            self.constants.errors.push(FoldError::DivisionByZero {
+                span: expression.span(),
+                divisor: divisor.span(),
+            });
+
+            return None;
+        }
+
+        // Results, which overflow, are left to the runtime to report.
+        match (operator, left, right) {
+            (Plus, Constant::Number(left), Constant::Number(right)) => {
+                left.checked_add(right).map(Constant::Number)
+            }
+            (Minus, Constant::Number(left), Constant::Number(right)) => {
+                left.checked_sub(right).map(Constant::Number)
+            }
+            (Star, Constant::Number(left), Constant::Number(right)) => {
+                left.checked_mul(right).map(Constant::Number)
+            }
+            (Slash, Constant::Number(left), Constant::Number(right)) => {
+                left.checked_div(right).map(Constant::Number)
+            }
+            (Plus, Constant::Duration(left), Constant::Duration(right)) => {
+                left.checked_add(right).map(Constant::Duration)
+            }
+            (Minus, Constant::Duration(left), Constant::Duration(right)) => {
+                left.checked_sub(right).map(Constant::Duration)
+            }
+            (Star, Constant::Duration(seconds), Constant::Number(factor))
+            | (Star, Constant::Number(factor), Constant::Duration(seconds)) => {
+                seconds.checked_mul(factor).map(Constant::Duration)
+            }
+            (Slash, Constant::Duration(seconds), Constant::Number(divisor)) => {
+                seconds.checked_div(divisor).map(Constant::Duration)
+            }
+            // Transposition by a number of semitones.
+            (Plus | Minus, Constant::Note(note), Constant::Number(semitones)) => {
+                let semitones = transposition(operator, semitones)?;
+
+                Some(Constant::Note(note.transpose(semitones)?))
+            }
+            (Plus | Minus, Constant::Chord(chord), Constant::Number(semitones)) => {
+                let semitones = transposition(operator, semitones)?;
+
+                Some(Constant::Chord(Chord::new(
+                    chord.root.transpose(semitones)?,
+                    chord.quality,
+                )))
+            }
+            (Plus, Constant::String(left), Constant::String(right)) => Some(Constant::String(
+                StringId::new(left.resolve() + &right.resolve()),
+            )),
+            (Plus, Constant::List(mut left), Constant::List(right)) => {
+                left.extend(right);
+                Some(Constant::List(left))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns the number of semitones a note is transposed by, or `None` if
+/// it is not a whole number.
+fn transposition(operator: BinaryOperatorKind, semitones: Rational) -> Option<i32> {
+    if !semitones.is_integer() {
+        return None;
+    }
+
+    let semitones = i32::try_from(semitones.numerator()).ok()?;
+
+    match operator {
+        BinaryOperatorKind::Minus => Some(-semitones),
+        _ => Some(semitones),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_debug_snapshot;
+
+    use crate::{
+        analysis::resolve::resolve,
+        syntax::{ast::Statement, parse::parse, scan::scan},
+    };
+
+    use super::fold;
+
+    /// Returns values of `let` statements of a program.
+    fn constants(source: &str) -> Vec<String> {
+        let statements = parse(scan(source).unwrap()).unwrap();
+        let constants = fold(&statements, &resolve(&statements));
+
+        statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Let { value, .. } => Some(
+                    constants
+                        .get(value)
+                        .map_or("?".to_owned(), |constant| constant.to_string()),
+                ),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn folds() {
+        let source = "
+            let a = 1 + 2 * 3
+            let b = a / 4 - 1/2
+            let c = [c4 + 12, cmaj7 - 2, a]
+            let d = 250ms * 2 + 1.5s
+            let e = \"kick\" + \".wav\"
+            let f = !false
+            let g = [c4] + [e4, g4]
+            let h = load_sample e
+            let i = [a, ^1]
+            let j = -60%
+            let k = a
+            k = 1
+            let l = k
+        ";
+
+        assert_eq!(
+            constants(source),
+            [
+                "7",
+                "5/4",
+                "[c5, asmaj7, 7]",
+                "2s",
+                "\"kick.wav\"",
+                "true",
+                "[c4, e4, g4]",
+                "?",
+                "?",
+                "-3/5",
+                "7",
+                "?",
+            ]
+        );
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let source = "let a = 1 - 1\nwait 1 / a\nplay [c4 + 12 / 0]\nlet b = 250ms / (2 - 2)";
+        let statements = parse(scan(source).unwrap()).unwrap();
+
+        assert_debug_snapshot!(fold(&statements, &resolve(&statements)).errors);
+    }
+}
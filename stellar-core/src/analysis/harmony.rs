@@ -0,0 +1,197 @@
+//! Voice-leading and key checks over the [timeline](super::timeline) of a
+//! program: parallel fifths and octaves, crossing voices and notes, which
+//! don't belong to the key of their block.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    rational::Rational,
+    syntax::location::{Span, Spanned},
+    theory::{key::Key, note::Note},
+};
+
+use super::timeline::{TimedNote, Voice};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HarmonyWarning {
+    /// Two voices a fifth apart moving in the same direction to another
+    /// fifth.
+    ParallelFifths {
+        span: Span,
+        previous: Span,
+    },
+    /// Two voices an octave or unison apart moving in the same direction
+    /// to another octave or unison.
+    ParallelOctaves {
+        span: Span,
+        previous: Span,
+    },
+    /// Voice moving above a voice, which was above it before, or below a
+    /// voice, which was below it before.
+    VoiceCrossing {
+        span: Span,
+    },
+    OutOfKey {
+        note: Note,
+        key: Key,
+        span: Span,
+    },
+}
+
+impl Spanned for HarmonyWarning {
+    fn span(&self) -> Span {
+        match self {
+            Self::ParallelFifths { span, .. }
+            | Self::ParallelOctaves { span, .. }
+            | Self::VoiceCrossing { span }
+            | Self::OutOfKey { span, .. } => *span,
+        }
+    }
+}
+
+/// Checks notes ordered by their start. Every warning is reported once,
+/// even if the statement producing it is played many times.
+pub fn check_harmony(notes: &[TimedNote]) -> Vec<HarmonyWarning> {
+    let mut warnings = Vec::new();
+    let mut warn = |warning| {
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    };
+
+    for note in notes {
+        if let Some(key) = note.key {
+            if !key.contains(note.note.pitch_class()) {
+                warn(HarmonyWarning::OutOfKey {
+                    note: note.note,
+                    key,
+                    span: note.span,
+                });
+            }
+        }
+    }
+
+    let mut onsets: Vec<_> = notes.iter().map(|note| note.start).collect();
+    onsets.dedup();
+
+    let slices: Vec<_> = onsets
+        .into_iter()
+        .map(|onset| slice(notes, onset))
+        .collect();
+
+    for pair in slices.windows(2) {
+        let (before, after) = (&pair[0], &pair[1]);
+
+        for (lower, (lower_before, lower_after)) in moves(before, after) {
+            for (upper, (upper_before, upper_after)) in moves(before, after) {
+                if lower >= upper {
+                    continue;
+                }
+
+                let previous = interval(lower_before, upper_before);
+                let next = interval(lower_after, upper_after);
+
+                if previous.signum() * next.signum() < 0 {
+                    let moved = match lower_before.note == lower_after.note {
+                        true => upper_after,
+                        false => lower_after,
+                    };
+                    warn(HarmonyWarning::VoiceCrossing { span: moved.span });
+                }
+
+                let lower_motion = motion(lower_before, lower_after);
+                let upper_motion = motion(upper_before, upper_after);
+                if lower_motion == 0 || lower_motion != upper_motion {
+                    continue;
+                }
+
+                let span = upper_after.span;
+                let previous_span = upper_before.span;
+
+                match (previous.abs() % 12, next.abs() % 12) {
+                    (7, 7) => warn(HarmonyWarning::ParallelFifths {
+                        span,
+                        previous: previous_span,
+                    }),
+                    (0, 0) => warn(HarmonyWarning::ParallelOctaves {
+                        span,
+                        previous: previous_span,
+                    }),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    warnings.sort_by_key(|warning| warning.span().start().index());
+    warnings
+}
+
+/// Notes sounding at a given beat, the latest one of every voice.
+fn slice(notes: &[TimedNote], beat: Rational) -> BTreeMap<Voice, &TimedNote> {
+    notes
+        .iter()
+        .take_while(|note| note.start <= beat)
+        .filter(|note| note.end() > beat)
+        .map(|note| (note.voice, note))
+        .collect()
+}
+
+/// Notes of every voice sounding in both slices.
+fn moves<'a>(
+    before: &'a BTreeMap<Voice, &'a TimedNote>,
+    after: &'a BTreeMap<Voice, &'a TimedNote>,
+) -> impl Iterator<Item = (Voice, (&'a TimedNote, &'a TimedNote))> {
+    before
+        .iter()
+        .filter_map(|(voice, note)| Some((*voice, (*note, *after.get(voice)?))))
+}
+
+/// Semitones from the lower voice to the upper one.
+fn interval(lower: &TimedNote, upper: &TimedNote) -> i32 {
+    upper.note.midi() - lower.note.midi()
+}
+
+fn motion(before: &TimedNote, after: &TimedNote) -> i32 {
+    (after.note.midi() - before.note.midi()).signum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::{resolve::resolve, timeline::timeline},
+        ctx::interpreter::{interpret, Options},
+        syntax::{parse::parse, scan::scan},
+        test_snapshots,
+    };
+
+    use super::check_harmony;
+
+    fn render(source: &str) -> String {
+        let statements = parse(scan(source).unwrap()).unwrap();
+        let ir = interpret(&statements, &resolve(&statements), &Options::default()).unwrap();
+        let notes = timeline(&ir);
+
+        format!("{:#?}", check_harmony(&notes))
+    }
+
+    test_snapshots!(
+        (
+            parallel_fifths,
+            "play [c4, g4]\nwait 1\nplay [d4, a4]\nwait 1\nplay [e4, g4]"
+        ),
+        (parallel_octaves, "play [c4, c5]\nwait 1\nplay [d4, d5]"),
+        (
+            computed_notes,
+            "let fifth = [c4, g4]\nwait 1 + 1\nplay fifth\nwait 2 - 1\nplay transpose(fifth, 2)"
+        ),
+        (
+            voice_crossing,
+            "sequence high {\n play e4\n wait 1\n play c4\n}\nplay! high\nplay d4\nwait 1\nplay d4"
+        ),
+        (
+            out_of_key,
+            "with key: d_minor {\n play [^1, ^3, ^5]\n wait 1\n play b4\n wait 1\n play bb4\n}"
+        ),
+    );
+}
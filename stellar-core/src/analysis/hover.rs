@@ -0,0 +1,138 @@
+//! Hover text of editors: describes the sound of the `play` statement
+//! under the cursor, naming the chords it plays.
+//!
+//! Notes are read from the events the program plays, so that variables and
+//! functions computing chords are described too.
+
+use crate::{
+    ctx::interpreter::{interpret, Options},
+    ir::EventKind,
+    syntax::{ast::Statement, location::Span},
+    theory::{note::Note, recognize::recognize},
+};
+
+use super::resolve::Resolution;
+
+/// Largest number of different sounds described for a single statement.
+const MAX_SOUNDS: usize = 8;
+
+/// Returns the hover text of a location, or `None` if no statement
+/// containing it plays notes, or the program can't be run. Every different
+/// sound of the statement is written on its own line: `e3 g3 c4: cmaj/e`.
+pub fn hover(
+    statements: &[Statement],
+    resolution: &Resolution,
+    line: u32,
+    column: u32,
+) -> Option<String> {
+    let contains = |span: &Span| {
+        let (start, end) = (span.start(), span.end());
+
+        (start.line(), start.column()) <= (line, column)
+            && (line, column) < (end.line(), end.column())
+    };
+
+    let ir = interpret(statements, resolution, &Options::default()).ok()?;
+    let notes: Vec<_> = ir
+        .channels
+        .iter()
+        .enumerate()
+        .flat_map(|(index, channel)| {
+            channel
+                .events
+                .iter()
+                .filter_map(move |event| match event.kind {
+                    EventKind::NoteOn { note, .. } => Some(((index, event.beat), note, event.span)),
+                    _ => None,
+                })
+        })
+        .collect();
+
+    // Statements of a sequence are inside of its definition.
+    let span = notes
+        .iter()
+        .map(|(_, _, span)| *span)
+        .filter(contains)
+        .min_by_key(Span::len)?;
+
+    // Notes starting together on a channel form a sound.
+    let mut sounds: Vec<(_, Vec<Note>)> = Vec::new();
+    for (start, note, _) in notes.iter().filter(|(_, _, note_span)| *note_span == span) {
+        match sounds.last_mut() {
+            Some((last, sound)) if last == start => sound.push(*note),
+            _ => sounds.push((*start, vec![*note])),
+        }
+    }
+
+    let mut distinct: Vec<Vec<Note>> = Vec::new();
+    for (_, mut sound) in sounds {
+        sound.sort();
+        if !distinct.contains(&sound) {
+            distinct.push(sound);
+        }
+    }
+
+    let lines: Vec<_> = distinct
+        .iter()
+        .take(MAX_SOUNDS)
+        .map(|sound| {
+            let notes: Vec<_> = sound.iter().map(Note::to_string).collect();
+            let chords: Vec<_> = recognize(sound).iter().map(ToString::to_string).collect();
+
+            match chords.is_empty() {
+                true => notes.join(" "),
+                false => format!("{}: {}", notes.join(" "), chords.join(" or ")),
+            }
+        })
+        .collect();
+
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::resolve::resolve,
+        syntax::{parse::parse, scan::scan},
+    };
+
+    use super::hover;
+
+    fn text(source: &str, line: u32, column: u32) -> Option<String> {
+        let statements = parse(scan(source).unwrap()).unwrap();
+
+        hover(&statements, &resolve(&statements), line, column)
+    }
+
+    #[test]
+    fn chords() {
+        let source = "sequence a {\n play [e3, g3, c4]\n wait 1\n}\nplay a\nplay [c4, e4, g4, a4]\nwait 1\nplay c4";
+
+        assert_eq!(text(source, 2, 8), Some("e3 g3 c4: cmaj/e".to_owned()));
+        assert_eq!(
+            text(source, 6, 5),
+            Some("c4 e4 g4 a4: cmaj6 or am7/c".to_owned())
+        );
+        assert_eq!(text(source, 8, 5), Some("c4".to_owned()));
+        assert_eq!(text(source, 7, 0), None);
+    }
+
+    #[test]
+    fn arpeggios() {
+        let source = "with key: c_major, arp: up, rate: 1/16 {\n play [^1, ^3]\n wait 1\n}";
+
+        assert_eq!(text(source, 2, 7), Some("c4\ne4".to_owned()));
+    }
+
+    #[test]
+    fn computed_chords() {
+        let source = "let x = [e3, g3, c4]\nplay x\nwait 1\nplay invert(cmaj7, 1)\nwait 1\nplay prog(\"I IV\", key: c_major)";
+
+        assert_eq!(text(source, 2, 5), Some("e3 g3 c4: cmaj/e".to_owned()));
+        assert_eq!(text(source, 4, 5), Some("e4 g4 b4 c5: cmaj7/e".to_owned()));
+        assert_eq!(
+            text(source, 6, 5),
+            Some("c4 e4 f4 g4 a4 c5: fmaj9/c".to_owned())
+        );
+    }
+}
@@ -0,0 +1,10 @@
+//! Static analysis passes run over the syntax tree before execution.
+
+pub mod duration;
+pub mod fold;
+pub mod harmony;
+pub mod hover;
+pub mod recursion;
+pub mod resolve;
+pub mod timeline;
+pub mod types;
@@ -0,0 +1,456 @@
+//! Recursion analysis: finds sequences, which play themselves directly or
+//! through other sequences.
+//!
+//! A cycle, in which no time passes before the next sequence is started,
+//! would make the scheduler start sequences forever without ever moving
+//! the clock, so it is an error. Other cycles make a piece play forever,
+//! which is reported as a warning.
+
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
+
+use crate::{
+    ctx::properties::literal_rational,
+    rational::Rational,
+    syntax::{
+        ast::{Expression, Statement},
+        location::{Span, Spanned},
+        string_id::StringId,
+        token::Identifier,
+    },
+};
+
+use super::resolve::{Resolution, SymbolId, SymbolKind};
+
+/// Reference from the body of one sequence to another one.
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    from: SymbolId,
+    to: SymbolId,
+    reference: Identifier,
+    /// Whether time is guaranteed to pass before the reference is played.
+    guarded: bool,
+}
+
+/// Statement of a sequence body, which matters for the analysis.
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    /// Time passes: `wait 1`, a `poly` block.
+    Wait,
+    Reference {
+        to: SymbolId,
+        reference: Identifier,
+        /// `play name`, which waits for the sequence to end.
+        sequential: bool,
+    },
+}
+
+/// Result of the recursion analysis.
+#[derive(Debug, Default)]
+pub struct Cycles {
+    pub errors: Vec<RecursionError>,
+    pub warnings: Vec<RecursionWarning>,
+}
+
+/// Finds recursive sequences in a resolved program.
+pub fn find_cycles(statements: &[Statement], resolution: &Resolution) -> Cycles {
+    let mut collector = Collector {
+        resolution,
+        bodies: HashMap::new(),
+    };
+    collector.collect_definitions(statements);
+
+    let sequences: Vec<_> = resolution
+        .symbols()
+        .filter(|(id, symbol)| {
+            symbol.kind == SymbolKind::Sequence && collector.bodies.contains_key(id)
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    let graph = Graph::new(&sequences, &collector.bodies);
+    let mut cycles = Cycles::default();
+    let mut reported = HashSet::new();
+
+    for &sequence in &sequences {
+        if reported.contains(&sequence) {
+            continue;
+        }
+
+        if let Some(path) = graph.cycle(sequence, true) {
+            reported.extend(graph.component(sequence, true));
+            cycles.errors.push(RecursionError::ZeroDurationCycle {
+                path: graph.names(&path),
+                span: path[0].reference.span(),
+            });
+        }
+    }
+
+    for &sequence in &sequences {
+        if reported.contains(&sequence) {
+            continue;
+        }
+
+        if let Some(path) = graph.cycle(sequence, false) {
+            reported.extend(graph.component(sequence, false));
+            cycles.warnings.push(RecursionWarning::UnboundedRecursion {
+                path: graph.names(&path),
+                span: path[0].reference.span(),
+            });
+        }
+    }
+
+    cycles
+}
+
+struct Collector<'r> {
+    resolution: &'r Resolution,
+    bodies: HashMap<SymbolId, Vec<Step>>,
+}
+
+impl Collector<'_> {
+    /// Finds sequence definitions, including the nested ones.
+    fn collect_definitions(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement.unattributed() {
+                Statement::Sequence { name, block } => {
+                    let mut steps = Vec::new();
+                    self.collect_steps(&block.statements, &mut steps);
+
+                    if let Some(id) = self.resolution.definition(name) {
+                        self.bodies.insert(id, steps);
+                    }
+
+                    self.collect_definitions(&block.statements);
+                }
+                Statement::With { block, .. }
+                | Statement::Poly { block, .. }
+                | Statement::Repeat { block, .. } => self.collect_definitions(&block.statements),
+                _ => {}
+            }
+        }
+    }
+
+    /// Flattens a sequence body into the order its statements start in.
+    fn collect_steps(&self, statements: &[Statement], steps: &mut Vec<Step>) {
+        for statement in statements {
+            match statement.unattributed() {
+                Statement::Wait { expression } => {
+                    // `wait 0` doesn't move the clock.
+                    if literal_rational(expression) != Some(Rational::ZERO) {
+                        steps.push(Step::Wait);
+                    }
+                }
+                Statement::Play {
+                    expression,
+                    parallel,
+                } => self.collect_references(expression, !parallel, steps),
+                // Repeated blocks start the same way as the block itself.
+                Statement::With { block, .. } | Statement::Repeat { block, .. } => {
+                    self.collect_steps(&block.statements, steps)
+                }
+                Statement::Poly { block, .. } => {
+                    for statement in &block.statements {
+                        if let Statement::Play { expression, .. } = statement.unattributed() {
+                            self.collect_references(expression, false, steps);
+                        }
+                    }
+
+                    steps.push(Step::Wait);
+                }
+                Statement::Sequence { .. }
+                | Statement::Let { .. }
+                | Statement::Tempo { .. }
+                | Statement::Tuning { .. }
+                | Statement::Expression(_)
+                | Statement::Attributed { .. } => {}
+            }
+        }
+    }
+
+    fn collect_references(&self, expression: &Expression, sequential: bool, steps: &mut Vec<Step>) {
+        match expression {
+            Expression::Identifier(identifier) => {
+                let Some(id) = self.resolution.symbol_of(identifier) else {
+                    return;
+                };
+
+                if self.resolution.symbol(id).kind == SymbolKind::Sequence {
+                    steps.push(Step::Reference {
+                        to: id,
+                        reference: *identifier,
+                        sequential,
+                    });
+                }
+            }
+            // Transformed sequences, like `fast(2, drums)`, are played
+            // without waiting for the original to end.
+            Expression::Call { arguments, .. } => {
+                for argument in arguments {
+                    self.collect_references(&argument.value, false, steps);
+                }
+            }
+            Expression::List { expressions, .. } => {
+                for expression in expressions {
+                    self.collect_references(expression, false, steps);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Graph {
+    edges: Vec<Edge>,
+}
+
+impl Graph {
+    fn new(sequences: &[SymbolId], bodies: &HashMap<SymbolId, Vec<Step>>) -> Self {
+        let mut advances = HashMap::new();
+        let mut edges = Vec::new();
+
+        for &from in sequences {
+            let mut guarded = false;
+
+            for step in &bodies[&from] {
+                match *step {
+                    Step::Wait => guarded = true,
+                    Step::Reference {
+                        to,
+                        reference,
+                        sequential,
+                    } => {
+                        edges.push(Edge {
+                            from,
+                            to,
+                            reference,
+                            guarded,
+                        });
+
+                        if sequential {
+                            guarded |= sequence_advances(to, bodies, &mut advances);
+                        }
+                    }
+                }
+            }
+        }
+
+        Self { edges }
+    }
+
+    fn successors(&self, from: SymbolId, unguarded: bool) -> impl Iterator<Item = &Edge> {
+        self.edges
+            .iter()
+            .filter(move |edge| edge.from == from && !(unguarded && edge.guarded))
+    }
+
+    /// Returns sequences reachable from a given one.
+    fn reachable(&self, from: SymbolId, unguarded: bool) -> HashSet<SymbolId> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![from];
+
+        while let Some(node) = stack.pop() {
+            for edge in self.successors(node, unguarded) {
+                if visited.insert(edge.to) {
+                    stack.push(edge.to);
+                }
+            }
+        }
+
+        visited
+    }
+
+    /// Returns sequences lying on cycles going through a given one.
+    fn component(&self, sequence: SymbolId, unguarded: bool) -> HashSet<SymbolId> {
+        self.reachable(sequence, unguarded)
+            .into_iter()
+            .filter(|other| self.reachable(*other, unguarded).contains(&sequence))
+            .collect()
+    }
+
+    /// Returns the shortest cycle going through a given sequence, if any.
+    /// With `unguarded` only references played before any time passes are
+    /// followed.
+    fn cycle(&self, start: SymbolId, unguarded: bool) -> Option<Vec<Edge>> {
+        let mut previous: HashMap<SymbolId, Edge> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(node) = queue.pop_front() {
+            for edge in self.successors(node, unguarded) {
+                if edge.to == start {
+                    let mut path = vec![*edge];
+                    let mut node = edge.from;
+
+                    while node != start {
+                        let edge = previous[&node];
+                        path.push(edge);
+                        node = edge.from;
+                    }
+
+                    path.reverse();
+                    return Some(path);
+                }
+
+                if let Entry::Vacant(entry) = previous.entry(edge.to) {
+                    entry.insert(*edge);
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns names of sequences along a cycle: `a -> b -> a`.
+    fn names(&self, path: &[Edge]) -> Vec<StringId> {
+        path.iter().map(|edge| edge.reference.name()).fold(
+            vec![path[path.len() - 1].reference.name()],
+            |mut names, name| {
+                names.push(name);
+                names
+            },
+        )
+    }
+}
+
+/// Returns `true` if playing a sequence to the end always takes time.
+fn sequence_advances(
+    sequence: SymbolId,
+    bodies: &HashMap<SymbolId, Vec<Step>>,
+    memo: &mut HashMap<SymbolId, bool>,
+) -> bool {
+    if let Some(advances) = memo.get(&sequence) {
+        return *advances;
+    }
+
+    // Assume recursive references don't advance, until proven otherwise.
+    memo.insert(sequence, false);
+
+    let advances = bodies.get(&sequence).is_some_and(|steps| {
+        steps.iter().any(|step| match *step {
+            Step::Wait => true,
+            Step::Reference { to, sequential, .. } => {
+                sequential && sequence_advances(to, bodies, memo)
+            }
+        })
+    });
+
+    memo.insert(sequence, advances);
+    advances
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RecursionError {
+    /// Sequences start each other without any time passing in between:
+    /// `sequence a { play b }`, `sequence b { play a }`.
+    ZeroDurationCycle { path: Vec<StringId>, span: Span },
+}
+
+impl Spanned for RecursionError {
+    fn span(&self) -> Span {
+        match self {
+            Self::ZeroDurationCycle { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RecursionWarning {
+    /// Sequence keeps playing itself forever.
+    UnboundedRecursion { path: Vec<StringId>, span: Span },
+}
+
+impl Spanned for RecursionWarning {
+    fn span(&self) -> Span {
+        match self {
+            Self::UnboundedRecursion { span, .. } => *span,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::resolve::resolve,
+        syntax::{parse::parse, scan::scan},
+    };
+
+    use super::{find_cycles, Cycles, RecursionError, RecursionWarning};
+
+    fn cycles(source: &str) -> Cycles {
+        let statements = parse(scan(source).unwrap()).unwrap();
+        find_cycles(&statements, &resolve(&statements))
+    }
+
+    fn path(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn resolve_path(path: &[crate::syntax::string_id::StringId]) -> Vec<String> {
+        path.iter().map(|name| name.resolve()).collect()
+    }
+
+    #[test]
+    fn zero_duration_cycle() {
+        let cycles = cycles(
+            "
+            sequence a {
+                play c4
+                play b
+            }
+            sequence b {
+                with synth: dsaw {
+                    play! a
+                }
+                wait 1
+            }
+            ",
+        );
+
+        let [RecursionError::ZeroDurationCycle { path: found, .. }] = cycles.errors.as_slice()
+        else {
+            panic!("expected a single error, got {:?}", cycles.errors);
+        };
+        assert_eq!(resolve_path(found), path(&["a", "b", "a"]));
+        assert!(cycles.warnings.is_empty());
+    }
+
+    #[test]
+    fn unbounded_recursion() {
+        let cycles = cycles(
+            "
+            sequence intro {
+                wait 1/2
+            }
+            sequence loop {
+                play c4
+                play intro
+                play loop
+            }
+            sequence stuck {
+                wait 0
+                play stuck
+            }
+            ",
+        );
+
+        let [RecursionError::ZeroDurationCycle { path: stuck, .. }] = cycles.errors.as_slice()
+        else {
+            panic!("expected a single error, got {:?}", cycles.errors);
+        };
+        assert_eq!(resolve_path(stuck), path(&["stuck", "stuck"]));
+
+        let [RecursionWarning::UnboundedRecursion { path: looped, .. }] =
+            cycles.warnings.as_slice()
+        else {
+            panic!("expected a single warning, got {:?}", cycles.warnings);
+        };
+        assert_eq!(resolve_path(looped), path(&["loop", "loop"]));
+    }
+
+    #[test]
+    fn no_cycles() {
+        let cycles = cycles("sequence a {\n play b\n}\nsequence b {\n play c4\n}\nplay a");
+
+        assert!(cycles.errors.is_empty() && cycles.warnings.is_empty());
+    }
+}
@@ -0,0 +1,489 @@
+//! Name resolution: connects every identifier in the program with the
+//! `let` or `sequence` statement defining it.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ctx::{
+        builtins::is_builtin_function,
+        properties::{property_kind, PropertyKind},
+    },
+    syntax::{
+        ast::{BinaryOperatorKind, Block, Expression, Statement},
+        location::{Span, Spanned},
+        string_id::StringId,
+        token::Identifier,
+    },
+    theory::parse_name,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScopeId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SymbolId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    /// Top level of the program.
+    Global,
+    /// Body of a `sequence` statement.
+    Sequence(StringId),
+    /// Body of a `with` statement.
+    With,
+    /// Body of a `poly` statement.
+    Poly,
+    /// Body of a `repeat` statement.
+    Repeat,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    pub symbols: Vec<SymbolId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Let,
+    Sequence,
+}
+
+/// Represents a name defined with `let` or `sequence` statement.
+#[derive(Debug, PartialEq)]
+pub struct Symbol {
+    pub name: Identifier,
+    pub kind: SymbolKind,
+    pub scope: ScopeId,
+    /// Index of the byte, after which the symbol can be used. Sequences can
+    /// be used anywhere in their scope, variables only after their `let`
+    /// statement.
+    visible_from: u32,
+}
+
+/// Entity, which an identifier refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Symbol(SymbolId),
+    /// Builtin function, note, chord or key name.
+    Builtin,
+}
+
+/// Result of name resolution: the scope tree, defined symbols and targets
+/// of all identifiers used in the program.
+#[derive(Debug, Default)]
+pub struct Resolution {
+    scopes: Vec<Scope>,
+    symbols: Vec<Symbol>,
+    /// Targets of identifiers keyed by the index of their first byte.
+    references: HashMap<u32, Target>,
+    /// Variables changed with `=` after their definition.
+    assigned: HashSet<SymbolId>,
+    pub errors: Vec<ResolveError>,
+}
+
+impl Resolution {
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.0]
+    }
+
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id.0]
+    }
+
+    pub fn scopes(&self) -> impl Iterator<Item = (ScopeId, &Scope)> {
+        self.scopes
+            .iter()
+            .enumerate()
+            .map(|(index, scope)| (ScopeId(index), scope))
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = (SymbolId, &Symbol)> {
+        self.symbols
+            .iter()
+            .enumerate()
+            .map(|(index, symbol)| (SymbolId(index), symbol))
+    }
+
+    /// Returns the entity a used identifier refers to, or `None` if it is
+    /// undefined or isn't a usage at all.
+    pub fn target(&self, identifier: &Identifier) -> Option<Target> {
+        self.references
+            .get(&identifier.span().start().index())
+            .copied()
+    }
+
+    /// Returns the symbol a used identifier refers to, if it refers to one.
+    pub fn symbol_of(&self, identifier: &Identifier) -> Option<SymbolId> {
+        match self.target(identifier)? {
+            Target::Symbol(id) => Some(id),
+            Target::Builtin => None,
+        }
+    }
+
+    /// Returns the symbol defined by a `let` or `sequence` name.
+    pub fn definition(&self, name: &Identifier) -> Option<SymbolId> {
+        self.symbols()
+            .find(|(_, symbol)| symbol.name.span() == name.span())
+            .map(|(id, _)| id)
+    }
+
+    /// Returns `true` if a variable is changed with `=` anywhere in the
+    /// program.
+    pub fn is_assigned(&self, id: SymbolId) -> bool {
+        self.assigned.contains(&id)
+    }
+
+    /// Returns all usages of a symbol.
+    pub fn usages(&self, id: SymbolId) -> usize {
+        self.references
+            .values()
+            .filter(|target| **target == Target::Symbol(id))
+            .count()
+    }
+}
+
+/// Returns `true` if the name is meaningful without a definition.
+pub(crate) fn is_predefined(name: &str) -> bool {
+    is_builtin_function(name) || parse_name(name).is_some()
+}
+
+/// Builds the scope tree of a program and resolves every identifier in it.
+pub fn resolve(statements: &[Statement]) -> Resolution {
+    resolve_with(statements, &|_| false)
+}
+
+/// Resolves a program, in which names an embedding application provides
+/// are predefined too.
+pub fn resolve_with(statements: &[Statement], host: &dyn Fn(&str) -> bool) -> Resolution {
+    let mut resolver = Resolver {
+        resolution: Resolution::default(),
+        current: ScopeId(0),
+        host,
+    };
+
+    resolver.resolution.scopes.push(Scope {
+        kind: ScopeKind::Global,
+        parent: None,
+        symbols: Vec::new(),
+    });
+    resolver.resolve_statements(statements);
+
+    resolver.resolution
+}
+
+struct Resolver<'h> {
+    resolution: Resolution,
+    current: ScopeId,
+    host: &'h dyn Fn(&str) -> bool,
+}
+
+impl Resolver<'_> {
+    fn resolve_statements(&mut self, statements: &[Statement]) {
+        // Symbols are defined upfront, so that sequences can refer to each
+        // other regardless of the order of definitions, and variables used
+        // too early are reported as such.
+        for statement in statements {
+            match statement.unattributed() {
+                Statement::Sequence { name, .. } => self.define_sequence(*name),
+                Statement::Let { name, value } => {
+                    self.define(*name, SymbolKind::Let, value.span().end().index())
+                }
+                _ => {}
+            }
+        }
+
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn define_sequence(&mut self, name: Identifier) {
+        let duplicate = self
+            .scope_symbols(self.current)
+            .find(|symbol| symbol.kind == SymbolKind::Sequence && symbol.name.name() == name.name())
+            .map(|symbol| symbol.name);
+
+        if let Some(previous) = duplicate {
+            self.resolution
+                .errors
+                .push(ResolveError::DuplicateSequence { name, previous });
+            return;
+        }
+
+        self.define(name, SymbolKind::Sequence, 0);
+    }
+
+    fn define(&mut self, name: Identifier, kind: SymbolKind, visible_from: u32) {
+        let id = SymbolId(self.resolution.symbols.len());
+
+        self.resolution.symbols.push(Symbol {
+            name,
+            kind,
+            scope: self.current,
+            visible_from,
+        });
+        self.resolution.scopes[self.current.0].symbols.push(id);
+    }
+
+    fn scope_symbols(&self, scope: ScopeId) -> impl Iterator<Item = &Symbol> {
+        self.resolution.scopes[scope.0]
+            .symbols
+            .iter()
+            .map(|id| &self.resolution.symbols[id.0])
+    }
+
+    fn resolve_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Wait { expression }
+            | Statement::Play { expression, .. }
+            | Statement::Expression(expression) => self.resolve_expression(expression),
+            Statement::Attributed { statement, .. } => self.resolve_statement(statement),
+            Statement::Let { value, .. } => self.resolve_expression(value),
+            Statement::Sequence { name, block } => {
+                self.resolve_block(ScopeKind::Sequence(name.name()), block);
+            }
+            Statement::With { properties, block } => {
+                for property in properties {
+                    let kind = property_kind(&property.name.name().resolve());
+
+                    // Names like `dsaw` in `synth: dsaw` are not variables.
+                    if kind != Some(PropertyKind::Name)
+                        || !matches!(property.value, Expression::Identifier(_))
+                    {
+                        self.resolve_expression(&property.value);
+                    }
+                }
+
+                self.resolve_block(ScopeKind::With, block);
+            }
+            Statement::Poly { arguments, block } => {
+                for argument in arguments {
+                    self.resolve_expression(&argument.value);
+                }
+
+                self.resolve_block(ScopeKind::Poly, block);
+            }
+            Statement::Repeat { count, block } => {
+                if let Some(count) = count {
+                    self.resolve_expression(count);
+                }
+
+                self.resolve_block(ScopeKind::Repeat, block);
+            }
+            Statement::Tempo { bpm, ramp } => {
+                self.resolve_expression(bpm);
+
+                if let Some(ramp) = ramp {
+                    self.resolve_expression(&ramp.target);
+                    self.resolve_expression(&ramp.length);
+                }
+            }
+            Statement::Tuning { tuning } => self.resolve_expression(tuning),
+        }
+    }
+
+    fn resolve_block(&mut self, kind: ScopeKind, block: &Block) {
+        let id = ScopeId(self.resolution.scopes.len());
+
+        self.resolution.scopes.push(Scope {
+            kind,
+            parent: Some(self.current),
+            symbols: Vec::new(),
+        });
+
+        let enclosing = self.current;
+        self.current = id;
+        self.resolve_statements(&block.statements);
+        self.current = enclosing;
+    }
+
+    fn resolve_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(identifier) => self.resolve_identifier(*identifier),
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+
+                if let (BinaryOperatorKind::Assign, Expression::Identifier(identifier)) =
+                    (operator.kind, left.as_ref())
+                {
+                    if let Some(id) = self.resolution.symbol_of(identifier) {
+                        self.resolution.assigned.insert(id);
+                    }
+                }
+            }
+            Expression::Prefix { operand, .. } => self.resolve_expression(operand),
+            Expression::List { expressions, .. } => {
+                for expression in expressions {
+                    self.resolve_expression(expression);
+                }
+            }
+            Expression::LoadSample { sample, .. } => self.resolve_expression(sample),
+            Expression::Call {
+                callee, arguments, ..
+            } => {
+                self.resolve_expression(callee);
+
+                for argument in arguments {
+                    self.resolve_expression(&argument.value);
+                }
+            }
+            Expression::Index { target, index, .. } => {
+                self.resolve_expression(target);
+                self.resolve_expression(index);
+            }
+            Expression::Float { .. }
+            | Expression::Integer { .. }
+            | Expression::Quantity { .. }
+            | Expression::String { .. }
+            | Expression::Bool { .. }
+            | Expression::Degree { .. } => {}
+        }
+    }
+
+    fn resolve_identifier(&mut self, identifier: Identifier) {
+        let position = identifier.span().start().index();
+        let mut defined_later = None;
+        let mut scope = Some(self.current);
+
+        while let Some(id) = scope {
+            let found = self.resolution.scopes[id.0]
+                .symbols
+                .iter()
+                .rev()
+                .map(|symbol| (*symbol, &self.resolution.symbols[symbol.0]))
+                .filter(|(_, symbol)| symbol.name.name() == identifier.name())
+                .find(|(_, symbol)| {
+                    if symbol.visible_from <= position {
+                        return true;
+                    }
+
+                    defined_later.get_or_insert(symbol.name);
+                    false
+                });
+
+            if let Some((symbol, _)) = found {
+                self.resolution
+                    .references
+                    .insert(position, Target::Symbol(symbol));
+                return;
+            }
+
+            scope = self.resolution.scopes[id.0].parent;
+        }
+
+        let name = identifier.name().resolve();
+        if is_predefined(&name) || (self.host)(&name) {
+            self.resolution.references.insert(position, Target::Builtin);
+            return;
+        }
+
+        self.resolution.errors.push(match defined_later {
+            Some(definition) => ResolveError::UseBeforeDefinition {
+                identifier,
+                definition,
+            },
+            None => ResolveError::UndefinedIdentifier { identifier },
+        });
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ResolveError {
+    UndefinedIdentifier {
+        identifier: Identifier,
+    },
+    DuplicateSequence {
+        name: Identifier,
+        previous: Identifier,
+    },
+    UseBeforeDefinition {
+        identifier: Identifier,
+        definition: Identifier,
+    },
+}
+
+impl Spanned for ResolveError {
+    fn span(&self) -> Span {
+        match self {
+            Self::UndefinedIdentifier { identifier }
+            | Self::UseBeforeDefinition { identifier, .. } => identifier.span(),
+            Self::DuplicateSequence { name, .. } => name.span(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        syntax::{parse::parse, scan::scan},
+        test_snapshots,
+    };
+
+    use super::{resolve, ScopeKind, Target};
+
+    fn render(source: &str) -> String {
+        let statements = parse(scan(source).unwrap()).unwrap();
+
+        format!("{:#?}", resolve(&statements).errors)
+    }
+
+    test_snapshots!(
+        (undefined, "play melodyy\nsequence melody {}"),
+        (duplicate_sequence, "sequence a {}\nsequence a {}"),
+        (
+            use_before_definition,
+            "play kick\nlet kick = load_sample \"kick.wav\""
+        ),
+        (self_reference, "let a = a + 1"),
+    );
+
+    #[test]
+    fn scopes() {
+        let source = "
+            let a = 1
+            sequence intro {
+                let b = a
+                with synth: dsaw, transpose: b {
+                    play [c4, cmaj7, ^1]
+                    play outro
+                }
+            }
+            sequence outro {
+                play intro
+            }
+            play b
+        ";
+        let statements = parse(scan(source).unwrap()).unwrap();
+        let resolution = resolve(&statements);
+
+        let kinds: Vec<_> = resolution.scopes().map(|(_, scope)| scope.kind).collect();
+        assert!(matches!(
+            kinds.as_slice(),
+            [
+                ScopeKind::Global,
+                ScopeKind::Sequence(_),
+                ScopeKind::With,
+                ScopeKind::Sequence(_)
+            ]
+        ));
+
+        // Only `b` is undefined: it is local to `intro`.
+        assert_eq!(resolution.errors.len(), 1);
+
+        let (a, _) = resolution.symbols().next().unwrap();
+        assert_eq!(resolution.usages(a), 1);
+        assert!(resolution.symbols().all(|(id, _)| resolution.usages(id) > 0
+            || resolution.symbol(id).name.name().resolve() == "b"));
+        assert_ne!(
+            resolution.target(&resolution.symbol(a).name),
+            Some(Target::Builtin)
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Notes a program plays, for analyses, which need to know what sounds at
+//! the same time. They are read from the [event timeline](crate::ir) the
+//! interpreter builds, so that analyses see exactly what `run` plays.
+
+use crate::{
+    analysis::fold::Constant,
+    ir::{EventKind, Ir},
+    rational::Rational,
+    syntax::{location::Span, string_id::StringId},
+    theory::{key::Key, note::Note},
+};
+
+/// Melodic line: a channel started with `play!`, or one of the tones of
+/// chords played in it, counting from the lowest one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Voice {
+    pub channel: usize,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedNote {
+    pub start: Rational,
+    pub length: Rational,
+    pub note: Note,
+    pub voice: Voice,
+    /// Key declared for the block the note is played in.
+    pub key: Option<Key>,
+    /// Synth of the block the note is played in.
+    pub synth: Option<StringId>,
+    /// Span of the `play` statement, which produced the note.
+    pub span: Span,
+}
+
+impl TimedNote {
+    pub fn end(&self) -> Rational {
+        self.start + self.length
+    }
+}
+
+/// Collects notes of an event timeline, ordered by their start. Notes
+/// starting together on a channel are tones of a chord, and arpeggios are
+/// melodic lines of a single voice.
+pub fn timeline(ir: &Ir) -> Vec<TimedNote> {
+    let mut notes = Vec::new();
+
+    for (index, channel) in ir.channels.iter().enumerate() {
+        let first = notes.len();
+        let mut synth = None;
+        // Notes of the channel, which haven't been released yet.
+        let mut sounding: Vec<usize> = Vec::new();
+
+        for event in &channel.events {
+            match &event.kind {
+                EventKind::NoteOn { note, key, .. } => {
+                    sounding.push(notes.len());
+                    notes.push(TimedNote {
+                        start: event.beat,
+                        length: Rational::ZERO,
+                        note: *note,
+                        voice: Voice {
+                            channel: index,
+                            index: 0,
+                        },
+                        key: *key,
+                        synth,
+                        span: event.span,
+                    });
+                }
+                EventKind::NoteOff { note } => {
+                    let released = sounding.iter().position(|sounding| {
+                        notes[*sounding].note == *note && notes[*sounding].span == event.span
+                    });
+
+                    if let Some(position) = released {
+                        let released = &mut notes[sounding.remove(position)];
+                        released.length = event.beat - released.start;
+                    }
+                }
+                EventKind::ParamChange { name, value } if name.resolve() == "synth" => {
+                    synth = match value {
+                        Some(Constant::String(name)) => Some(*name),
+                        _ => None,
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        for chord in notes[first..].chunk_by_mut(|a, b| a.start == b.start) {
+            let mut tones: Vec<_> = chord.iter().map(|note| note.note).collect();
+            tones.sort();
+            tones.dedup();
+
+            for note in chord {
+                note.voice.index = tones.binary_search(&note.note).unwrap_or_default();
+            }
+        }
+    }
+
+    notes.sort_by_key(|note| (note.start, note.voice));
+    notes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::resolve::resolve,
+        ctx::interpreter::{interpret, Options},
+        rational::Rational,
+        syntax::{parse::parse, scan::scan},
+    };
+
+    use super::{timeline, Voice};
+
+    #[test]
+    fn channels_and_voices() {
+        let source = "
+            sequence bass {
+                repeat 2 {
+                    play c2
+                    wait 2
+                }
+            }
+            play! bass
+            play [e4, c4]
+            wait 1
+            with transpose: 2, arp: up, rate: 1/8 {
+                play c4 + 12
+            }
+        ";
+        let statements = parse(scan(source).unwrap()).unwrap();
+        let ir = interpret(&statements, &resolve(&statements), &Options::default()).unwrap();
+        let notes: Vec<_> = timeline(&ir)
+            .into_iter()
+            .map(|note| (note.start, note.note.to_string(), note.voice))
+            .collect();
+
+        let voice = |channel, index| Voice { channel, index };
+        assert_eq!(
+            notes,
+            [
+                (Rational::ZERO, "c4".to_owned(), voice(0, 0)),
+                (Rational::ZERO, "e4".to_owned(), voice(0, 1)),
+                (Rational::ZERO, "c2".to_owned(), voice(1, 0)),
+                (Rational::ONE, "d5".to_owned(), voice(0, 0)),
+                (Rational::new(3, 2), "d5".to_owned(), voice(0, 0)),
+                (Rational::integer(2), "c2".to_owned(), voice(1, 0)),
+            ]
+        );
+    }
+}
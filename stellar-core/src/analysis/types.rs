@@ -0,0 +1,564 @@
+//! Static type checking: rejects programs, which would fail at runtime
+//! because of a value of a wrong type, before anything is played.
+
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    ctx::{
+        builtins::{is_builtin_function, Builtin},
+        properties::{property_kind, PropertyKind},
+    },
+    syntax::{
+        ast::{BinaryOperatorKind, Expression, PrefixOperatorKind, Statement},
+        location::{Span, Spanned},
+        token::{Identifier, Unit},
+    },
+    theory::{parse_name, Name},
+};
+
+use super::resolve::{Resolution, SymbolId, SymbolKind, Target};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Note,
+    Chord,
+    /// Time in seconds: `8ms`, `1.5s`.
+    Duration,
+    Sample,
+    Sequence,
+    Number,
+    String,
+    Bool,
+    List(Box<Type>),
+    Key,
+    Scale,
+    Tuning,
+    /// Builtin function: `fast`, `every`.
+    Function,
+    /// Type of an expression, which couldn't be checked because of another
+    /// error. Accepted everywhere, so that errors don't cascade.
+    Unknown,
+}
+
+impl Type {
+    /// Returns `true` if values of the type can be used in `play`
+    /// statements.
+    pub fn is_playable(&self) -> bool {
+        match self {
+            Self::Note | Self::Chord | Self::Sample | Self::Sequence | Self::Unknown => true,
+            Self::List(element) => element.is_playable(),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if values of the type can be passed where `expected`
+    /// is required.
+    pub fn is_assignable_to(&self, expected: &Type) -> bool {
+        match (self, expected) {
+            (Self::Unknown, _) | (_, Self::Unknown) => true,
+            (Self::List(element), Self::List(expected)) => element.is_assignable_to(expected),
+            _ => self == expected,
+        }
+    }
+
+    /// Returns the type of a list containing values of both types, if they
+    /// can be mixed: a note is a chord of a single note.
+    fn join(&self, other: &Type) -> Option<Type> {
+        match (self, other) {
+            (Self::Unknown, other) | (other, Self::Unknown) => Some(other.clone()),
+            (Self::Note, Self::Chord) | (Self::Chord, Self::Note) => Some(Self::Chord),
+            (Self::List(left), Self::List(right)) => Some(Self::List(Box::new(left.join(right)?))),
+            _ if self == other => Some(self.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Note => write!(f, "Note"),
+            Self::Chord => write!(f, "Chord"),
+            Self::Duration => write!(f, "Duration"),
+            Self::Sample => write!(f, "Sample"),
+            Self::Sequence => write!(f, "Sequence"),
+            Self::Number => write!(f, "Number"),
+            Self::String => write!(f, "String"),
+            Self::Bool => write!(f, "Bool"),
+            Self::List(element) => write!(f, "List<{element}>"),
+            Self::Key => write!(f, "Key"),
+            Self::Scale => write!(f, "Scale"),
+            Self::Tuning => write!(f, "Tuning"),
+            Self::Function => write!(f, "Function"),
+            Self::Unknown => write!(f, "?"),
+        }
+    }
+}
+
+/// Checks types of every statement of a resolved program.
+pub fn check(statements: &[Statement], resolution: &Resolution) -> Vec<TypeError> {
+    let mut checker = Checker {
+        resolution,
+        variables: HashMap::new(),
+        errors: Vec::new(),
+    };
+
+    checker.check_statements(statements);
+    checker.errors
+}
+
+struct Checker<'r> {
+    resolution: &'r Resolution,
+    /// Types of values bound with `let`.
+    variables: HashMap<SymbolId, Type>,
+    errors: Vec<TypeError>,
+}
+
+impl Checker<'_> {
+    fn check_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.check_statement(statement);
+        }
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Play { expression, .. } => {
+                let found = self.check_expression(expression);
+
+                if !found.is_playable() {
+                    self.errors.push(TypeError::NotPlayable {
+                        found,
+                        span: expression.span(),
+                    });
+                }
+            }
+            Statement::Wait { expression } => {
+                let found = self.check_expression(expression);
+
+                // Plain numbers are beats.
+                if !found.is_assignable_to(&Type::Number)
+                    && !found.is_assignable_to(&Type::Duration)
+                {
+                    self.errors.push(TypeError::NotDuration {
+                        found,
+                        span: expression.span(),
+                    });
+                }
+            }
+            Statement::Let { name, value } => {
+                let found = self.check_expression(value);
+
+                if let Some(id) = self.resolution.definition(name) {
+                    self.variables.insert(id, found);
+                }
+            }
+            Statement::Sequence { block, .. } => self.check_statements(&block.statements),
+            Statement::With { properties, block } => {
+                for property in properties {
+                    let name = property.name.name().resolve();
+
+                    let expected = match property_kind(&name) {
+                        Some(PropertyKind::Name) => {
+                            if !matches!(property.value, Expression::Identifier(_)) {
+                                self.errors.push(TypeError::ExpectedName {
+                                    property: property.name,
+                                    span: property.value.span(),
+                                });
+                            }
+
+                            continue;
+                        }
+                        Some(PropertyKind::Key) => Type::Key,
+                        Some(PropertyKind::Number | PropertyKind::Ratio) => Type::Number,
+                        Some(PropertyKind::String) => Type::String,
+                        None => {
+                            self.errors.push(TypeError::UnknownProperty {
+                                property: property.name,
+                            });
+
+                            continue;
+                        }
+                    };
+
+                    self.expect(&property.value, &expected);
+                }
+
+                self.check_statements(&block.statements);
+            }
+            Statement::Poly { arguments, block } => {
+                for argument in arguments {
+                    self.expect(&argument.value, &Type::Number);
+                }
+
+                self.check_statements(&block.statements);
+            }
+            Statement::Repeat { count, block } => {
+                if let Some(count) = count {
+                    self.expect(count, &Type::Number);
+                }
+
+                self.check_statements(&block.statements);
+            }
+            Statement::Tempo { bpm, ramp } => {
+                self.expect(bpm, &Type::Number);
+
+                if let Some(ramp) = ramp {
+                    self.expect(&ramp.target, &Type::Number);
+                    self.expect(&ramp.length, &Type::Number);
+                }
+            }
+            Statement::Tuning { tuning } => self.expect(tuning, &Type::Tuning),
+            Statement::Attributed { statement, .. } => self.check_statement(statement),
+            Statement::Expression(expression) => {
+                self.check_expression(expression);
+            }
+        }
+    }
+
+    fn expect(&mut self, expression: &Expression, expected: &Type) {
+        let found = self.check_expression(expression);
+
+        if !found.is_assignable_to(expected) {
+            self.errors.push(TypeError::Mismatch {
+                expected: expected.clone(),
+                found,
+                span: expression.span(),
+            });
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Expression) -> Type {
+        match expression {
+            Expression::Integer { .. } | Expression::Float { .. } => Type::Number,
+            Expression::Quantity { unit, .. } => match unit {
+                Unit::Percent => Type::Number,
+                Unit::Milliseconds | Unit::Seconds => Type::Duration,
+            },
+            Expression::String { .. } => Type::String,
+            Expression::Bool { .. } => Type::Bool,
+            Expression::Degree { .. } => Type::Note,
+            Expression::Identifier(identifier) => self.check_identifier(identifier),
+            Expression::List { expressions, .. } => {
+                let mut element = Type::Unknown;
+
+                for expression in expressions {
+                    let found = self.check_expression(expression);
+
+                    match element.join(&found) {
+                        Some(joined) => element = joined,
+                        None => self.errors.push(TypeError::MixedList {
+                            element: element.clone(),
+                            found,
+                            span: expression.span(),
+                        }),
+                    }
+                }
+
+                Type::List(Box::new(element))
+            }
+            Expression::LoadSample { sample, .. } => {
+                self.expect(sample, &Type::String);
+
+                Type::Sample
+            }
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let left_type = self.check_expression(left);
+                let right_type = self.check_expression(right);
+
+                binary_type(operator.kind, &left_type, &right_type).unwrap_or_else(|| {
+                    self.errors.push(TypeError::InvalidOperands {
+                        operator: operator.kind,
+                        left: left_type,
+                        right: right_type,
+                        span: expression.span(),
+                    });
+
+                    Type::Unknown
+                })
+            }
+            Expression::Prefix { operator, operand } => {
+                let found = self.check_expression(operand);
+
+                let result = match (operator.kind, &found) {
+                    (_, Type::Unknown) => Some(Type::Unknown),
+                    (PrefixOperatorKind::Minus, Type::Number | Type::Duration) => {
+                        Some(found.clone())
+                    }
+                    (PrefixOperatorKind::Exclamation, Type::Bool) => Some(Type::Bool),
+                    _ => None,
+                };
+
+                result.unwrap_or_else(|| {
+                    self.errors.push(TypeError::InvalidOperand {
+                        operator: operator.kind,
+                        operand: found,
+                        span: expression.span(),
+                    });
+
+                    Type::Unknown
+                })
+            }
+            Expression::Index { target, index, .. } => {
+                let found = self.check_expression(target);
+                self.expect(index, &Type::Number);
+
+                match found {
+                    Type::Scale => Type::Note,
+                    Type::List(element) => *element,
+                    Type::Unknown => Type::Unknown,
+                    _ => {
+                        self.errors.push(TypeError::NotIndexable {
+                            found,
+                            span: target.span(),
+                        });
+
+                        Type::Unknown
+                    }
+                }
+            }
+            Expression::Call {
+                callee, arguments, ..
+            } => {
+                let found = self.check_expression(callee);
+
+                let mut positional = Vec::new();
+                for argument in arguments {
+                    let argument_type = self.check_expression(&argument.value);
+
+                    if argument.name.is_none() {
+                        positional.push(argument_type);
+                    }
+                }
+
+                match found {
+                    Type::Function => match self.builtin(callee) {
+                        Some(function) => call_type(function, &positional),
+                        // Variables may hold any builtin function.
+                        None => Type::Unknown,
+                    },
+                    Type::Unknown => Type::Unknown,
+                    _ => {
+                        self.errors.push(TypeError::NotCallable {
+                            found,
+                            span: callee.span(),
+                        });
+
+                        Type::Unknown
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the builtin function an expression names directly.
+    fn builtin(&self, expression: &Expression) -> Option<Builtin> {
+        match expression {
+            Expression::Identifier(identifier)
+                if self.resolution.target(identifier) == Some(Target::Builtin) =>
+            {
+                Builtin::parse(&identifier.name().resolve())
+            }
+            _ => None,
+        }
+    }
+
+    fn check_identifier(&self, identifier: &Identifier) -> Type {
+        match self.resolution.target(identifier) {
+            Some(Target::Symbol(id)) => match self.resolution.symbol(id).kind {
+                SymbolKind::Sequence => Type::Sequence,
+                SymbolKind::Let => self.variables.get(&id).cloned().unwrap_or(Type::Unknown),
+            },
+            Some(Target::Builtin) => {
+                let name = identifier.name().resolve();
+
+                if is_builtin_function(&name) {
+                    Type::Function
+                } else {
+                    match parse_name(&name) {
+                        Some(Name::Note(_)) => Type::Note,
+                        Some(Name::Chord(_)) => Type::Chord,
+                        Some(Name::Key(_)) => Type::Key,
+                        None => Type::Unknown,
+                    }
+                }
+            }
+            None => Type::Unknown,
+        }
+    }
+}
+
+/// Returns the type of the result of a builtin function, given the types
+/// of its positional arguments.
+fn call_type(function: Builtin, positional: &[Type]) -> Type {
+    match function {
+        // Transposed and quantized values keep their type.
+        Builtin::Transpose | Builtin::Octave | Builtin::Quantize => {
+            positional.first().cloned().unwrap_or(Type::Unknown)
+        }
+        Builtin::Invert | Builtin::Drop2 | Builtin::Voicing | Builtin::ChordTones => {
+            Type::List(Box::new(Type::Note))
+        }
+        Builtin::Prog | Builtin::Recognize => Type::List(Box::new(Type::Chord)),
+        Builtin::VoiceLead => Type::List(Box::new(Type::List(Box::new(Type::Note)))),
+        Builtin::NoteFromMidi => Type::Note,
+        Builtin::Scale => Type::Scale,
+        Builtin::Edo | Builtin::Just | Builtin::Scala => Type::Tuning,
+        Builtin::Midi | Builtin::Freq | Builtin::Interval => Type::Number,
+        // Every other function transforms or generates a sequence.
+        _ => Type::Sequence,
+    }
+}
+
+/// Returns the type of a binary expression, or `None` if the operator
+/// can't be applied to its operands.
+fn binary_type(operator: BinaryOperatorKind, left: &Type, right: &Type) -> Option<Type> {
+    use BinaryOperatorKind::{Assign, Minus, Plus, Slash, Star};
+
+    match (operator, left, right) {
+        (_, Type::Unknown, _) | (_, _, Type::Unknown) => Some(Type::Unknown),
+        (Assign, _, right) => Some(right.clone()),
+        (_, Type::Number, Type::Number) => Some(Type::Number),
+        (Plus | Minus, Type::Duration, Type::Duration) => Some(Type::Duration),
+        (Star | Slash, Type::Duration, Type::Number) => Some(Type::Duration),
+        (Star, Type::Number, Type::Duration) => Some(Type::Duration),
+        // Transposition by a number of semitones.
+        (Plus | Minus, Type::Note | Type::Chord, Type::Number) => Some(left.clone()),
+        (Plus, Type::String, Type::String) => Some(Type::String),
+        (Plus, Type::List(_), Type::List(_)) => left.join(right),
+        _ => None,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TypeError {
+    Mismatch {
+        expected: Type,
+        found: Type,
+        span: Span,
+    },
+    NotPlayable {
+        found: Type,
+        span: Span,
+    },
+    NotDuration {
+        found: Type,
+        span: Span,
+    },
+    NotCallable {
+        found: Type,
+        span: Span,
+    },
+    NotIndexable {
+        found: Type,
+        span: Span,
+    },
+    MixedList {
+        element: Type,
+        found: Type,
+        span: Span,
+    },
+    InvalidOperands {
+        operator: BinaryOperatorKind,
+        left: Type,
+        right: Type,
+        span: Span,
+    },
+    InvalidOperand {
+        operator: PrefixOperatorKind,
+        operand: Type,
+        span: Span,
+    },
+    UnknownProperty {
+        property: Identifier,
+    },
+    ExpectedName {
+        property: Identifier,
+        span: Span,
+    },
+}
+
+impl Spanned for TypeError {
+    fn span(&self) -> Span {
+        match self {
+            Self::Mismatch { span, .. }
+            | Self::NotPlayable { span, .. }
+            | Self::NotDuration { span, .. }
+            | Self::NotCallable { span, .. }
+            | Self::NotIndexable { span, .. }
+            | Self::MixedList { span, .. }
+            | Self::InvalidOperands { span, .. }
+            | Self::InvalidOperand { span, .. }
+            | Self::ExpectedName { span, .. } => *span,
+            Self::UnknownProperty { property } => property.span(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::resolve::resolve,
+        syntax::{parse::parse, scan::scan},
+        test_snapshots,
+    };
+
+    use super::{check, Type};
+
+    fn errors(source: &str) -> Vec<super::TypeError> {
+        let statements = parse(scan(source).unwrap()).unwrap();
+        check(&statements, &resolve(&statements))
+    }
+
+    fn render(source: &str) -> String {
+        format!("{:#?}", errors(source))
+    }
+
+    test_snapshots!(
+        (play_number, "play 3"),
+        (wait_string, "wait \"x\""),
+        (note_times_string, "let a = c4 * \"a\""),
+        (unknown_property, "with volume: 3 {}"),
+        (wait_note, "wait note_from_midi(60)\nplay midi(c4)"),
+        (
+            index_note,
+            "let s = scale(c4, \"major\")\nplay c4[1]\nplay s[c4]"
+        ),
+    );
+
+    #[test]
+    fn well_typed() {
+        let source = "
+            let kick = load_sample \"kick.wav\"
+            let riff = [c4, cmaj7, ^1 + 12]
+            sequence drums {
+                play kick
+                wait 1/4
+                wait 250ms * 2
+            }
+            with synth: dsaw, rate: 1/16, swing: 60%, groove: \"mpc_16\" {
+                play [riff, [e4]]
+                play fast(2, drums)
+                play every(3, rev, drums)
+                play invert(cmaj7, 1) + [transpose(c4, 2)]
+                play prog(\"I IV V7/IV\", key: bb_minor) + [cmaj7]
+                play recognize([e3, g3, c4])[0] + 2
+                play voice_lead(prog(\"I V\", key: c_major))[0] + [c5]
+                play quantize([c4, ^3], scale(d4, \"blues\")) + [scale(d4, [0, 5])[3] + 12]
+                wait freq(a4) / 440
+            }
+        ";
+
+        assert_eq!(errors(source), []);
+    }
+
+    #[test]
+    fn display() {
+        let nested = Type::List(Box::new(Type::List(Box::new(Type::Note))));
+        assert_eq!(nested.to_string(), "List<List<Note>>");
+    }
+}
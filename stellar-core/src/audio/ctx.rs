@@ -1,3 +1,24 @@
-struct AudioContext {
-    beats_per_minute: i64
+use super::tempo::{TempoError, TempoMap};
+
+/// Default tempo of a piece, which doesn't set one explicitly.
+pub const DEFAULT_BPM: f64 = 120.0;
+
+pub struct AudioContext {
+    pub tempo: TempoMap,
+}
+
+impl AudioContext {
+    pub fn new(beats_per_minute: f64) -> Result<Self, TempoError> {
+        Ok(Self {
+            tempo: TempoMap::new(beats_per_minute)?,
+        })
+    }
+}
+
+impl Default for AudioContext {
+    fn default() -> Self {
+        Self {
+            tempo: TempoMap::new(DEFAULT_BPM).expect("default tempo is valid"),
+        }
+    }
 }
@@ -0,0 +1,3 @@
+pub mod ctx;
+pub mod preset;
+pub mod tempo;
@@ -0,0 +1,41 @@
+use crate::theory::note::Note;
+
+/// Synth, which notes of a `with synth: <name>` block are played with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Preset {
+    pub name: &'static str,
+    /// MIDI number of the lowest note the preset can play.
+    pub lowest: i32,
+    /// MIDI number of the highest note the preset can play.
+    pub highest: i32,
+}
+
+/// Synths shipped with Stellar.
+pub const PRESETS: [Preset; 6] = [
+    Preset::new("dsaw", 24, 108),
+    Preset::new("sine", 12, 120),
+    Preset::new("piano", 21, 108),
+    Preset::new("bass", 28, 67),
+    Preset::new("pad", 36, 96),
+    Preset::new("pluck", 40, 100),
+];
+
+impl Preset {
+    pub const fn new(name: &'static str, lowest: i32, highest: i32) -> Self {
+        Self {
+            name,
+            lowest,
+            highest,
+        }
+    }
+
+    /// Finds a preset by name.
+    pub fn find(name: &str) -> Option<Self> {
+        PRESETS.iter().find(|preset| preset.name == name).copied()
+    }
+
+    /// Returns `true` if the preset can play a given note.
+    pub fn contains(&self, note: Note) -> bool {
+        (self.lowest..=self.highest).contains(&note.midi())
+    }
+}
@@ -0,0 +1,330 @@
+use crate::rational::Rational;
+
+/// Shape of a gradual tempo change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Curve {
+    /// Tempo changes by the same number of BPM every beat.
+    Linear,
+    /// Tempo changes by the same ratio every beat, which is perceived as
+    /// a more even ritardando/accelerando.
+    Exponential,
+}
+
+/// Gradual change of tempo towards `target` BPM over `length` beats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ramp {
+    pub target: f64,
+    pub length: Rational,
+    pub curve: Curve,
+}
+
+/// Tempo starting from a given beat and lasting until the next segment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segment {
+    start: Rational,
+    bpm: f64,
+    ramp: Option<Ramp>,
+}
+
+impl Segment {
+    /// Returns the tempo `offset` beats after the beginning of the segment.
+    fn bpm_at(&self, offset: f64) -> f64 {
+        let Some(ramp) = self.ramp else {
+            return self.bpm;
+        };
+
+        let length = ramp.length.to_f64();
+        if offset >= length {
+            return ramp.target;
+        }
+
+        let progress = offset / length;
+
+        match ramp.curve {
+            Curve::Linear => self.bpm + (ramp.target - self.bpm) * progress,
+            Curve::Exponential => self.bpm * (ramp.target / self.bpm).powf(progress),
+        }
+    }
+
+    /// Returns the number of seconds it takes to play the first `offset`
+    /// beats of the segment, integrating `60 / bpm(beat)` analytically.
+    fn seconds_at(&self, offset: f64) -> f64 {
+        let Some(ramp) = self.ramp else {
+            return 60.0 * offset / self.bpm;
+        };
+
+        let length = ramp.length.to_f64();
+        let ramped = offset.min(length);
+        let rest = offset - ramped;
+
+        let seconds = match ramp.curve {
+            _ if ramp.target == self.bpm => 60.0 * ramped / self.bpm,
+            Curve::Linear => {
+                // bpm(x) = a + k * x
+                let slope = (ramp.target - self.bpm) / length;
+
+                60.0 / slope * ((self.bpm + slope * ramped) / self.bpm).ln()
+            }
+            Curve::Exponential => {
+                // bpm(x) = a * e^(r * x)
+                let rate = (ramp.target / self.bpm).ln() / length;
+
+                60.0 / (self.bpm * rate) * (1.0 - (-rate * ramped).exp())
+            }
+        };
+
+        seconds + 60.0 * rest / ramp.target
+    }
+
+    /// Inverse of [`Segment::seconds_at`]: returns the number of beats
+    /// played during the first `seconds` of the segment.
+    fn beats_at(&self, seconds: f64) -> f64 {
+        let Some(ramp) = self.ramp else {
+            return seconds * self.bpm / 60.0;
+        };
+
+        let length = ramp.length.to_f64();
+        let ramp_seconds = self.seconds_at(length);
+        if seconds >= ramp_seconds {
+            return length + (seconds - ramp_seconds) * ramp.target / 60.0;
+        }
+
+        match ramp.curve {
+            _ if ramp.target == self.bpm => seconds * self.bpm / 60.0,
+            Curve::Linear => {
+                let slope = (ramp.target - self.bpm) / length;
+
+                self.bpm / slope * ((slope * seconds / 60.0).exp() - 1.0)
+            }
+            Curve::Exponential => {
+                let rate = (ramp.target / self.bpm).ln() / length;
+
+                -(1.0 - self.bpm * rate * seconds / 60.0).ln() / rate
+            }
+        }
+    }
+}
+
+/// Describes how tempo changes over the course of a piece: a list of step
+/// changes and ramps, ordered by the beat they start at.
+///
+/// Converting beats into seconds is done by integrating the tempo curve,
+/// so arbitrary ritardandos and accelerandos stay sample-accurate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TempoMap {
+    segments: Vec<Segment>,
+}
+
+impl TempoMap {
+    /// Creates a tempo map with a constant tempo.
+    pub fn new(bpm: f64) -> Result<Self, TempoError> {
+        check_tempo(bpm)?;
+
+        Ok(Self {
+            segments: vec![Segment {
+                start: Rational::ZERO,
+                bpm,
+                ramp: None,
+            }],
+        })
+    }
+
+    /// Changes tempo to `bpm` immediately at a given beat.
+    pub fn set(&mut self, beat: Rational, bpm: f64) -> Result<(), TempoError> {
+        check_tempo(bpm)?;
+        self.insert(Segment {
+            start: beat,
+            bpm,
+            ramp: None,
+        })
+    }
+
+    /// Changes tempo gradually: sets it to `bpm` at a given beat and then
+    /// moves it towards `ramp.target` over `ramp.length` beats.
+    pub fn ramp(&mut self, beat: Rational, bpm: f64, ramp: Ramp) -> Result<(), TempoError> {
+        check_tempo(bpm)?;
+        check_tempo(ramp.target)?;
+
+        if ramp.length <= Rational::ZERO {
+            return Err(TempoError::InvalidRampLength {
+                length: ramp.length,
+            });
+        }
+
+        self.insert(Segment {
+            start: beat,
+            bpm,
+            ramp: Some(ramp),
+        })
+    }
+
+    fn insert(&mut self, segment: Segment) -> Result<(), TempoError> {
+        if segment.start < Rational::ZERO {
+            return Err(TempoError::NegativeBeat {
+                beat: segment.start,
+            });
+        }
+
+        match self
+            .segments
+            .binary_search_by_key(&segment.start, |other| other.start)
+        {
+            Ok(index) => self.segments[index] = segment,
+            Err(index) => self.segments.insert(index, segment),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the segment, which contains a given beat.
+    fn segment(&self, beat: Rational) -> &Segment {
+        let index = self
+            .segments
+            .partition_point(|segment| segment.start <= beat);
+
+        &self.segments[index.saturating_sub(1)]
+    }
+
+    /// Returns the tempo at a given beat in beats per minute.
+    pub fn bpm_at(&self, beat: Rational) -> f64 {
+        let segment = self.segment(beat);
+
+        segment.bpm_at((beat - segment.start).to_f64())
+    }
+
+    /// Returns the time in seconds, at which a given beat is played.
+    pub fn seconds_at(&self, beat: Rational) -> f64 {
+        let mut seconds = 0.0;
+
+        for (index, segment) in self.segments.iter().enumerate() {
+            let end = self.segments.get(index + 1).map(|next| next.start);
+
+            match end {
+                Some(end) if end <= beat => {
+                    seconds += segment.seconds_at((end - segment.start).to_f64());
+                }
+                _ => return seconds + segment.seconds_at((beat - segment.start).to_f64()),
+            }
+        }
+
+        seconds
+    }
+
+    /// Returns the beat, which is played at a given time in seconds.
+    pub fn beat_at(&self, seconds: f64) -> f64 {
+        let mut elapsed = 0.0;
+
+        for (index, segment) in self.segments.iter().enumerate() {
+            let next = self.segments.get(index + 1).map(|next| next.start);
+
+            if let Some(end) = next {
+                let duration = segment.seconds_at((end - segment.start).to_f64());
+
+                if elapsed + duration <= seconds {
+                    elapsed += duration;
+                    continue;
+                }
+            }
+
+            return segment.start.to_f64() + segment.beats_at(seconds - elapsed);
+        }
+
+        0.0
+    }
+}
+
+fn check_tempo(bpm: f64) -> Result<(), TempoError> {
+    if bpm.is_finite() && bpm > 0.0 {
+        Ok(())
+    } else {
+        Err(TempoError::InvalidTempo { bpm })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TempoError {
+    InvalidTempo { bpm: f64 },
+    InvalidRampLength { length: Rational },
+    NegativeBeat { beat: Rational },
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rational::Rational;
+
+    use super::{Curve, Ramp, TempoError, TempoMap};
+
+    fn assert_close(left: f64, right: f64) {
+        assert!((left - right).abs() < 1e-9, "{left} != {right}");
+    }
+
+    #[test]
+    fn step_changes() {
+        let mut tempo = TempoMap::new(120.0).unwrap();
+        tempo.set(Rational::integer(4), 60.0).unwrap();
+
+        assert_close(tempo.seconds_at(Rational::integer(2)), 1.0);
+        assert_close(tempo.seconds_at(Rational::integer(4)), 2.0);
+        assert_close(tempo.seconds_at(Rational::integer(6)), 4.0);
+        assert_close(tempo.beat_at(4.0), 6.0);
+        assert_close(tempo.bpm_at(Rational::integer(5)), 60.0);
+    }
+
+    #[test]
+    fn linear_ramp() {
+        let mut tempo = TempoMap::new(120.0).unwrap();
+        let ramp = Ramp {
+            target: 60.0,
+            length: Rational::integer(4),
+            curve: Curve::Linear,
+        };
+        tempo.ramp(Rational::ZERO, 120.0, ramp).unwrap();
+
+        assert_close(tempo.bpm_at(Rational::integer(2)), 90.0);
+
+        // 60 / 15 * ln(120 / 60)
+        let ramp_seconds = 4.0 * 2f64.ln();
+        assert_close(tempo.seconds_at(Rational::integer(4)), ramp_seconds);
+        assert_close(tempo.seconds_at(Rational::integer(5)), ramp_seconds + 1.0);
+
+        for beat in [1, 3, 5] {
+            let seconds = tempo.seconds_at(Rational::integer(beat));
+            assert_close(tempo.beat_at(seconds), beat as f64);
+        }
+    }
+
+    #[test]
+    fn exponential_ramp() {
+        let mut tempo = TempoMap::new(100.0).unwrap();
+        let ramp = Ramp {
+            target: 200.0,
+            length: Rational::integer(8),
+            curve: Curve::Exponential,
+        };
+        tempo.ramp(Rational::integer(4), 100.0, ramp).unwrap();
+
+        assert_close(tempo.bpm_at(Rational::integer(8)), 100.0 * 2f64.sqrt());
+
+        for beat in [2, 6, 12, 20] {
+            let seconds = tempo.seconds_at(Rational::integer(beat));
+            assert_close(tempo.beat_at(seconds), beat as f64);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            TempoMap::new(0.0),
+            Err(TempoError::InvalidTempo { bpm: 0.0 })
+        );
+
+        let mut tempo = TempoMap::new(120.0).unwrap();
+        let ramp = Ramp {
+            target: 90.0,
+            length: Rational::ZERO,
+            curve: Curve::Linear,
+        };
+
+        assert!(tempo.ramp(Rational::ZERO, 120.0, ramp).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::theory::note::{Note, A4_FREQUENCY};
+
+use super::value::Value;
+
+/// Names of functions available in every program without a definition.
+pub const BUILTIN_FUNCTIONS: [&str; 27] = [
+    "every",
+    "fast",
+    "slow",
+    "rev",
+    "rotate",
+    "palindrome",
+    "degrade",
+    "euclid",
+    "humanize",
+    "transpose",
+    "invert",
+    "drop2",
+    "voicing",
+    "octave",
+    "midi",
+    "freq",
+    "note_from_midi",
+    "interval",
+    "chord_tones",
+    "scale",
+    "quantize",
+    "prog",
+    "voice_lead",
+    "recognize",
+    "edo",
+    "just",
+    "scala",
+];
+
+pub fn is_builtin_function(name: &str) -> bool {
+    BUILTIN_FUNCTIONS.contains(&name)
+}
+
+/// Builtin function, which transforms or generates a sequence, or computes
+/// a value from notes and chords.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    Every,
+    Fast,
+    Slow,
+    Rev,
+    Rotate,
+    Palindrome,
+    Degrade,
+    Euclid,
+    Humanize,
+    Transpose,
+    Invert,
+    Drop2,
+    Voicing,
+    Octave,
+    Midi,
+    Freq,
+    NoteFromMidi,
+    Interval,
+    ChordTones,
+    Scale,
+    Quantize,
+    Prog,
+    VoiceLead,
+    Recognize,
+    Edo,
+    Just,
+    Scala,
+}
+
+impl Builtin {
+    pub const ALL: [Self; 27] = [
+        Self::Every,
+        Self::Fast,
+        Self::Slow,
+        Self::Rev,
+        Self::Rotate,
+        Self::Palindrome,
+        Self::Degrade,
+        Self::Euclid,
+        Self::Humanize,
+        Self::Transpose,
+        Self::Invert,
+        Self::Drop2,
+        Self::Voicing,
+        Self::Octave,
+        Self::Midi,
+        Self::Freq,
+        Self::NoteFromMidi,
+        Self::Interval,
+        Self::ChordTones,
+        Self::Scale,
+        Self::Quantize,
+        Self::Prog,
+        Self::VoiceLead,
+        Self::Recognize,
+        Self::Edo,
+        Self::Just,
+        Self::Scala,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|builtin| builtin.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Every => "every",
+            Self::Fast => "fast",
+            Self::Slow => "slow",
+            Self::Rev => "rev",
+            Self::Rotate => "rotate",
+            Self::Palindrome => "palindrome",
+            Self::Degrade => "degrade",
+            Self::Euclid => "euclid",
+            Self::Humanize => "humanize",
+            Self::Transpose => "transpose",
+            Self::Invert => "invert",
+            Self::Drop2 => "drop2",
+            Self::Voicing => "voicing",
+            Self::Octave => "octave",
+            Self::Midi => "midi",
+            Self::Freq => "freq",
+            Self::NoteFromMidi => "note_from_midi",
+            Self::Interval => "interval",
+            Self::ChordTones => "chord_tones",
+            Self::Scale => "scale",
+            Self::Quantize => "quantize",
+            Self::Prog => "prog",
+            Self::VoiceLead => "voice_lead",
+            Self::Recognize => "recognize",
+            Self::Edo => "edo",
+            Self::Just => "just",
+            Self::Scala => "scala",
+        }
+    }
+
+    /// Returns names of the parameters. Functions of sequences take the
+    /// sequence they transform last. Arguments are passed in this order, or
+    /// by name: `humanize(timing: 8ms, drums)`. Some of them have a
+    /// [default](Self::default_argument), and can be left out before the
+    /// sequence: `euclid(3, 8, kick)`.
+    pub fn parameters(self) -> &'static [&'static str] {
+        match self {
+            Self::Every => &["n", "function", "sequence"],
+            Self::Fast | Self::Slow => &["factor", "sequence"],
+            Self::Rev | Self::Palindrome => &["sequence"],
+            Self::Rotate => &["amount", "sequence"],
+            Self::Degrade => &["probability", "sequence"],
+            Self::Euclid => &["hits", "steps", "rotation", "sequence"],
+            Self::Humanize => &["timing", "velocity", "sequence"],
+            Self::Transpose => &["notes", "semitones"],
+            Self::Invert => &["chord", "n"],
+            Self::Drop2 | Self::ChordTones => &["chord"],
+            Self::Voicing => &["chord", "low", "high"],
+            Self::Octave => &["notes", "k"],
+            Self::Midi | Self::Freq => &["note"],
+            Self::NoteFromMidi => &["n"],
+            Self::Interval => &["a", "b"],
+            Self::Scale => &["root", "name"],
+            Self::Quantize => &["notes", "scale"],
+            Self::Prog => &["numerals", "key"],
+            Self::VoiceLead => &["chords", "voices", "low", "high"],
+            Self::Recognize => &["notes"],
+            Self::Edo => &["steps", "reference", "frequency"],
+            Self::Just => &["ratios", "reference", "frequency"],
+            Self::Scala => &["scale", "mapping"],
+        }
+    }
+
+    /// Returns the value of a parameter, which can be left out.
+    pub fn default_argument(self, parameter: &str) -> Option<Value> {
+        let note = |midi| Value::Note {
+            note: Note::from_midi(midi).expect("default notes are in range"),
+        };
+
+        match (self, parameter) {
+            (Self::Euclid, "rotation") => Some(Value::Integer { value: 0 }),
+            // Without a sequence, the rhythm plays `c4` on every hit.
+            (Self::Euclid, "sequence") => Some(note(60)),
+            (Self::VoiceLead, "voices") => Some(Value::Integer { value: 4 }),
+            (Self::VoiceLead, "low") => Some(note(48)),
+            (Self::VoiceLead, "high") => Some(note(84)),
+            (Self::Edo | Self::Just, "reference") => Some(note(69)),
+            (Self::Edo | Self::Just, "frequency") => Some(Value::Float {
+                float: A4_FREQUENCY,
+            }),
+            // Without a keyboard mapping, the scale starts at `c4`.
+            (Self::Scala, "mapping") => Some(Value::String {
+                string: String::new(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the function transforms a sequence, which is
+    /// rendered into a pattern before the call.
+    pub fn is_sequence_function(self) -> bool {
+        self.parameters().last() == Some(&"sequence")
+    }
+}
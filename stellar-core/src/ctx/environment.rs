@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use crate::analysis::resolve::SymbolId;
+
+use super::value::Value;
+
+/// Values of local `let` variables of one channel. Every sequence being
+/// played has its own frame, so that variables of recursive sequences
+/// don't overwrite each other. Global variables are shared by channels
+/// and kept by the interpreter.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    frames: Vec<HashMap<SymbolId, Value>>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self {
+            frames: vec![HashMap::new()],
+        }
+    }
+}
+
+impl Environment {
+    /// Starts a frame of a sequence.
+    pub fn push(&mut self) {
+        self.frames.push(HashMap::new());
+    }
+
+    /// Ends the innermost frame. The global one is never removed.
+    pub fn pop(&mut self) {
+        if self.frames.len() > 1 {
+            self.frames.pop();
+        }
+    }
+
+    pub fn define(&mut self, id: SymbolId, value: Value) {
+        self.frames
+            .last_mut()
+            .expect("global frame is never removed")
+            .insert(id, value);
+    }
+
+    /// Returns the value of a variable from the innermost frame defining it.
+    pub fn get(&self, id: SymbolId) -> Option<&Value> {
+        self.frames.iter().rev().find_map(|frame| frame.get(&id))
+    }
+
+    /// Changes the value of a defined variable. Returns `false` if it is
+    /// not defined yet.
+    pub fn assign(&mut self, id: SymbolId, value: Value) -> bool {
+        match self
+            .frames
+            .iter_mut()
+            .rev()
+            .find_map(|frame| frame.get_mut(&id))
+        {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => false,
+        }
+    }
+}
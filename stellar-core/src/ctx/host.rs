@@ -0,0 +1,74 @@
+//! Functions and values, which an application embedding Stellar provides
+//! to the scripts it runs.
+
+use std::collections::HashMap;
+
+use crate::{analysis::resolve::is_predefined, syntax::string_id::StringId};
+
+use super::value::Value;
+
+/// Function implemented by the host. It gets the values of the arguments
+/// and returns its result, or a message explaining why it failed.
+pub type NativeFunction = dyn Fn(&[Value]) -> Result<Value, String>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostError {
+    /// Name of a builtin function, note, chord or key, which scripts would
+    /// keep referring to instead of the host's definition.
+    ReservedName { name: String },
+}
+
+#[derive(Default)]
+pub struct Host {
+    functions: HashMap<StringId, Box<NativeFunction>>,
+    globals: HashMap<StringId, Value>,
+}
+
+impl Host {
+    /// Makes a function callable by name. Names of builtin functions,
+    /// notes, chords and keys can't be taken.
+    pub fn register_fn(
+        &mut self,
+        name: &str,
+        function: impl Fn(&[Value]) -> Result<Value, String> + 'static,
+    ) -> Result<(), HostError> {
+        check_name(name)?;
+        self.functions
+            .insert(StringId::new(name), Box::new(function));
+
+        Ok(())
+    }
+
+    /// Sets the value of a global, which scripts can read, but not assign.
+    /// Names of builtin functions, notes, chords and keys can't be taken.
+    pub fn set_global(&mut self, name: &str, value: Value) -> Result<(), HostError> {
+        check_name(name)?;
+        self.globals.insert(StringId::new(name), value);
+
+        Ok(())
+    }
+
+    pub fn function(&self, name: StringId) -> Option<&NativeFunction> {
+        self.functions.get(&name).map(Box::as_ref)
+    }
+
+    pub fn global(&self, name: StringId) -> Option<&Value> {
+        self.globals.get(&name)
+    }
+
+    /// Returns `true` if a function or a global has the name.
+    pub fn contains(&self, name: &str) -> bool {
+        let name = StringId::new(name);
+
+        self.functions.contains_key(&name) || self.globals.contains_key(&name)
+    }
+}
+
+fn check_name(name: &str) -> Result<(), HostError> {
+    match is_predefined(name) {
+        true => Err(HostError::ReservedName {
+            name: name.to_owned(),
+        }),
+        false => Ok(()),
+    }
+}
@@ -0,0 +1,2008 @@
+//! Tree-walking interpreter: evaluates a resolved program and schedules
+//! the events it plays on a virtual timeline instead of sleeping between
+//! them. The result is the same [event timeline](crate::ir) backends
+//! consume.
+//!
+//! Every channel is a coroutine, which the [scheduler](super::scheduler)
+//! resumes whenever the virtual clock reaches the beat it waits for, so
+//! channels sharing variables see each other's changes in time order.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    analysis::{
+        fold::{fold, Constant, Constants},
+        resolve::{Resolution, ScopeKind, SymbolId, SymbolKind, Target},
+    },
+    audio::{
+        ctx::DEFAULT_BPM,
+        tempo::{Curve, Ramp, TempoError, TempoMap},
+    },
+    ir::{Channel, Event, EventKind, Ir},
+    pattern::{
+        self,
+        groove::{swing_delay, Groove, GrooveError, Humanize, DEFAULT_GROOVE_STEP},
+        rhythm::{euclid, poly, DEFAULT_POLY_SPAN, DEFAULT_STEP},
+        Pattern, PatternError, BEATS_PER_BAR,
+    },
+    random::Rng,
+    rational::Rational,
+    syntax::{
+        ast::{
+            BinaryOperatorKind, Block, Expression, PrefixOperatorKind, RampCurve, Statement,
+            TimeUnit,
+        },
+        location::{Span, Spanned},
+        string_id::StringId,
+        token::{Identifier, Unit},
+    },
+    theory::{
+        chord::Chord,
+        key::Key,
+        note::Note,
+        parse_name,
+        tuning::{Tuning, TuningError},
+    },
+};
+
+use super::{
+    builtins::Builtin,
+    environment::Environment,
+    limits::{resolve_file, Budget, Limit, Limits},
+    properties::{is_evaluated, Properties, PropertyError, Setting, PARAMS},
+    scheduler::Scheduler,
+    stdlib,
+    value::{SampleHandle, Sequence, Sound, Value},
+};
+
+/// Beat, after which the program is stopped, so that endless repeats end.
+pub const DEFAULT_HORIZON: Rational = Rational::integer(1024);
+
+/// Maximal number of blocks a channel runs inside each other, such as
+/// sequences playing each other.
+pub const MAX_DEPTH: usize = 1024;
+
+/// Maximal number of sequences passed to functions inside each other. It is
+/// lower than [`MAX_DEPTH`], because every one of them is rendered into a
+/// pattern recursively.
+pub(crate) const MAX_RENDER_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Seed of the random number generator used by `degrade`, `humanize`
+    /// and random arpeggios.
+    pub seed: u64,
+    pub horizon: Rational,
+    pub limits: Limits,
+    /// Directory scripts may read tuning and groove files from, which
+    /// their paths are relative to. Scripts can't read any files if it is
+    /// not set.
+    pub files: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            horizon: DEFAULT_HORIZON,
+            limits: Limits::default(),
+            files: None,
+        }
+    }
+}
+
+/// Runs a resolved program and returns the events it plays.
+pub fn interpret(
+    statements: &[Statement],
+    resolution: &Resolution,
+    options: &Options,
+) -> Result<Ir, RuntimeError> {
+    let mut interpreter = Interpreter {
+        resolution,
+        constants: fold(statements, resolution),
+        horizon: options.horizon,
+        sequences: HashMap::new(),
+        samples: HashMap::new(),
+        globals: HashMap::new(),
+        spawned: Vec::new(),
+        tempo: TempoMap::new(DEFAULT_BPM).expect("default tempo is valid"),
+        tuning: Tuning::default(),
+        rng: Rng::new(options.seed),
+        budget: Budget::new(options.limits),
+        files: options.files.clone(),
+    };
+
+    interpreter.collect(statements);
+
+    let output = Output::Events(Vec::new());
+    let mut main = Task::new(Rational::ZERO, Environment::default(), output, 0);
+    main.frames.push(Frame::new(
+        statements,
+        Rc::new(State::default()),
+        FrameKind::Block,
+    ));
+
+    Ok(Ir {
+        channels: interpreter.schedule(main)?,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RuntimeError {
+    UndefinedName {
+        identifier: Identifier,
+    },
+    /// Variable used before its `let` statement was run.
+    UninitializedVariable {
+        identifier: Identifier,
+    },
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+        span: Span,
+    },
+    InvalidOperands {
+        operator: BinaryOperatorKind,
+        left: &'static str,
+        right: &'static str,
+        span: Span,
+    },
+    InvalidOperand {
+        operator: PrefixOperatorKind,
+        operand: &'static str,
+        span: Span,
+    },
+    InvalidAssignment {
+        span: Span,
+    },
+    DivisionByZero {
+        span: Span,
+    },
+    /// Arithmetic result, which doesn't fit into the range of numbers.
+    Overflow {
+        span: Span,
+    },
+    /// Note outside of the range from [`Note::LOWEST`] to
+    /// [`Note::HIGHEST`].
+    NoteOutOfRange {
+        span: Span,
+    },
+    /// Scale degree outside of a block with a key, or degree `0` of a
+    /// scale.
+    InvalidDegree {
+        span: Span,
+    },
+    InvalidCount {
+        span: Span,
+    },
+    /// Name of a scale, which isn't known, or an empty list of intervals.
+    UnknownScale {
+        name: String,
+        span: Span,
+    },
+    /// Chord, which can't be voiced with the given number of voices in
+    /// the given range.
+    NoVoicing {
+        span: Span,
+    },
+    IndexOutOfBounds {
+        index: i64,
+        length: usize,
+        span: Span,
+    },
+    /// Roman numeral of a progression, which isn't valid: `prog("I X")`.
+    InvalidNumeral {
+        numeral: String,
+        span: Span,
+    },
+    NegativeWait {
+        span: Span,
+    },
+    NotCallable {
+        found: &'static str,
+        span: Span,
+    },
+    MissingArgument {
+        function: Builtin,
+        parameter: &'static str,
+        span: Span,
+    },
+    UnknownArgument {
+        function: Builtin,
+        name: Identifier,
+    },
+    /// Argument passed by the name of a parameter, which was already given:
+    /// `fast(factor: 2, factor: 3)`.
+    DuplicateArgument {
+        function: Builtin,
+        name: Identifier,
+    },
+    TooManyArguments {
+        function: Builtin,
+        span: Span,
+    },
+    /// Error of a function the embedding application registered.
+    Native {
+        function: StringId,
+        message: String,
+        span: Span,
+    },
+    /// Endless `repeat`, which doesn't wait.
+    EmptyLoop {
+        span: Span,
+    },
+    /// More sequences played inside each other than [`MAX_DEPTH`], or
+    /// passed to functions inside each other than [`MAX_RENDER_DEPTH`].
+    StackOverflow {
+        span: Span,
+    },
+    LimitExceeded {
+        limit: Limit,
+        span: Span,
+    },
+    Property(PropertyError),
+    Pattern {
+        error: PatternError,
+        span: Span,
+    },
+    Tempo {
+        error: TempoError,
+        span: Span,
+    },
+    Tuning {
+        error: TuningError,
+        span: Span,
+    },
+    /// Groove template, which is neither builtin nor found in the
+    /// directory set by [`Options::files`].
+    Groove {
+        error: GrooveError,
+        span: Span,
+    },
+    /// File outside of the directory set by [`Options::files`], or any
+    /// file if it is not set. Scripts aren't told whether it exists.
+    FileAccess {
+        path: String,
+        span: Span,
+    },
+    /// Note the keyboard mapping of the tuning leaves without a
+    /// frequency.
+    UnmappedNote {
+        note: Note,
+        span: Span,
+    },
+}
+
+impl Spanned for RuntimeError {
+    fn span(&self) -> Span {
+        match self {
+            Self::UndefinedName { identifier } | Self::UninitializedVariable { identifier } => {
+                identifier.span()
+            }
+            Self::UnknownArgument { name, .. } | Self::DuplicateArgument { name, .. } => {
+                name.span()
+            }
+            Self::TypeMismatch { span, .. }
+            | Self::InvalidOperands { span, .. }
+            | Self::InvalidOperand { span, .. }
+            | Self::InvalidAssignment { span }
+            | Self::DivisionByZero { span }
+            | Self::Overflow { span }
+            | Self::NoteOutOfRange { span }
+            | Self::InvalidDegree { span }
+            | Self::InvalidCount { span }
+            | Self::UnknownScale { span, .. }
+            | Self::InvalidNumeral { span, .. }
+            | Self::NoVoicing { span }
+            | Self::IndexOutOfBounds { span, .. }
+            | Self::NegativeWait { span }
+            | Self::NotCallable { span, .. }
+            | Self::MissingArgument { span, .. }
+            | Self::TooManyArguments { span, .. }
+            | Self::Native { span, .. }
+            | Self::EmptyLoop { span }
+            | Self::StackOverflow { span }
+            | Self::LimitExceeded { span, .. }
+            | Self::Pattern { span, .. }
+            | Self::Tempo { span, .. }
+            | Self::Tuning { span, .. }
+            | Self::Groove { span, .. }
+            | Self::FileAccess { span, .. }
+            | Self::UnmappedNote { span, .. } => *span,
+            Self::Property(error) => error.span(),
+        }
+    }
+}
+
+/// Settings of the block being run.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct State {
+    pub(crate) properties: Properties,
+    /// Template named by the `groove` property.
+    pub(crate) groove: Option<Rc<Groove>>,
+    /// Values of properties passed on to backends.
+    pub(crate) params: HashMap<StringId, Value>,
+}
+
+impl State {
+    /// Returns the state of a nested `with` block, loading the groove
+    /// template it names from the builtin ones or from `files`, like Scala
+    /// files.
+    pub(crate) fn with(
+        &self,
+        settings: &[(Setting, Span)],
+        files: Option<&Path>,
+    ) -> Result<Self, RuntimeError> {
+        let mut groove = self.groove.clone();
+        for (setting, span) in settings {
+            if let Setting::Groove(name) = setting {
+                let name = name.resolve();
+                let error = |error| RuntimeError::Groove { error, span: *span };
+
+                let loaded = match (Groove::builtin(&name), files) {
+                    (Some(builtin), _) => builtin,
+                    (None, None) => return Err(error(GrooveError::NotFound { name })),
+                    (None, Some(_)) => {
+                        let file = Groove::file_name(&name).map_err(error)?;
+                        let path = resolve_file(files, &file, *span)?;
+
+                        Groove::read(&name, &path).map_err(error)?
+                    }
+                };
+
+                groove = Some(Rc::new(loaded));
+            }
+        }
+
+        Ok(Self {
+            properties: self
+                .properties
+                .with_settings(settings)
+                .map_err(RuntimeError::Property)?,
+            groove,
+            params: self.params.clone(),
+        })
+    }
+
+    /// Moves a sound played at `offset` by the swing and the groove
+    /// template of the block, both following the grid from the start of
+    /// the channel or the rendered pattern.
+    pub(crate) fn groove(
+        &self,
+        mut event: pattern::Event<Sound>,
+        offset: Rational,
+        span: Span,
+    ) -> Result<pattern::Event<Sound>, RuntimeError> {
+        let overflow = || RuntimeError::Overflow { span };
+        let beat = offset.checked_add(event.start).ok_or_else(overflow)?;
+
+        let mut shift = match self.properties.swing {
+            Some(amount) => swing_delay(beat, amount, DEFAULT_GROOVE_STEP),
+            None => Rational::ZERO,
+        };
+        if let Some(groove) = &self.groove {
+            let (offset, velocity) = groove.deviation(beat);
+
+            shift = shift.checked_add(offset).ok_or_else(overflow)?;
+            event.velocity = (event.velocity * velocity).clamp(0.0, 1.0);
+        }
+
+        // Events can't be moved before the start.
+        let shift = shift.max(-beat);
+        event.start = event.start.checked_add(shift).ok_or_else(overflow)?;
+
+        Ok(event)
+    }
+}
+
+/// Where played sounds go: events of a channel, or a pattern, which a
+/// builtin function transforms.
+pub(crate) enum Output {
+    Events(Vec<Event>),
+    Pattern(Vec<pattern::Event<Sound>>),
+}
+
+impl Output {
+    pub(crate) fn sound(
+        &mut self,
+        offset: Rational,
+        event: pattern::Event<Sound>,
+        key: Option<Key>,
+        span: Span,
+    ) {
+        let events = match self {
+            Self::Events(events) => events,
+            Self::Pattern(events) => {
+                events.push(pattern::Event {
+                    start: offset + event.start,
+                    ..event
+                });
+                return;
+            }
+        };
+
+        let beat = offset + event.start;
+
+        match event.value {
+            Sound::Note(note) => {
+                events.push(Event {
+                    beat,
+                    kind: EventKind::NoteOn {
+                        note,
+                        velocity: event.velocity,
+                        key,
+                    },
+                    span,
+                });
+                events.push(Event {
+                    beat: offset + event.end(),
+                    kind: EventKind::NoteOff { note },
+                    span,
+                });
+            }
+            Sound::Sample(sample) => events.push(Event {
+                beat,
+                kind: EventKind::SampleTrigger {
+                    path: sample.path,
+                    velocity: event.velocity,
+                },
+                span,
+            }),
+        }
+    }
+
+    /// Returns the events of a channel. Patterns have none.
+    pub(crate) fn events(&self) -> &[Event] {
+        match self {
+            Self::Events(events) => events,
+            Self::Pattern(_) => &[],
+        }
+    }
+
+    /// Adds an event, which doesn't play anything. Patterns ignore them.
+    pub(crate) fn control(&mut self, beat: Rational, kind: EventKind, span: Span) {
+        if let Self::Events(events) = self {
+            events.push(Event { beat, kind, span });
+        }
+    }
+
+    pub(crate) fn into_channel(self) -> Channel {
+        match self {
+            Self::Events(events) => Channel::new(events),
+            Self::Pattern(_) => Channel::default(),
+        }
+    }
+}
+
+/// Runs statements of one channel, or renders a sequence into a pattern.
+struct Task<'a> {
+    cursor: Rational,
+    /// Blocks being run, innermost last.
+    frames: Vec<Frame<'a>>,
+    environment: Environment,
+    output: Output,
+    /// Number of sequences rendered into patterns around the task.
+    depth: usize,
+}
+
+impl<'a> Task<'a> {
+    fn new(cursor: Rational, environment: Environment, output: Output, depth: usize) -> Self {
+        Self {
+            cursor,
+            frames: Vec::new(),
+            environment,
+            output,
+            depth,
+        }
+    }
+}
+
+struct Frame<'a> {
+    statements: &'a [Statement],
+    /// Index of the next statement to run.
+    index: usize,
+    state: Rc<State>,
+    kind: FrameKind,
+}
+
+impl<'a> Frame<'a> {
+    fn new(statements: &'a [Statement], state: Rc<State>, kind: FrameKind) -> Self {
+        Self {
+            statements,
+            index: 0,
+            state,
+            kind,
+        }
+    }
+}
+
+enum FrameKind {
+    Block,
+    /// Body of a `repeat`, run again until no iterations remain, or
+    /// forever if the count is `None`.
+    Repeat {
+        remaining: Option<usize>,
+        /// Beat, at which the current iteration started.
+        start: Rational,
+        span: Span,
+    },
+    /// Body of a `with` block, which restores the changed parameters.
+    With {
+        previous: Rc<State>,
+        changed: Vec<(StringId, Span)>,
+    },
+    /// Body of a played sequence, which has its own environment frame.
+    Sequence,
+}
+
+struct Interpreter<'a> {
+    resolution: &'a Resolution,
+    constants: Constants,
+    horizon: Rational,
+    sequences: HashMap<SymbolId, &'a Block>,
+    samples: HashMap<StringId, SampleHandle>,
+    /// Variables of the global scope, shared by all channels.
+    globals: HashMap<SymbolId, Value>,
+    /// Channels started by the task being run.
+    spawned: Vec<Task<'a>>,
+    tempo: TempoMap,
+    /// Tuning set by the last `tuning` statement, which `freq` uses.
+    tuning: Tuning,
+    rng: Rng,
+    budget: Budget,
+    /// Directory files are read from, see [`Options::files`].
+    files: Option<PathBuf>,
+}
+
+impl<'a> Interpreter<'a> {
+    fn collect(&mut self, statements: &'a [Statement]) {
+        Statement::walk(statements, &mut |statement, _| {
+            if let Statement::Sequence { name, block } = statement {
+                if let Some(id) = self.resolution.definition(name) {
+                    self.sequences.insert(id, block);
+                }
+            }
+        });
+    }
+
+    /// Runs channels, resuming the one waiting for the earliest beat
+    /// first, until all of them end or reach the horizon.
+    fn schedule(&mut self, main: Task<'a>) -> Result<Vec<Channel>, RuntimeError> {
+        let mut scheduler = Scheduler::default();
+        let mut channels = Vec::new();
+
+        scheduler.spawn(main.cursor, main);
+        channels.push(Channel::default());
+
+        while let Some((id, mut task)) = scheduler.resume() {
+            let wake = self.resume(&mut task)?;
+
+            for spawned in std::mem::take(&mut self.spawned) {
+                scheduler.spawn(spawned.cursor, spawned);
+                channels.push(Channel::default());
+            }
+
+            match wake {
+                Some(beat) => scheduler.schedule(id, beat, task),
+                None => channels[id.index()] = task.output.into_channel(),
+            }
+        }
+
+        Ok(channels)
+    }
+
+    /// Runs a task until it waits or ends. Returns the beat to resume it
+    /// at, or `None` if it has ended.
+    fn resume(&mut self, task: &mut Task<'a>) -> Result<Option<Rational>, RuntimeError> {
+        let start = task.cursor;
+
+        loop {
+            if task.cursor >= self.horizon {
+                return Ok(None);
+            }
+
+            if task.cursor > start {
+                return Ok(Some(task.cursor));
+            }
+
+            let Some(frame) = task.frames.last_mut() else {
+                return Ok(None);
+            };
+
+            let played = task.output.events().len();
+
+            let statements = frame.statements;
+            match statements.get(frame.index) {
+                Some(statement) => {
+                    frame.index += 1;
+                    let state = frame.state.clone();
+
+                    self.run_statement(statement, &state, task)?;
+                }
+                None => self.end_frame(task)?,
+            }
+
+            self.budget
+                .events(&task.output.events()[played..], &self.tempo)?;
+        }
+    }
+
+    /// Runs a task, which doesn't play on its own channel, to the end.
+    fn complete(&mut self, task: &mut Task<'a>) -> Result<(), RuntimeError> {
+        while self.resume(task)?.is_some() {}
+
+        Ok(())
+    }
+
+    fn end_frame(&mut self, task: &mut Task<'a>) -> Result<(), RuntimeError> {
+        let frame = task.frames.last_mut().expect("task has a frame");
+
+        if let FrameKind::Repeat {
+            remaining,
+            start,
+            span,
+        } = &mut frame.kind
+        {
+            if remaining.is_none() && task.cursor == *start {
+                return Err(RuntimeError::EmptyLoop { span: *span });
+            }
+
+            *remaining = remaining.map(|remaining| remaining - 1);
+            if *remaining != Some(0) {
+                *start = task.cursor;
+                frame.index = 0;
+
+                return Ok(());
+            }
+        }
+
+        let frame = task.frames.pop().expect("task has a frame");
+
+        match frame.kind {
+            FrameKind::With { previous, changed } => {
+                for (name, span) in changed {
+                    let change = param_change(name, previous.params.get(&name));
+                    task.output.control(task.cursor, change, span);
+                }
+            }
+            FrameKind::Sequence => task.environment.pop(),
+            FrameKind::Block | FrameKind::Repeat { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    fn run_statement(
+        &mut self,
+        statement: &'a Statement,
+        state: &Rc<State>,
+        task: &mut Task<'a>,
+    ) -> Result<(), RuntimeError> {
+        self.budget.step(statement.span())?;
+        let cursor = task.cursor;
+
+        match statement {
+            Statement::Wait { expression } => {
+                let value = self.evaluate(expression, state, task)?;
+                task.cursor = wait(&value, cursor, &self.tempo, expression.span())?;
+            }
+            Statement::Play {
+                expression,
+                parallel,
+            } => {
+                let value = self.evaluate(expression, state, task)?;
+
+                if *parallel {
+                    self.spawn(&value, state, task, expression.span())?;
+                } else {
+                    self.play(&value, state, task, expression.span())?;
+                }
+            }
+            Statement::With {
+                properties: changes,
+                block,
+            } => {
+                Setting::check_unique(changes).map_err(RuntimeError::Property)?;
+
+                let mut settings = Vec::new();
+                for property in changes {
+                    let span = property.value.span();
+                    let setting = match is_evaluated(&property.name.name().resolve()) {
+                        true => {
+                            let value = self.evaluate(&property.value, state, task)?;
+                            Setting::from_value(property.name, &value, span)
+                        }
+                        false => Setting::parse(property),
+                    }
+                    .map_err(RuntimeError::Property)?;
+
+                    settings.push((setting, span));
+                }
+                let mut nested = state.with(&settings, self.files.as_deref())?;
+
+                let mut changed = Vec::new();
+                for property in changes {
+                    let name = property.name.name();
+                    if !PARAMS.contains(&name.resolve().as_str()) {
+                        continue;
+                    }
+
+                    let value = match &property.value {
+                        // Names, such as `synth: pad`, are not variables.
+                        Expression::Identifier(identifier) => Value::String {
+                            string: identifier.name().resolve(),
+                        },
+                        value => self.evaluate(value, state, task)?,
+                    };
+
+                    let change = param_change(name, Some(&value));
+                    task.output.control(cursor, change, property.span());
+
+                    nested.params.insert(name, value);
+                    changed.push((name, property.span()));
+                }
+
+                let kind = FrameKind::With {
+                    previous: state.clone(),
+                    changed,
+                };
+                let frame = Frame::new(&block.statements, Rc::new(nested), kind);
+                task.frames.push(frame);
+            }
+            Statement::Let { name, value } => {
+                let value = self.evaluate(value, state, task)?;
+
+                if let Some(id) = self.resolution.definition(name) {
+                    if self.is_global(id) {
+                        self.globals.insert(id, value);
+                    } else {
+                        task.environment.define(id, value);
+                    }
+                }
+            }
+            Statement::Poly { arguments, block } => {
+                let counts = arguments
+                    .iter()
+                    .map(|argument| {
+                        let count = self.evaluate(&argument.value, state, task)?;
+                        expect_count(&count, argument.value.span())
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let mut patterns = Vec::new();
+                for voice in &block.statements {
+                    let frame =
+                        Frame::new(std::slice::from_ref(voice), state.clone(), FrameKind::Block);
+                    let (events, length) =
+                        self.render(task, Some(frame), statement.span(), |_, _| Ok(()))?;
+
+                    patterns.push(into_pattern(events, length, &state.properties));
+                }
+
+                self.budget.poly(&patterns, &counts, statement.span())?;
+                let stretched = poly(patterns, &counts, DEFAULT_POLY_SPAN).map_err(|error| {
+                    RuntimeError::Pattern {
+                        error,
+                        span: statement.span(),
+                    }
+                })?;
+
+                for event in stretched.into_events() {
+                    task.output
+                        .sound(cursor, event, state.properties.key, statement.span());
+                }
+
+                task.cursor = advance(cursor, DEFAULT_POLY_SPAN, statement.span())?;
+            }
+            Statement::Tempo { bpm, ramp } => {
+                let span = statement.span();
+                let bpm = self.evaluate_f64(bpm, state, task)?;
+
+                let ramp = match ramp {
+                    Some(ramp) => {
+                        let unit = match ramp.unit {
+                            TimeUnit::Beats => Rational::ONE,
+                            TimeUnit::Bars => BEATS_PER_BAR,
+                        };
+                        let length = self.evaluate(&ramp.length, state, task)?;
+
+                        Some(Ramp {
+                            target: self.evaluate_f64(&ramp.target, state, task)?,
+                            length: expect_rational(&length, "number", ramp.length.span())? * unit,
+                            curve: match ramp.curve {
+                                RampCurve::Linear => Curve::Linear,
+                                RampCurve::Exponential => Curve::Exponential,
+                            },
+                        })
+                    }
+                    None => None,
+                };
+
+                match ramp {
+                    Some(ramp) => self.tempo.ramp(cursor, bpm, ramp),
+                    None => self.tempo.set(cursor, bpm),
+                }
+                .map_err(|error| RuntimeError::Tempo { error, span })?;
+
+                task.output
+                    .control(cursor, EventKind::TempoChange { bpm, ramp }, span);
+            }
+            Statement::Tuning { tuning } => {
+                let span = statement.span();
+                let tuning = match self.evaluate(tuning, state, task)? {
+                    Value::Tuning { tuning } => tuning,
+                    value => {
+                        return Err(RuntimeError::TypeMismatch {
+                            expected: "tuning",
+                            found: value.type_name(),
+                            span,
+                        })
+                    }
+                };
+
+                self.tuning = (*tuning).clone();
+                task.output
+                    .control(cursor, EventKind::TuningChange { tuning }, span);
+            }
+            Statement::Repeat { count, block } => {
+                let remaining = match count {
+                    Some(count) => {
+                        let value = self.evaluate(count, state, task)?;
+                        Some(expect_count(&value, count.span())?)
+                    }
+                    None => None,
+                };
+
+                if remaining != Some(0) {
+                    let kind = FrameKind::Repeat {
+                        remaining,
+                        start: cursor,
+                        span: statement.span(),
+                    };
+                    task.frames
+                        .push(Frame::new(&block.statements, state.clone(), kind));
+                }
+            }
+            Statement::Expression(expression) => {
+                self.evaluate(expression, state, task)?;
+            }
+            Statement::Attributed { statement, .. } => {
+                self.run_statement(statement, state, task)?
+            }
+            Statement::Sequence { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    /// Plays a value at the cursor of a task. Sequences move the cursor
+    /// to their end, notes and samples don't wait.
+    fn play(
+        &mut self,
+        value: &Value,
+        state: &Rc<State>,
+        task: &mut Task<'a>,
+        span: Span,
+    ) -> Result<(), RuntimeError> {
+        if let Some(notes) = notes(value) {
+            let properties = &state.properties;
+            let notes = notes
+                .into_iter()
+                .map(|note| {
+                    properties
+                        .transpose_note(note)
+                        .ok_or(RuntimeError::NoteOutOfRange { span })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let pattern = properties
+                .expand_chord(&notes, properties.length, &mut self.rng)
+                .map_err(|error| RuntimeError::Pattern { error, span })?;
+
+            for event in pattern.into_events() {
+                let note = event.value;
+                let event = state.groove(event.with_value(Sound::Note(note)), task.cursor, span)?;
+                task.output
+                    .sound(task.cursor, event, state.properties.key, span);
+            }
+
+            return Ok(());
+        }
+
+        match value {
+            Value::Sample { sample } => {
+                let event = pattern::Event::new(
+                    Rational::ZERO,
+                    state.properties.length,
+                    Sound::Sample(*sample),
+                );
+                let event = state.groove(event, task.cursor, span)?;
+                task.output
+                    .sound(task.cursor, event, state.properties.key, span);
+            }
+            Value::Sequence {
+                sequence: Sequence::Defined(id),
+            } => {
+                // The statements of the sequence are run by the task.
+                if let Some(block) = self.sequences.get(id).copied() {
+                    self.budget.depth(task.frames.len(), span)?;
+
+                    task.environment.push();
+                    task.frames.push(Frame::new(
+                        &block.statements,
+                        state.clone(),
+                        FrameKind::Sequence,
+                    ));
+                }
+            }
+            Value::Sequence {
+                sequence: Sequence::Pattern(pattern),
+            } => {
+                for event in pattern.events() {
+                    task.output
+                        .sound(task.cursor, event.clone(), state.properties.key, span);
+                }
+
+                task.cursor = advance(task.cursor, pattern.length(), span)?;
+            }
+            // Lists of samples and sequences play every element at once.
+            Value::List { values } => {
+                let mut length = Rational::ZERO;
+                for value in values {
+                    let (events, played) = self.render_value(value, state, task, span)?;
+
+                    for event in events {
+                        task.output
+                            .sound(task.cursor, event, state.properties.key, span);
+                    }
+                    length = length.max(played);
+                }
+
+                task.cursor = advance(task.cursor, length, span)?;
+            }
+            _ => {
+                return Err(RuntimeError::TypeMismatch {
+                    expected: "playable value",
+                    found: value.type_name(),
+                    span,
+                })
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Plays a value on a new channel: `play! drums`. Inside patterns, it
+    /// is played without moving the cursor instead.
+    fn spawn(
+        &mut self,
+        value: &Value,
+        state: &Rc<State>,
+        task: &mut Task<'a>,
+        span: Span,
+    ) -> Result<(), RuntimeError> {
+        if let Output::Pattern(_) = task.output {
+            let (events, _) = self.render_value(value, state, task, span)?;
+
+            for event in events {
+                task.output
+                    .sound(task.cursor, event, state.properties.key, span);
+            }
+
+            return Ok(());
+        }
+
+        // The new channel starts with the parameters of the block it is
+        // started from, and sees its local variables.
+        let mut params: Vec<_> = state.params.iter().collect();
+        params.sort_by_key(|(name, _)| name.resolve());
+
+        let mut output = Output::Events(Vec::new());
+        for (name, value) in params {
+            output.control(task.cursor, param_change(*name, Some(value)), span);
+        }
+
+        self.budget.channel(span)?;
+
+        let mut channel = Task::new(task.cursor, task.environment.clone(), output, 0);
+        self.play(value, state, &mut channel, span)?;
+        self.budget.events(channel.output.events(), &self.tempo)?;
+        self.spawned.push(channel);
+
+        Ok(())
+    }
+
+    /// Runs a task rendering sounds into a pattern, which starts with a
+    /// frame, or with whatever `start` plays. Returns the sounds and the
+    /// number of beats the task waited.
+    fn render(
+        &mut self,
+        task: &mut Task<'a>,
+        frame: Option<Frame<'a>>,
+        span: Span,
+        start: impl FnOnce(&mut Self, &mut Task<'a>) -> Result<(), RuntimeError>,
+    ) -> Result<(Vec<pattern::Event<Sound>>, Rational), RuntimeError> {
+        if task.depth >= MAX_RENDER_DEPTH {
+            return Err(RuntimeError::StackOverflow { span });
+        }
+
+        let environment = std::mem::take(&mut task.environment);
+        let output = Output::Pattern(Vec::new());
+        let mut rendered = Task::new(Rational::ZERO, environment, output, task.depth + 1);
+        rendered.frames.extend(frame);
+
+        let result = start(self, &mut rendered).and_then(|()| self.complete(&mut rendered));
+
+        // Sequences cut off by the horizon don't end their frames.
+        for frame in &rendered.frames {
+            if let FrameKind::Sequence = frame.kind {
+                rendered.environment.pop();
+            }
+        }
+        task.environment = rendered.environment;
+        result?;
+
+        match rendered.output {
+            Output::Pattern(events) => Ok((events, rendered.cursor)),
+            Output::Events(_) => unreachable!("rendered task plays into a pattern"),
+        }
+    }
+
+    fn render_value(
+        &mut self,
+        value: &Value,
+        state: &Rc<State>,
+        task: &mut Task<'a>,
+        span: Span,
+    ) -> Result<(Vec<pattern::Event<Sound>>, Rational), RuntimeError> {
+        self.render(task, None, span, |interpreter, rendered| {
+            interpreter.play(value, state, rendered, span)
+        })
+    }
+
+    /// Turns a value into a pattern, which builtin functions transform.
+    fn pattern(
+        &mut self,
+        value: &Value,
+        state: &Rc<State>,
+        task: &mut Task<'a>,
+        span: Span,
+    ) -> Result<Pattern<Sound>, RuntimeError> {
+        if let Value::Sequence {
+            sequence: Sequence::Pattern(pattern),
+        } = value
+        {
+            return Ok(pattern.clone());
+        }
+
+        let (events, length) = self.render_value(value, state, task, span)?;
+
+        Ok(into_pattern(events, length, &state.properties))
+    }
+
+    /// Returns `true` if a variable is shared by all channels.
+    fn is_global(&self, id: SymbolId) -> bool {
+        let scope = self.resolution.symbol(id).scope;
+
+        self.resolution.scope(scope).kind == ScopeKind::Global
+    }
+
+    fn evaluate(
+        &mut self,
+        expression: &Expression,
+        state: &Rc<State>,
+        task: &mut Task<'a>,
+    ) -> Result<Value, RuntimeError> {
+        self.budget.step(expression.span())?;
+
+        // Pure expressions are evaluated before the program is run.
+        if let Some(constant) = self.constants.get(expression) {
+            let value = constant_value(constant);
+            self.budget.value(&value, expression.span())?;
+
+            return Ok(value);
+        }
+
+        match expression {
+            Expression::Integer { value, .. } => Ok(Value::Integer { value: *value }),
+            Expression::Float { value, .. } => Ok(Value::Float { float: *value }),
+            Expression::Quantity { value, unit, .. } => Ok(match unit {
+                Unit::Percent => Value::Float {
+                    float: value / 100.0,
+                },
+                Unit::Milliseconds => Value::Duration {
+                    seconds: value / 1000.0,
+                },
+                Unit::Seconds => Value::Duration { seconds: *value },
+            }),
+            Expression::String { value, .. } => Ok(Value::String {
+                string: value.resolve(),
+            }),
+            Expression::Bool { value, .. } => Ok(Value::Bool { value: *value }),
+            Expression::Identifier(identifier) => self.evaluate_identifier(identifier, task),
+            Expression::Degree { degree, span } => state
+                .properties
+                .key
+                .and_then(|key| key.degree(*degree))
+                .map(|note| Value::Note { note })
+                .ok_or(RuntimeError::InvalidDegree { span: *span }),
+            Expression::List { expressions, .. } => {
+                let value = Value::List {
+                    values: expressions
+                        .iter()
+                        .map(|expression| self.evaluate(expression, state, task))
+                        .collect::<Result<_, _>>()?,
+                };
+                self.budget.value(&value, expression.span())?;
+
+                Ok(value)
+            }
+            Expression::LoadSample { sample, .. } => match self.evaluate(sample, state, task)? {
+                Value::String { string } => {
+                    let path = StringId::new(string);
+                    let id = self.samples.len();
+                    let sample = *self
+                        .samples
+                        .entry(path)
+                        .or_insert(SampleHandle { id, path });
+
+                    Ok(Value::Sample { sample })
+                }
+                value => Err(RuntimeError::TypeMismatch {
+                    expected: "string",
+                    found: value.type_name(),
+                    span: sample.span(),
+                }),
+            },
+            Expression::Prefix { operator, operand } => {
+                let value = self.evaluate(operand, state, task)?;
+
+                prefix(operator.kind, value, expression.span())
+            }
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } if operator.kind == BinaryOperatorKind::Assign => {
+                let target = match left.as_ref() {
+                    Expression::Identifier(identifier) => self
+                        .resolution
+                        .symbol_of(identifier)
+                        .filter(|id| self.resolution.symbol(*id).kind == SymbolKind::Let)
+                        .map(|id| (id, *identifier)),
+                    _ => None,
+                };
+                let Some((id, identifier)) = target else {
+                    return Err(RuntimeError::InvalidAssignment { span: left.span() });
+                };
+
+                let value = self.evaluate(right, state, task)?;
+                let assigned = match self.globals.get_mut(&id) {
+                    Some(slot) => {
+                        *slot = value.clone();
+                        true
+                    }
+                    _ => task.environment.assign(id, value.clone()),
+                };
+                if !assigned {
+                    return Err(RuntimeError::UninitializedVariable { identifier });
+                }
+
+                Ok(value)
+            }
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let left_value = self.evaluate(left, state, task)?;
+                let right_value = self.evaluate(right, state, task)?;
+
+                let value = binary(operator.kind, left_value, right_value, expression.span())?;
+                self.budget.value(&value, expression.span())?;
+
+                Ok(value)
+            }
+            Expression::Index {
+                target,
+                index: position,
+                span,
+            } => {
+                let target_value = self.evaluate(target, state, task)?;
+                let position_value = self.evaluate(position, state, task)?;
+
+                index(
+                    &target_value,
+                    &position_value,
+                    target.span(),
+                    position.span(),
+                    *span,
+                )
+            }
+            Expression::Call {
+                callee,
+                arguments,
+                span,
+            } => {
+                let function = match self.evaluate(callee, state, task)? {
+                    Value::Function { function } => function,
+                    value => {
+                        return Err(RuntimeError::NotCallable {
+                            found: value.type_name(),
+                            span: callee.span(),
+                        })
+                    }
+                };
+
+                let mut values = Vec::new();
+                for argument in arguments {
+                    let value = self.evaluate(&argument.value, state, task)?;
+                    values.push((argument.name, value, argument.span()));
+                }
+
+                let arguments = bind(function, values, *span)?;
+                if !function.is_sequence_function() {
+                    let value =
+                        stdlib::call(function, &arguments, &self.tuning, self.files.as_deref())?;
+                    self.budget.value(&value, *span)?;
+
+                    return Ok(value);
+                }
+
+                let pattern = self.apply(function, arguments, state, task, *span)?;
+
+                Ok(Value::Sequence {
+                    sequence: Sequence::Pattern(pattern),
+                })
+            }
+        }
+    }
+
+    fn evaluate_identifier(
+        &self,
+        identifier: &Identifier,
+        task: &Task<'a>,
+    ) -> Result<Value, RuntimeError> {
+        let undefined = || RuntimeError::UndefinedName {
+            identifier: *identifier,
+        };
+
+        match self.resolution.target(identifier).ok_or_else(undefined)? {
+            Target::Symbol(id) => match self.resolution.symbol(id).kind {
+                SymbolKind::Sequence => Ok(Value::Sequence {
+                    sequence: Sequence::Defined(id),
+                }),
+                SymbolKind::Let => task
+                    .environment
+                    .get(id)
+                    .or_else(|| self.globals.get(&id))
+                    .cloned()
+                    .ok_or(RuntimeError::UninitializedVariable {
+                        identifier: *identifier,
+                    }),
+            },
+            Target::Builtin => {
+                let name = identifier.name().resolve();
+
+                if let Some(function) = Builtin::parse(&name) {
+                    Ok(Value::Function { function })
+                } else if let Some(name) = parse_name(&name) {
+                    Ok(Value::name(name))
+                } else {
+                    Err(undefined())
+                }
+            }
+        }
+    }
+
+    fn evaluate_f64(
+        &mut self,
+        expression: &Expression,
+        state: &Rc<State>,
+        task: &mut Task<'a>,
+    ) -> Result<f64, RuntimeError> {
+        let value = self.evaluate(expression, state, task)?;
+
+        value.as_f64().ok_or(RuntimeError::TypeMismatch {
+            expected: "number",
+            found: value.type_name(),
+            span: expression.span(),
+        })
+    }
+
+    /// Renders the sequence a function is called with, and calls it.
+    fn apply(
+        &mut self,
+        function: Builtin,
+        mut arguments: Vec<(Value, Span)>,
+        state: &Rc<State>,
+        task: &mut Task<'a>,
+        span: Span,
+    ) -> Result<Pattern<Sound>, RuntimeError> {
+        let (sequence, sequence_span) = arguments.pop().expect("sequence is the last parameter");
+        let pattern = self.pattern(&sequence, state, task, sequence_span)?;
+
+        let pattern = transform(
+            function,
+            pattern,
+            &arguments,
+            &mut self.rng,
+            &self.tempo,
+            &self.budget,
+            span,
+        )?;
+        self.budget.pattern(&pattern, span)?;
+
+        Ok(pattern)
+    }
+}
+
+/// Matches arguments of a call with the parameters of a function. Returns
+/// their values ordered like the parameters.
+pub(crate) fn bind(
+    function: Builtin,
+    arguments: Vec<(Option<Identifier>, Value, Span)>,
+    span: Span,
+) -> Result<Vec<(Value, Span)>, RuntimeError> {
+    let parameters = function.parameters();
+    let mut values: Vec<Option<(Value, Span)>> = vec![None; parameters.len()];
+
+    let (named, mut positional): (Vec<_>, Vec<_>) = arguments
+        .into_iter()
+        .partition(|(name, _, _)| name.is_some());
+
+    for (name, value, span) in named {
+        let name = name.expect("argument is named");
+        let index = parameters
+            .iter()
+            .position(|parameter| *parameter == name.name().resolve())
+            .ok_or(RuntimeError::UnknownArgument { function, name })?;
+
+        if values[index].is_some() {
+            return Err(RuntimeError::DuplicateArgument { function, name });
+        }
+
+        values[index] = Some((value, span));
+    }
+
+    // Positional arguments left over after the parameters without a
+    // default end with the sequence, so that the optional parameters before
+    // it can be left out: `euclid(3, 8, kick)`.
+    let required = parameters
+        .iter()
+        .zip(&values)
+        .filter(|(parameter, value)| {
+            value.is_none()
+                && **parameter != "sequence"
+                && function.default_argument(parameter).is_none()
+        })
+        .count();
+    if function.is_sequence_function()
+        && matches!(values.last(), Some(None))
+        && positional.len() > required
+    {
+        let (_, value, span) = positional.pop().expect("positional arguments are left");
+        *values.last_mut().expect("sequence is the last parameter") = Some((value, span));
+    }
+
+    for (_, value, span) in positional {
+        let slot = values
+            .iter_mut()
+            .find(|value| value.is_none())
+            .ok_or(RuntimeError::TooManyArguments { function, span })?;
+
+        *slot = Some((value, span));
+    }
+
+    values
+        .into_iter()
+        .zip(parameters)
+        .map(|(value, parameter)| {
+            value
+                .or_else(|| {
+                    function
+                        .default_argument(parameter)
+                        .map(|value| (value, span))
+                })
+                .ok_or(RuntimeError::MissingArgument {
+                    function,
+                    parameter,
+                    span,
+                })
+        })
+        .collect()
+}
+
+/// Calls a builtin function with the pattern of its sequence and the other
+/// arguments ordered like its parameters.
+pub(crate) fn transform(
+    function: Builtin,
+    pattern: Pattern<Sound>,
+    arguments: &[(Value, Span)],
+    rng: &mut Rng,
+    tempo: &TempoMap,
+    budget: &Budget,
+    span: Span,
+) -> Result<Pattern<Sound>, RuntimeError> {
+    let pattern_error = |error| RuntimeError::Pattern { error, span };
+
+    match (function, arguments) {
+        (Builtin::Fast, [(factor, factor_span)]) => pattern
+            .fast(expect_rational(factor, "number", *factor_span)?)
+            .map_err(pattern_error),
+        (Builtin::Slow, [(factor, factor_span)]) => pattern
+            .slow(expect_rational(factor, "number", *factor_span)?)
+            .map_err(pattern_error),
+        (Builtin::Rev, []) => Ok(pattern.rev()),
+        (Builtin::Palindrome, []) => Ok(pattern.palindrome()),
+        (Builtin::Rotate, [(amount, amount_span)]) => {
+            Ok(pattern.rotate(expect_rational(amount, "number", *amount_span)?))
+        }
+        (Builtin::Degrade, [(probability, probability_span)]) => {
+            let probability = expect_f64(probability, *probability_span)?;
+
+            pattern.degrade(probability, rng).map_err(pattern_error)
+        }
+        (Builtin::Every, [(n, n_span), (function, function_span)]) => {
+            let n = expect_count(n, *n_span)?;
+            // Only functions, which take just a sequence, can be passed.
+            let function = match function {
+                Value::Function { function } if function.parameters() == ["sequence"] => *function,
+                _ => {
+                    return Err(RuntimeError::TypeMismatch {
+                        expected: "function of a sequence",
+                        found: function.type_name(),
+                        span: *function_span,
+                    })
+                }
+            };
+
+            budget.reserve(n.saturating_mul(pattern.events().len()), span)?;
+            let transformed = transform(function, pattern.clone(), &[], rng, tempo, budget, span)?;
+
+            pattern.every(n, |_| Ok(transformed)).map_err(pattern_error)
+        }
+        (Builtin::Euclid, [(hits, hits_span), (steps, steps_span), (rotation, rotation_span)]) => {
+            let Value::Integer { value: rotation } = rotation else {
+                return Err(RuntimeError::TypeMismatch {
+                    expected: "integer",
+                    found: rotation.type_name(),
+                    span: *rotation_span,
+                });
+            };
+
+            let rhythm = euclid(
+                expect_count(hits, *hits_span)?,
+                expect_count(steps, *steps_span)?,
+                *rotation,
+                DEFAULT_STEP,
+            )
+            .map_err(pattern_error)?;
+            budget.reserve(
+                rhythm.events().len().saturating_mul(pattern.events().len()),
+                span,
+            )?;
+
+            // Every hit plays the whole sequence.
+            let mut result = Pattern::silence(rhythm.length());
+            for hit in rhythm.events() {
+                for event in pattern.events() {
+                    result.push(pattern::Event {
+                        start: hit.start + event.start,
+                        ..event.clone()
+                    });
+                }
+            }
+
+            Ok(result)
+        }
+        (Builtin::Humanize, [(timing, timing_span), (velocity, velocity_span)]) => {
+            let Value::Duration { seconds } = timing else {
+                return Err(RuntimeError::TypeMismatch {
+                    expected: "duration",
+                    found: timing.type_name(),
+                    span: *timing_span,
+                });
+            };
+
+            let humanize = Humanize {
+                timing: *seconds,
+                velocity: expect_f64(velocity, *velocity_span)?,
+            };
+
+            humanize
+                .apply(pattern, tempo, Rational::ZERO, rng)
+                .map_err(pattern_error)
+        }
+        _ => unreachable!("arguments are bound to the parameters of the function"),
+    }
+}
+
+/// Applies a prefix operator to an evaluated operand.
+pub(crate) fn prefix(
+    operator: PrefixOperatorKind,
+    operand: Value,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    match (operator, operand) {
+        (PrefixOperatorKind::Minus, Value::Integer { value }) => value
+            .checked_neg()
+            .map(|value| Value::Integer { value })
+            .ok_or(RuntimeError::Overflow { span }),
+        (PrefixOperatorKind::Minus, Value::Rational { rational }) => rational
+            .checked_neg()
+            .map(Value::number)
+            .ok_or(RuntimeError::Overflow { span }),
+        (PrefixOperatorKind::Minus, Value::Float { float }) => Ok(Value::Float { float: -float }),
+        (PrefixOperatorKind::Minus, Value::Duration { seconds }) => {
+            Ok(Value::Duration { seconds: -seconds })
+        }
+        (PrefixOperatorKind::Exclamation, Value::Bool { value }) => {
+            Ok(Value::Bool { value: !value })
+        }
+        (operator, operand) => Err(RuntimeError::InvalidOperand {
+            operator,
+            operand: operand.type_name(),
+            span,
+        }),
+    }
+}
+
+/// Applies a binary operator to evaluated operands.
+pub(crate) fn binary(
+    operator: BinaryOperatorKind,
+    left: Value,
+    right: Value,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    use BinaryOperatorKind::{Minus, Plus, Slash, Star};
+
+    let invalid = |left: &Value, right: &Value| RuntimeError::InvalidOperands {
+        operator,
+        left: left.type_name(),
+        right: right.type_name(),
+        span,
+    };
+
+    match (operator, &left, &right) {
+        (
+            Slash,
+            Value::Integer { .. }
+            | Value::Rational { .. }
+            | Value::Float { .. }
+            | Value::Duration { .. },
+            divisor,
+        ) if divisor.as_f64() == Some(0.0) => Err(RuntimeError::DivisionByZero { span }),
+        // Integers and fractions stay exact.
+        (
+            _,
+            Value::Integer { .. } | Value::Rational { .. },
+            Value::Integer { .. } | Value::Rational { .. },
+        ) => {
+            let (left, right) = (left.as_rational().unwrap(), right.as_rational().unwrap());
+
+            match operator {
+                Plus => left.checked_add(right),
+                Minus => left.checked_sub(right),
+                Star => left.checked_mul(right),
+                _ => left.checked_div(right),
+            }
+            .map(Value::number)
+            .ok_or(RuntimeError::Overflow { span })
+        }
+        (
+            _,
+            Value::Integer { .. } | Value::Rational { .. } | Value::Float { .. },
+            Value::Integer { .. } | Value::Rational { .. } | Value::Float { .. },
+        ) => {
+            let (left, right) = (left.as_f64().unwrap(), right.as_f64().unwrap());
+
+            let float = match operator {
+                Plus => left + right,
+                Minus => left - right,
+                Star => left * right,
+                _ => left / right,
+            };
+
+            Ok(Value::Float { float })
+        }
+        (Plus | Minus, Value::Duration { seconds: left }, Value::Duration { seconds: right }) => {
+            let seconds = match operator {
+                Plus => left + right,
+                _ => left - right,
+            };
+
+            Ok(Value::Duration { seconds })
+        }
+        (Star | Slash, Value::Duration { seconds }, factor) if factor.as_f64().is_some() => {
+            let factor = factor.as_f64().unwrap();
+            let seconds = match operator {
+                Star => seconds * factor,
+                _ => seconds / factor,
+            };
+
+            Ok(Value::Duration { seconds })
+        }
+        (Star, factor, Value::Duration { seconds }) if factor.as_f64().is_some() => {
+            Ok(Value::Duration {
+                seconds: seconds * factor.as_f64().unwrap(),
+            })
+        }
+        // Transposition by a number of semitones.
+        (Plus | Minus, Value::Note { .. } | Value::Chord { .. }, Value::Integer { value }) => {
+            let out_of_range = || RuntimeError::NoteOutOfRange { span };
+            let semitones = match operator {
+                Minus => value.checked_neg().ok_or_else(out_of_range)?,
+                _ => *value,
+            };
+            let semitones = i32::try_from(semitones).map_err(|_| out_of_range())?;
+
+            Ok(match left {
+                Value::Note { note } => Value::Note {
+                    note: note.transpose(semitones).ok_or_else(out_of_range)?,
+                },
+                Value::Chord { chord } => Value::Chord {
+                    chord: Chord {
+                        root: chord.root.transpose(semitones).ok_or_else(out_of_range)?,
+                        ..chord
+                    },
+                },
+                _ => unreachable!("left operand is a note or a chord"),
+            })
+        }
+        (Plus, Value::String { string: left }, Value::String { string: right }) => {
+            Ok(Value::String {
+                string: format!("{left}{right}"),
+            })
+        }
+        (Plus, Value::List { values: left }, Value::List { values: right }) => Ok(Value::List {
+            values: left.iter().chain(right).cloned().collect(),
+        }),
+        _ => Err(invalid(&left, &right)),
+    }
+}
+
+/// Reads a degree of a scale (`s[5]`), or an element of a list
+/// (`chords[0]`). Lists are indexed from `0`, and negative indices count
+/// from the end.
+pub(crate) fn index(
+    target: &Value,
+    index: &Value,
+    target_span: Span,
+    index_span: Span,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let Value::Integer { value: index } = index else {
+        return Err(RuntimeError::TypeMismatch {
+            expected: "integer",
+            found: index.type_name(),
+            span: index_span,
+        });
+    };
+
+    match target {
+        Value::Scale { scale } => scale
+            .degree(*index)
+            .map(|note| Value::Note { note })
+            .ok_or(RuntimeError::InvalidDegree { span }),
+        Value::List { values } => {
+            let length = values.len();
+            let position = match *index {
+                0.. => usize::try_from(*index).ok(),
+                _ => length.checked_sub(index.unsigned_abs() as usize),
+            };
+
+            position
+                .and_then(|position| values.get(position))
+                .cloned()
+                .ok_or(RuntimeError::IndexOutOfBounds {
+                    index: *index,
+                    length,
+                    span,
+                })
+        }
+        _ => Err(RuntimeError::TypeMismatch {
+            expected: "scale or list",
+            found: target.type_name(),
+            span: target_span,
+        }),
+    }
+}
+
+/// Returns notes of a note, a chord or a list of them.
+pub(crate) fn notes(value: &Value) -> Option<Vec<Note>> {
+    match value {
+        Value::Note { note } => Some(vec![*note]),
+        Value::Chord { chord } => Some(chord.notes()),
+        Value::List { values } => {
+            let mut notes = Vec::new();
+            for value in values {
+                notes.extend(self::notes(value)?);
+            }
+
+            Some(notes)
+        }
+        _ => None,
+    }
+}
+
+/// Collects rendered sounds into a pattern. Notes and samples, which don't
+/// wait, last until the end of the longest of them.
+pub(crate) fn into_pattern(
+    events: Vec<pattern::Event<Sound>>,
+    length: Rational,
+    properties: &Properties,
+) -> Pattern<Sound> {
+    let length = match length.is_zero() {
+        true => events
+            .iter()
+            .map(pattern::Event::end)
+            .max()
+            .unwrap_or(properties.length),
+        false => length,
+    };
+
+    Pattern::from_events(length, events)
+}
+
+pub(crate) fn param_change(name: StringId, value: Option<&Value>) -> EventKind {
+    EventKind::ParamChange {
+        name,
+        value: value.and_then(value_constant),
+    }
+}
+
+pub(crate) fn constant_value(constant: &Constant) -> Value {
+    match constant {
+        Constant::Number(number) => Value::number(*number),
+        Constant::Duration(seconds) => Value::Duration {
+            seconds: seconds.to_f64(),
+        },
+        Constant::String(string) => Value::String {
+            string: string.resolve(),
+        },
+        Constant::Bool(value) => Value::Bool { value: *value },
+        Constant::Note(note) => Value::Note { note: *note },
+        Constant::Chord(chord) => Value::Chord { chord: *chord },
+        Constant::List(elements) => Value::List {
+            values: elements.iter().map(constant_value).collect(),
+        },
+    }
+}
+
+/// Converts a value passed on to backends into a constant.
+fn value_constant(value: &Value) -> Option<Constant> {
+    match value {
+        Value::Integer { .. } | Value::Rational { .. } | Value::Float { .. } => {
+            value.as_rational().map(Constant::Number)
+        }
+        Value::Duration { seconds } => Value::Float { float: *seconds }
+            .as_rational()
+            .map(Constant::Duration),
+        Value::String { string } => Some(Constant::String(StringId::new(string))),
+        Value::Bool { value } => Some(Constant::Bool(*value)),
+        Value::Note { note } => Some(Constant::Note(*note)),
+        Value::Chord { chord } => Some(Constant::Chord(*chord)),
+        Value::List { values } => values
+            .iter()
+            .map(value_constant)
+            .collect::<Option<_>>()
+            .map(Constant::List),
+        Value::Key { .. }
+        | Value::Scale { .. }
+        | Value::Tuning { .. }
+        | Value::Sample { .. }
+        | Value::Sequence { .. }
+        | Value::Function { .. }
+        | Value::Native { .. } => None,
+    }
+}
+
+/// Returns the cursor after waiting for a number of beats or a duration,
+/// which is converted to beats with the tempo at the cursor.
+pub(crate) fn wait(
+    value: &Value,
+    cursor: Rational,
+    tempo: &TempoMap,
+    span: Span,
+) -> Result<Rational, RuntimeError> {
+    let beats = match value {
+        Value::Duration { seconds } => {
+            let start = tempo.seconds_at(cursor);
+            let end = tempo.beat_at(start + seconds);
+
+            Value::Float { float: end }
+                .as_rational()
+                .unwrap_or(cursor)
+                .checked_sub(cursor)
+                .ok_or(RuntimeError::Overflow { span })?
+        }
+        value => expect_rational(value, "number of beats", span)?,
+    };
+
+    if beats.is_negative() {
+        return Err(RuntimeError::NegativeWait { span });
+    }
+
+    advance(cursor, beats, span)
+}
+
+/// Moves a cursor forward by a number of beats.
+pub(crate) fn advance(
+    cursor: Rational,
+    beats: Rational,
+    span: Span,
+) -> Result<Rational, RuntimeError> {
+    cursor
+        .checked_add(beats)
+        .ok_or(RuntimeError::Overflow { span })
+}
+
+pub(crate) fn expect_rational(
+    value: &Value,
+    expected: &'static str,
+    span: Span,
+) -> Result<Rational, RuntimeError> {
+    value.as_rational().ok_or(RuntimeError::TypeMismatch {
+        expected,
+        found: value.type_name(),
+        span,
+    })
+}
+
+pub(crate) fn expect_f64(value: &Value, span: Span) -> Result<f64, RuntimeError> {
+    value.as_f64().ok_or(RuntimeError::TypeMismatch {
+        expected: "number",
+        found: value.type_name(),
+        span,
+    })
+}
+
+/// Reads a number of repetitions.
+pub(crate) fn expect_count(value: &Value, span: Span) -> Result<usize, RuntimeError> {
+    match value {
+        Value::Integer { value } => {
+            usize::try_from(*value).map_err(|_| RuntimeError::InvalidCount { span })
+        }
+        _ => Err(RuntimeError::TypeMismatch {
+            expected: "integer",
+            found: value.type_name(),
+            span,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::resolve::resolve,
+        syntax::{macros::render_error, parse::parse, scan::scan},
+        test_snapshots,
+    };
+
+    use super::{interpret, Options};
+
+    fn render(source: &str) -> String {
+        let statements = parse(scan(source).unwrap()).unwrap();
+
+        match interpret(&statements, &resolve(&statements), &Options::default()) {
+            Ok(ir) => ir.to_string(),
+            Err(error) => render_error(&error),
+        }
+    }
+
+    test_snapshots!(
+        (
+            variables,
+            "let root = c4\nlet step = 2\nrepeat 3 {\n play root\n root = root + step\n wait 1/2\n}"
+        ),
+        (
+            sequences,
+            "sequence riff {\n play [c4, e4]\n wait 1\n play g4\n wait 1\n}\nplay! riff\nplay fast(2, riff)\nplay rev(riff)"
+        ),
+        (
+            samples,
+            "let kick = load_sample \"kick.wav\"\nplay euclid(3, 8, kick)\nplay [kick, load_sample \"hat.wav\"]"
+        ),
+        (euclid_rotation, "play euclid(3, 8, 2, c4)"),
+        (euclid_rhythm, "play euclid(3, 8)\nwait 2\nplay euclid(2, 4, rotation: -1)"),
+        (
+            every,
+            "sequence a {\n play c4\n wait 1\n play d4\n wait 1\n}\nplay every(2, rev, a)"
+        ),
+        (
+            endless,
+            "repeat {\n play c4\n wait 256\n}"
+        ),
+        (
+            theory,
+            "play invert(cmaj7, 1)\nwait 1\nplay drop2(transpose(octave(cmaj7, -1), 2))\nwait midi(c4) - 59\nplay voicing(chord: am, low: g3, high: c5)\nwait 1\nplay note_from_midi(interval(c4, g4) + 60)"
+        ),
+        (
+            scales,
+            "let s = scale(c4, \"dorian\")\nplay [s[1], s[3], s[8], s[-1]]\nwait 1\nplay quantize([cs4, g4], scale(d4, [0, 4, 7]))"
+        ),
+        (degree_without_key, "play ^1"),
+        (degree_zero, "play scale(c4, \"major\")[0]"),
+        (
+            progression,
+            "let chords = prog(\"ii7 V65 I6\", key: bb_major)\nplay chords\nwait 1\nplay transpose(prog(\"V/V\", c_major), 12)"
+        ),
+        (invalid_numeral, "play prog(\"I IV V/X\", key: c_major)"),
+        (
+            voice_leading,
+            "let voicings = voice_lead(prog(\"I vi IV V7\", key: c_major), voices: 4)\nplay voicings[0]\nwait 1\nplay voicings[1]\nwait 1\nplay voicings[-1]"
+        ),
+        (
+            recognition,
+            "let chords = recognize([e3, g3, c4])\nplay chords[0]\nwait 1\nplay transpose(recognize(am7 - 12), 12)"
+        ),
+        (no_voicing, "play voice_lead([cmaj7], 4, low: c4, high: d4)"),
+        (duplicate_argument, "play fast(factor: 2, factor: 3, c4)"),
+        (
+            computed_properties,
+            "let n = 7\nlet home = d_minor\nwith transpose: n - 2, key: home, length: 1 / (2 * 4), swing: 0.6 {\n play [^1, ^3]\n wait 1\n}"
+        ),
+        (duplicate_property, "with transpose: -2, transpose: 7 {\n play c4\n}"),
+        (
+            tunings,
+            "tuning edo(19, frequency: 432)\nwait freq(a4 + 19) / 432\nplay c4\ntuning just([9/8, 5/4, 3/2, 2], reference: c4, frequency: 264)\nwait freq(e4) / 396\nplay e4"
+        ),
+        (invalid_tuning, "tuning just([3/2, 0])"),
+        (index_out_of_bounds, "play [c4, e4][2]"),
+        (unknown_scale, "play scale(c4, \"bebop\")[1]"),
+        (empty_loop, "repeat {\n play c4\n}"),
+        (division_by_zero, "let a = 0\nwait 1 / a"),
+        (
+            exact_fractions,
+            "let n = 3\nrepeat 3 {\n play c4\n wait 1 / n\n}\nplay d4\nwait -(1 / n) + 2 * (1 / n)\nplay e4"
+        ),
+        (overflow, "let a = 9223372036854775807\na = -a - 1\na = -a"),
+        (note_out_of_range, "play c4\nplay c4 + 2147483647"),
+        (
+            transpose_out_of_range,
+            "with transpose: 2147483647 {\n with transpose: 1 {\n  play c4\n }\n}"
+        ),
+        (degree_out_of_range, "with key: c_major {\n play ^9223372036854775807\n}"),
+        (invert_out_of_range, "play invert(cmaj7, 2147483647)"),
+        (octave_out_of_range, "play octave(c4, 200000000)"),
+        (
+            interval_out_of_range,
+            "wait interval(c4, g4)\nwait interval(note_from_midi(-2147483648), c4)"
+        ),
+        (
+            groove,
+            "with swing: 75%, groove: \"mpc_16\" {\n repeat 4 {\n  play c4\n  wait 1/4\n }\n play fast(2, [e4, g4])\n}"
+        ),
+        (unknown_groove, "with groove: \"../mpc_16\" {\n play c4\n}"),
+        (recursion, "sequence a {\n play a\n}\nplay a"),
+        (
+            render_recursion,
+            "sequence a {\n play fast(2, a)\n}\nplay a"
+        ),
+        (
+            channels,
+            "let root = c4\nsequence arp {\n repeat 3 {\n  play root\n  wait 1\n }\n}\nplay! arp\nwith synth: pad {\n play! [e4, g4]\n}\nrepeat 2 {\n wait 1/2\n root = root + 2\n wait 1/2\n}"
+        ),
+    );
+
+    #[test]
+    fn deterministic() {
+        let source = "sequence hats {\n repeat 8 {\n  play degrade(0.5, [c4, e4])\n  wait 1/2\n }\n}\nplay! hats\nplay! humanize(20ms, 0.2, hats)\nplay hats";
+        let statements = parse(scan(source).unwrap()).unwrap();
+        let resolution = resolve(&statements);
+        let options = Options {
+            seed: 7,
+            ..Options::default()
+        };
+
+        let first = interpret(&statements, &resolution, &options).unwrap();
+        let second = interpret(&statements, &resolution, &options).unwrap();
+
+        assert_eq!(first, second);
+    }
+}
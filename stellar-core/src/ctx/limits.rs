@@ -0,0 +1,463 @@
+//! Limits of the resources a program may use, so that untrusted programs
+//! can be run on shared machines. Every exceeded limit is reported as its
+//! own [`Limit`]. Programs nested deeper than
+//! [`MAX_NESTING`](crate::syntax::parse::MAX_NESTING) are already rejected
+//! by the parser.
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::{audio::tempo::TempoMap, ir::Event, pattern::Pattern, syntax::location::Span};
+
+use super::{
+    interpreter::{RuntimeError, MAX_DEPTH},
+    value::{Sound, Value},
+};
+
+/// Limits of a run. `None` means unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Limits {
+    /// Number of events all channels may play together.
+    pub max_events: Option<usize>,
+    /// Number of channels, including the main one.
+    pub max_channels: Option<usize>,
+    /// Number of sequences played inside each other. Programs can't exceed
+    /// [`MAX_DEPTH`] either way, which is reported as a stack overflow.
+    pub max_depth: Option<usize>,
+    pub max_list_size: Option<usize>,
+    /// Number of bytes of a string.
+    pub max_string_size: Option<usize>,
+    /// Number of seconds, after which no event may be played.
+    pub max_duration: Option<f64>,
+    /// Number of statements and expressions the interpreter may evaluate,
+    /// or instructions the bytecode machine may execute.
+    pub max_steps: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Events,
+    Channels,
+    Depth,
+    ListSize,
+    StringSize,
+    Duration,
+    Steps,
+}
+
+/// Resources used so far by a run.
+#[derive(Debug)]
+pub(crate) struct Budget {
+    pub(crate) limits: Limits,
+    events: usize,
+    channels: usize,
+    steps: u64,
+}
+
+impl Budget {
+    pub(crate) fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            events: 0,
+            channels: 1,
+            steps: 0,
+        }
+    }
+
+    pub(crate) fn step(&mut self, span: Span) -> Result<(), RuntimeError> {
+        self.steps += 1;
+
+        check(self.steps, self.limits.max_steps, Limit::Steps, span)
+    }
+
+    /// Counts a channel started with `play!`.
+    pub(crate) fn channel(&mut self, span: Span) -> Result<(), RuntimeError> {
+        self.channels += 1;
+
+        check(
+            self.channels,
+            self.limits.max_channels,
+            Limit::Channels,
+            span,
+        )
+    }
+
+    /// Checks the number of blocks a channel runs inside each other, before
+    /// it plays one more sequence.
+    pub(crate) fn depth(&self, depth: usize, span: Span) -> Result<(), RuntimeError> {
+        if depth >= MAX_DEPTH {
+            return Err(RuntimeError::StackOverflow { span });
+        }
+
+        check(depth + 1, self.limits.max_depth, Limit::Depth, span)
+    }
+
+    /// Counts events added to a channel, checking when they happen.
+    pub(crate) fn events(
+        &mut self,
+        events: &[Event],
+        tempo: &TempoMap,
+    ) -> Result<(), RuntimeError> {
+        let Some(last) = events.last() else {
+            return Ok(());
+        };
+
+        self.events += events.len();
+        check(
+            self.events,
+            self.limits.max_events,
+            Limit::Events,
+            last.span,
+        )?;
+
+        if let Some(max) = self.limits.max_duration {
+            if let Some(event) = events
+                .iter()
+                .find(|event| tempo.seconds_at(event.beat) > max)
+            {
+                return Err(RuntimeError::LimitExceeded {
+                    limit: Limit::Duration,
+                    span: event.span,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the size of a value, which has just been created.
+    pub(crate) fn value(&self, value: &Value, span: Span) -> Result<(), RuntimeError> {
+        match value {
+            Value::List { values } => check(
+                values.len(),
+                self.limits.max_list_size,
+                Limit::ListSize,
+                span,
+            ),
+            Value::String { string } => check(
+                string.len(),
+                self.limits.max_string_size,
+                Limit::StringSize,
+                span,
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks a pattern created by a function, which couldn't be played
+    /// without exceeding the number of events.
+    pub(crate) fn pattern(&self, pattern: &Pattern<Sound>, span: Span) -> Result<(), RuntimeError> {
+        self.reserve(pattern.events().len(), span)
+    }
+
+    /// Checks voices of a polyrhythm, before they are repeated `counts`
+    /// times.
+    pub(crate) fn poly(
+        &self,
+        voices: &[Pattern<Sound>],
+        counts: &[usize],
+        span: Span,
+    ) -> Result<(), RuntimeError> {
+        let events = voices
+            .iter()
+            .enumerate()
+            .map(|(index, voice)| {
+                let count = counts.get(index).copied().unwrap_or(1);
+                voice.events().len().saturating_mul(count)
+            })
+            .fold(0, usize::saturating_add);
+
+        self.reserve(events, span)
+    }
+
+    /// Checks that a function could create a number of events, before it
+    /// allocates them.
+    pub(crate) fn reserve(&self, events: usize, span: Span) -> Result<(), RuntimeError> {
+        check(
+            self.events.saturating_add(events),
+            self.limits.max_events,
+            Limit::Events,
+            span,
+        )
+    }
+}
+
+/// Resolves a path read by a script relative to the directory it may read
+/// files from. Paths leading out of the directory are denied before the
+/// file system is touched, so that scripts can't find out which files
+/// exist outside of it.
+pub(crate) fn resolve_file(
+    files: Option<&Path>,
+    path: &str,
+    span: Span,
+) -> Result<PathBuf, RuntimeError> {
+    let denied = || RuntimeError::FileAccess {
+        path: path.to_owned(),
+        span,
+    };
+
+    let directory = files.ok_or_else(denied)?;
+    let relative = Path::new(path);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(denied());
+    }
+
+    let resolved = directory.join(relative);
+
+    // Symbolic links inside of the directory may still point out of it.
+    if let (Ok(directory), Ok(file)) = (directory.canonicalize(), resolved.canonicalize()) {
+        if !file.starts_with(directory) {
+            return Err(denied());
+        }
+    }
+
+    Ok(resolved)
+}
+
+fn check<T: PartialOrd>(
+    used: T,
+    max: Option<T>,
+    limit: Limit,
+    span: Span,
+) -> Result<(), RuntimeError> {
+    match max {
+        Some(max) if used > max => Err(RuntimeError::LimitExceeded { limit, span }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use crate::{
+        analysis::{duration::analyze, fold::fold, resolve::resolve, types::check},
+        ctx::interpreter::{interpret, Options, RuntimeError},
+        lint::{config::LintConfig, lint},
+        syntax::{
+            location::{Location, Span},
+            parse::{parse, MAX_NESTING},
+            scan::scan,
+        },
+        vm::{compile::compile, machine::run},
+    };
+
+    use super::{resolve_file, Limit, Limits};
+
+    /// Returns the limits the interpreter and the bytecode machine report
+    /// as exceeded.
+    fn exceeded(source: &str, limits: Limits) -> [Option<Limit>; 2] {
+        let statements = parse(scan(source).unwrap()).unwrap();
+        let resolution = resolve(&statements);
+        let options = Options {
+            limits,
+            ..Options::default()
+        };
+
+        let limit = |result| match result {
+            Err(RuntimeError::LimitExceeded { limit, .. }) => Some(limit),
+            Err(error) => panic!("unexpected error: {error:?}"),
+            Ok(_) => None,
+        };
+
+        [
+            limit(interpret(&statements, &resolution, &options)),
+            limit(run(&compile(&statements, &resolution), &options)),
+        ]
+    }
+
+    const ENDLESS: &str = "repeat {\n play c4\n wait 1\n}";
+
+    #[test]
+    fn unlimited() {
+        assert_eq!(exceeded(ENDLESS, Limits::default()), [None, None]);
+    }
+
+    #[test]
+    fn events() {
+        let limits = Limits {
+            max_events: Some(5),
+            ..Limits::default()
+        };
+
+        assert_eq!(exceeded(ENDLESS, limits), [Some(Limit::Events); 2]);
+        assert_eq!(
+            exceeded("play fast(4, [c4, e4, g4])", limits),
+            [Some(Limit::Events); 2]
+        );
+
+        // Counts are checked before the events are created.
+        for source in [
+            "play euclid(60000, 65536, c4)",
+            "play every(65536, rev, c4)",
+            "poly(65536) {\n play c4\n}",
+        ] {
+            assert_eq!(exceeded(source, limits), [Some(Limit::Events); 2]);
+        }
+    }
+
+    #[test]
+    fn channels() {
+        let source = "sequence a {\n play c4\n wait 1\n}\nplay! a\nplay! a";
+        let limits = |max| Limits {
+            max_channels: Some(max),
+            ..Limits::default()
+        };
+
+        assert_eq!(exceeded(source, limits(2)), [Some(Limit::Channels); 2]);
+        assert_eq!(exceeded(source, limits(3)), [None, None]);
+    }
+
+    #[test]
+    fn depth() {
+        let source = "sequence a {\n play a\n}\nplay a";
+        let limits = Limits {
+            max_depth: Some(8),
+            ..Limits::default()
+        };
+
+        assert_eq!(exceeded(source, limits), [Some(Limit::Depth); 2]);
+    }
+
+    #[test]
+    fn list_size() {
+        let source = "let notes = [c4]\nrepeat 4 {\n notes = notes + notes\n}";
+        let limits = Limits {
+            max_list_size: Some(8),
+            ..Limits::default()
+        };
+
+        assert_eq!(exceeded(source, limits), [Some(Limit::ListSize); 2]);
+    }
+
+    #[test]
+    fn string_size() {
+        let source = "let s = \"beat\"\nrepeat 16 {\n s = s + s\n}";
+        let limits = Limits {
+            max_string_size: Some(1024),
+            ..Limits::default()
+        };
+
+        assert_eq!(exceeded(source, limits), [Some(Limit::StringSize); 2]);
+    }
+
+    #[test]
+    fn duration() {
+        let limits = Limits {
+            max_duration: Some(10.0),
+            ..Limits::default()
+        };
+
+        assert_eq!(exceeded(ENDLESS, limits), [Some(Limit::Duration); 2]);
+    }
+
+    #[test]
+    fn files() {
+        let statements = parse(scan("tuning scala(\"/etc/passwd\")").unwrap()).unwrap();
+        let resolution = resolve(&statements);
+
+        let denied = |options: &Options| {
+            let denied = |result| matches!(result, Err(RuntimeError::FileAccess { .. }));
+
+            denied(interpret(&statements, &resolution, options))
+                && denied(run(&compile(&statements, &resolution), options))
+        };
+
+        assert!(denied(&Options::default()));
+        assert!(denied(&Options {
+            files: Some(PathBuf::from(".")),
+            ..Options::default()
+        }));
+
+        let start = Location::new(1, 0, 0);
+        let outside = Span::new(start, start);
+        for path in ["../secret.scl", "/etc/passwd", "a/../../b.scl"] {
+            assert!(matches!(
+                resolve_file(Some(Path::new("tunings")), path, outside),
+                Err(RuntimeError::FileAccess { .. })
+            ));
+        }
+        assert_eq!(
+            resolve_file(Some(Path::new("tunings")), "a/b.scl", outside).unwrap(),
+            Path::new("tunings/a/b.scl")
+        );
+    }
+
+    #[test]
+    fn groove_files() {
+        let directory =
+            std::env::temp_dir().join(format!("stellar-grooves-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("mpc.v2.groove"), "step 1/16\n0 1").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("/etc/passwd", directory.join("passwd.groove")).unwrap();
+
+        let options = Options {
+            files: Some(directory.clone()),
+            ..Options::default()
+        };
+        let play = |name: &str| {
+            let source = format!("with groove: \"{name}\" {{\n play c4\n}}");
+            let statements = parse(scan(&source).unwrap()).unwrap();
+
+            interpret(&statements, &resolve(&statements), &options)
+        };
+
+        assert!(play("mpc.v2").is_ok());
+        #[cfg(unix)]
+        assert!(matches!(
+            play("passwd"),
+            Err(RuntimeError::FileAccess { .. })
+        ));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn steps() {
+        let limits = Limits {
+            max_steps: Some(100),
+            ..Limits::default()
+        };
+
+        assert_eq!(exceeded(ENDLESS, limits), [Some(Limit::Steps); 2]);
+    }
+
+    #[test]
+    fn nesting() {
+        // Programs nested as deep as the parser allows go through every
+        // pass without overflowing the stack.
+        let depth = MAX_NESTING - 1;
+        let sources = [
+            format!("play {}c4{}", "[".repeat(depth), "]".repeat(depth)),
+            format!("play c4{}", " + 1".repeat(depth)),
+            format!(
+                "{}play c4{}",
+                "repeat 1 {\n".repeat(depth / 2),
+                "\n}".repeat(depth / 2)
+            ),
+        ];
+
+        for source in sources {
+            let statements = parse(scan(&source).unwrap()).unwrap();
+            let resolution = resolve(&statements);
+
+            check(&statements, &resolution);
+            fold(&statements, &resolution);
+            analyze(&statements, &resolution);
+            lint(
+                &statements,
+                &resolution,
+                &LintConfig::default(),
+                &Options::default(),
+            );
+            interpret(&statements, &resolution, &Options::default()).unwrap();
+            run(&compile(&statements, &resolution), &Options::default()).unwrap();
+        }
+    }
+}
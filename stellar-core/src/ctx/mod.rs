@@ -1 +1,9 @@
-mod value;
+pub mod builtins;
+pub mod environment;
+pub mod host;
+pub mod interpreter;
+pub mod limits;
+pub mod properties;
+pub mod scheduler;
+pub(crate) mod stdlib;
+pub mod value;
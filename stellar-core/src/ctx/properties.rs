@@ -0,0 +1,501 @@
+use crate::{
+    pattern::{
+        arp::{ArpMode, Arpeggiator, DEFAULT_RATE},
+        Event, Pattern, PatternError, BEATS_PER_WHOLE_NOTE,
+    },
+    random::Rng,
+    rational::{Rational, MAX_DENOMINATOR},
+    syntax::{
+        ast::{BinaryOperatorKind, Expression, PrefixOperatorKind, Property},
+        location::{Span, Spanned},
+        string_id::StringId,
+        token::{Identifier, Unit},
+    },
+    theory::{key::Key, note::Note},
+};
+
+use super::value::Value;
+
+/// Length of played notes and chords in beats, if not set with `length`:
+/// a quarter note.
+pub const DEFAULT_NOTE_LENGTH: Rational = Rational::integer(1);
+
+/// Kind of value a `with` property accepts. Values of every kind but
+/// names are evaluated when the block is run: `transpose: n`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropertyKind {
+    /// A bare name, which is not looked up as a variable: `synth: dsaw`.
+    Name,
+    Key,
+    /// A number or a fraction: `rate: 1/16`.
+    Number,
+    /// A percentage or a fraction: `swing: 60%`.
+    Ratio,
+    String,
+}
+
+/// Every property understood by `with` blocks.
+pub const PROPERTIES: [(&str, PropertyKind); 9] = [
+    ("synth", PropertyKind::Name),
+    ("arp", PropertyKind::Name),
+    ("rate", PropertyKind::Number),
+    ("octaves", PropertyKind::Number),
+    ("length", PropertyKind::Number),
+    ("transpose", PropertyKind::Number),
+    ("key", PropertyKind::Key),
+    ("swing", PropertyKind::Ratio),
+    ("groove", PropertyKind::String),
+];
+
+/// Properties passed on to backends as [`EventKind::ParamChange`] events,
+/// instead of being applied to notes.
+///
+/// [`EventKind::ParamChange`]: crate::ir::EventKind::ParamChange
+pub const PARAMS: [&str; 1] = ["synth"];
+
+/// Returns the kind of value a property accepts, or `None` if there is no
+/// such property.
+pub fn property_kind(name: &str) -> Option<PropertyKind> {
+    PROPERTIES
+        .iter()
+        .find(|(property, _)| *property == name)
+        .map(|(_, kind)| *kind)
+}
+
+/// Returns `true` if the value of a property is evaluated when its block is
+/// run, instead of being read as a bare name.
+pub fn is_evaluated(name: &str) -> bool {
+    property_kind(name).is_some_and(|kind| kind != PropertyKind::Name)
+}
+
+/// Settings, which statements inside of a `with` block are played with.
+///
+/// Nested `with` blocks inherit settings of the enclosing ones and override
+/// only the properties they mention.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Properties {
+    pub synth: Option<StringId>,
+    pub arp: Option<ArpMode>,
+    pub rate: Rational,
+    pub octaves: u32,
+    /// Length of played notes and chords in beats.
+    pub length: Rational,
+    /// Number of semitones, which every note is shifted by.
+    pub transpose: i32,
+    /// Key, which scale degrees (`^1`, `^5`) are resolved in.
+    pub key: Option<Key>,
+    /// Swing amount in range `(0, 1)`, `0.5` being straight.
+    pub swing: Option<f64>,
+    /// Name of the groove template: `with groove: "mpc_16"`.
+    pub groove: Option<StringId>,
+}
+
+impl Default for Properties {
+    fn default() -> Self {
+        Self {
+            synth: None,
+            arp: None,
+            rate: DEFAULT_RATE,
+            octaves: 1,
+            length: DEFAULT_NOTE_LENGTH,
+            transpose: 0,
+            key: None,
+            swing: None,
+            groove: None,
+        }
+    }
+}
+
+impl Properties {
+    /// Returns settings of a nested `with` block.
+    pub fn with(&self, properties: &[Property]) -> Result<Self, PropertyError> {
+        self.with_settings(&Setting::parse_all(properties)?)
+    }
+
+    /// Returns settings of a nested block with already parsed properties
+    /// and spans of their values.
+    pub fn with_settings(&self, settings: &[(Setting, Span)]) -> Result<Self, PropertyError> {
+        let mut result = self.clone();
+
+        for (setting, span) in settings {
+            result
+                .apply(*setting)
+                .ok_or(PropertyError::OutOfRange { span: *span })?;
+        }
+
+        Ok(result)
+    }
+
+    /// Applies a setting, or returns `None` if the transpositions of nested
+    /// blocks add up to more than fits.
+    fn apply(&mut self, setting: Setting) -> Option<()> {
+        match setting {
+            Setting::Synth(synth) => self.synth = Some(synth),
+            Setting::Arp(mode) => self.arp = Some(mode),
+            Setting::Rate(rate) => self.rate = rate,
+            Setting::Octaves(octaves) => self.octaves = octaves,
+            Setting::Length(length) => self.length = length,
+            // Transpositions of nested blocks add up.
+            Setting::Transpose(semitones) => {
+                self.transpose = self.transpose.checked_add(semitones)?;
+            }
+            Setting::Key(key) => self.key = Some(key),
+            Setting::Swing(amount) => self.swing = Some(amount),
+            Setting::Groove(groove) => self.groove = Some(groove),
+        }
+
+        Some(())
+    }
+
+    /// Returns the arpeggiator enabled by `arp`, `rate` and `octaves`
+    /// properties, if any.
+    pub fn arpeggiator(&self) -> Option<Arpeggiator> {
+        self.arp.map(|mode| Arpeggiator {
+            mode,
+            rate: self.rate,
+            octaves: self.octaves,
+        })
+    }
+
+    /// Applies the block transposition to a note. Returns `None` if the
+    /// transposed note is outside of the range of notes.
+    pub fn transpose_note(&self, note: Note) -> Option<Note> {
+        note.transpose(self.transpose)
+    }
+
+    /// Returns the note of a scale degree in the block key, transposed
+    /// with the block transposition. Returns `None` if no key is set or
+    /// the degree is invalid.
+    pub fn resolve_degree(&self, degree: i64) -> Option<Note> {
+        self.key?
+            .degree(degree)
+            .and_then(|note| self.transpose_note(note))
+    }
+
+    /// Turns tones of a played chord into timed notes: broken up by the
+    /// arpeggiator if one is enabled, or sounding together otherwise.
+    pub fn expand_chord(
+        &self,
+        notes: &[Note],
+        length: Rational,
+        rng: &mut Rng,
+    ) -> Result<Pattern<Note>, PatternError> {
+        match self.arpeggiator() {
+            Some(arpeggiator) => arpeggiator.arpeggiate(notes, length, rng),
+            None => Ok(Pattern::from_events(
+                length,
+                notes
+                    .iter()
+                    .map(|note| Event::new(Rational::ZERO, length, *note))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+/// Value of a single property of a `with` block, read from the source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Setting {
+    Synth(StringId),
+    Arp(ArpMode),
+    /// Rate of the arpeggiator in beats.
+    Rate(Rational),
+    Octaves(u32),
+    /// Length of notes in beats.
+    Length(Rational),
+    Transpose(i32),
+    Key(Key),
+    Swing(f64),
+    Groove(StringId),
+}
+
+impl Setting {
+    /// Parses properties of a `with` block, paired with spans of their
+    /// values. Values, which are evaluated when the block is run, must be
+    /// literals.
+    pub fn parse_all(properties: &[Property]) -> Result<Vec<(Self, Span)>, PropertyError> {
+        Self::check_unique(properties)?;
+
+        properties
+            .iter()
+            .map(|property| Ok((Self::parse(property)?, property.value.span())))
+            .collect()
+    }
+
+    /// Checks that a `with` block sets every property at most once.
+    pub fn check_unique(properties: &[Property]) -> Result<(), PropertyError> {
+        for (index, property) in properties.iter().enumerate() {
+            let name = property.name.name();
+            if properties[..index]
+                .iter()
+                .any(|previous| previous.name.name() == name)
+            {
+                return Err(PropertyError::DuplicateProperty {
+                    property: property.name,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn parse(property: &Property) -> Result<Self, PropertyError> {
+        let name = property.name;
+        let value = &property.value;
+        let invalid = || PropertyError::InvalidValue {
+            property: name,
+            span: value.span(),
+        };
+
+        match name.name().resolve().as_str() {
+            "synth" => Ok(Self::Synth(expect_name(name, value)?)),
+            "arp" => {
+                let mode = expect_name(name, value)?;
+
+                Ok(Self::Arp(
+                    ArpMode::parse(&mode.resolve()).ok_or_else(invalid)?,
+                ))
+            }
+            property if property_kind(property).is_some() => Self::from_value(
+                name,
+                &literal_value(value).ok_or_else(invalid)?,
+                value.span(),
+            ),
+            _ => Err(PropertyError::UnknownProperty { property: name }),
+        }
+    }
+
+    /// Reads the value of a property, which is [evaluated](is_evaluated)
+    /// when its block is run. `span` is the span of the value.
+    pub fn from_value(
+        property: Identifier,
+        value: &Value,
+        span: Span,
+    ) -> Result<Self, PropertyError> {
+        let invalid = || PropertyError::InvalidValue { property, span };
+        // Rate and length are written as note values, so `1/16` is a
+        // sixteenth note.
+        let note_value = || {
+            value
+                .as_rational()
+                .filter(|rational| *rational > Rational::ZERO)
+                .and_then(|rational| rational.checked_mul(BEATS_PER_WHOLE_NOTE))
+                .ok_or_else(invalid)
+        };
+
+        Ok(match property.name().resolve().as_str() {
+            "rate" => Self::Rate(note_value()?),
+            "length" => Self::Length(note_value()?),
+            "octaves" => match value {
+                Value::Integer { value } if *value > 0 => {
+                    Self::Octaves(u32::try_from(*value).map_err(|_| invalid())?)
+                }
+                _ => return Err(invalid()),
+            },
+            "transpose" => match value {
+                Value::Integer { value } => {
+                    Self::Transpose(i32::try_from(*value).map_err(|_| invalid())?)
+                }
+                _ => return Err(invalid()),
+            },
+            "key" => match value {
+                Value::Key { key } => Self::Key(*key),
+                _ => return Err(invalid()),
+            },
+            "swing" => match value.as_f64() {
+                Some(amount) if amount > 0.0 && amount < 1.0 => Self::Swing(amount),
+                _ => return Err(invalid()),
+            },
+            "groove" => match value {
+                Value::String { string } => Self::Groove(StringId::new(string)),
+                _ => return Err(invalid()),
+            },
+            _ => return Err(invalid()),
+        })
+    }
+}
+
+/// Reads a bare name: `synth: dsaw`.
+fn expect_name(property: Identifier, value: &Expression) -> Result<StringId, PropertyError> {
+    match value {
+        Expression::Identifier(identifier) => Ok(identifier.name()),
+        _ => Err(PropertyError::InvalidValue {
+            property,
+            span: value.span(),
+        }),
+    }
+}
+
+/// Reads a literal value of a property, which is otherwise evaluated: a
+/// number, a percentage (`60%`), a key name or a string.
+fn literal_value(expression: &Expression) -> Option<Value> {
+    match expression {
+        Expression::Quantity {
+            value,
+            unit: Unit::Percent,
+            ..
+        } => Some(Value::Float {
+            float: value / 100.0,
+        }),
+        Expression::Identifier(identifier) => {
+            Key::parse(&identifier.name().resolve()).map(|key| Value::Key { key })
+        }
+        Expression::String { value, .. } => Some(Value::String {
+            string: value.resolve(),
+        }),
+        _ => literal_rational(expression).map(Value::number),
+    }
+}
+
+/// Evaluates a number literal or an arithmetic expression over them.
+pub(crate) fn literal_rational(expression: &Expression) -> Option<Rational> {
+    match expression {
+        Expression::Integer { value, .. } => Some(Rational::integer(*value)),
+        Expression::Float { value, .. } => Some(Rational::approximate(*value, MAX_DENOMINATOR)),
+        Expression::Prefix { operator, operand } if operator.kind == PrefixOperatorKind::Minus => {
+            literal_rational(operand)?.checked_neg()
+        }
+        Expression::Binary {
+            operator,
+            left,
+            right,
+        } => {
+            let left = literal_rational(left)?;
+            let right = literal_rational(right)?;
+
+            match operator.kind {
+                BinaryOperatorKind::Plus => left.checked_add(right),
+                BinaryOperatorKind::Minus => left.checked_sub(right),
+                BinaryOperatorKind::Star => left.checked_mul(right),
+                BinaryOperatorKind::Slash => left.checked_div(right),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyError {
+    UnknownProperty {
+        property: Identifier,
+    },
+    InvalidValue {
+        property: Identifier,
+        span: Span,
+    },
+    /// Property set twice by the same block: `transpose: 2, transpose: 7`.
+    DuplicateProperty {
+        property: Identifier,
+    },
+    /// Transposition, which adds up with the ones of enclosing blocks to
+    /// more semitones than fit.
+    OutOfRange {
+        span: Span,
+    },
+}
+
+impl Spanned for PropertyError {
+    fn span(&self) -> Span {
+        match self {
+            Self::UnknownProperty { property } | Self::DuplicateProperty { property } => {
+                property.span()
+            }
+            Self::InvalidValue { span, .. } | Self::OutOfRange { span } => *span,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        pattern::arp::ArpMode,
+        rational::Rational,
+        syntax::{ast::Statement, parse::parse, scan::scan},
+    };
+
+    use super::{Properties, PropertyError};
+
+    fn with(source: &str) -> Result<Properties, PropertyError> {
+        let statements = parse(scan(source).unwrap()).unwrap();
+        let Statement::With { properties, .. } = &statements[0] else {
+            panic!("expected with statement");
+        };
+
+        Properties::default().with(properties)
+    }
+
+    #[test]
+    fn arpeggiator() {
+        let properties = with("with arp: updown, rate: 1/16, octaves: 2 {}").unwrap();
+        let arpeggiator = properties.arpeggiator().unwrap();
+
+        assert_eq!(arpeggiator.mode, ArpMode::UpDown);
+        assert_eq!(arpeggiator.rate, Rational::new(1, 4));
+        assert_eq!(arpeggiator.octaves, 2);
+
+        assert_eq!(with("with rate: 1/8 {}").unwrap().arpeggiator(), None);
+    }
+
+    #[test]
+    fn note_length() {
+        assert_eq!(
+            with("with length: 1/2 {}").unwrap().length,
+            Rational::integer(2)
+        );
+        assert!(with("with length: 0 {}").is_err());
+    }
+
+    #[test]
+    fn transpose_and_key() {
+        let properties = with("with key: d_minor, transpose: 5 {}").unwrap();
+
+        assert_eq!(properties.transpose, 5);
+        assert_eq!(
+            properties.resolve_degree(3).map(|note| note.to_string()),
+            Some("as4".to_owned())
+        );
+        assert_eq!(Properties::default().resolve_degree(1), None);
+
+        assert!(matches!(
+            with("with transpose: 1/2 {}"),
+            Err(PropertyError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            with("with transpose: -2, transpose: 7 {}"),
+            Err(PropertyError::DuplicateProperty { .. })
+        ));
+    }
+
+    #[test]
+    fn swing_and_groove() {
+        let properties = with("with swing: 60%, groove: \"mpc_16\" {}").unwrap();
+
+        assert_eq!(properties.swing, Some(0.6));
+        assert_eq!(
+            properties.groove.map(|name| name.resolve()),
+            Some("mpc_16".to_owned())
+        );
+
+        assert!(matches!(
+            with("with swing: 100% {}"),
+            Err(PropertyError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            with("with arp: sideways {}"),
+            Err(PropertyError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            with("with rate: 1/0 {}"),
+            Err(PropertyError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            with("with colour: red {}"),
+            Err(PropertyError::UnknownProperty { .. })
+        ));
+    }
+}
@@ -0,0 +1,118 @@
+//! Deterministic scheduler: runs coroutines against a virtual beat clock
+//! instead of wall time. Coroutines waiting for the same beat are resumed
+//! in the order they were scheduled in, so a program always produces its
+//! events in the same order.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::rational::Rational;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(usize);
+
+impl TaskId {
+    /// Returns the position of the coroutine among all spawned ones.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Entry {
+    beat: Rational,
+    /// Number of coroutines scheduled before this one.
+    order: u64,
+    task: TaskId,
+}
+
+#[derive(Debug)]
+pub struct Scheduler<T> {
+    clock: Rational,
+    queue: BinaryHeap<Reverse<Entry>>,
+    /// Suspended coroutines. Running ones are taken out.
+    tasks: Vec<Option<T>>,
+    scheduled: u64,
+}
+
+impl<T> Default for Scheduler<T> {
+    fn default() -> Self {
+        Self {
+            clock: Rational::ZERO,
+            queue: BinaryHeap::new(),
+            tasks: Vec::new(),
+            scheduled: 0,
+        }
+    }
+}
+
+impl<T> Scheduler<T> {
+    /// Returns the beat of the coroutine resumed last.
+    pub fn now(&self) -> Rational {
+        self.clock
+    }
+
+    /// Adds a coroutine to be resumed at a beat. Ids are given out in
+    /// order, starting at zero.
+    pub fn spawn(&mut self, beat: Rational, task: T) -> TaskId {
+        let id = TaskId(self.tasks.len());
+        self.tasks.push(None);
+        self.schedule(id, beat, task);
+
+        id
+    }
+
+    /// Suspends a resumed coroutine until a beat. Beats in the past are
+    /// moved to the current one, because the clock never goes back.
+    pub fn schedule(&mut self, id: TaskId, beat: Rational, task: T) {
+        self.tasks[id.0] = Some(task);
+        self.queue.push(Reverse(Entry {
+            beat: beat.max(self.clock),
+            order: self.scheduled,
+            task: id,
+        }));
+        self.scheduled += 1;
+    }
+
+    /// Takes out the coroutine to be resumed next and advances the clock to
+    /// its beat.
+    pub fn resume(&mut self) -> Option<(TaskId, T)> {
+        let Reverse(entry) = self.queue.pop()?;
+        self.clock = entry.beat;
+
+        let task = self.tasks[entry.task.0]
+            .take()
+            .expect("scheduled coroutine is suspended");
+
+        Some((entry.task, task))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_snapshot;
+
+    use crate::rational::Rational;
+
+    use super::Scheduler;
+
+    #[test]
+    fn order() {
+        // Coroutines wait for the given numbers of beats in turn.
+        let mut scheduler = Scheduler::default();
+        for waits in [vec![1, 2], vec![2, 1], vec![0, 3]] {
+            scheduler.spawn(Rational::ZERO, waits.into_iter());
+        }
+
+        let mut trace = String::new();
+        while let Some((id, mut waits)) = scheduler.resume() {
+            trace += &format!("{}: {}\n", scheduler.now(), id.index());
+
+            if let Some(wait) = waits.next() {
+                let beat = scheduler.now() + Rational::integer(wait);
+                scheduler.schedule(id, beat, waits);
+            }
+        }
+
+        assert_snapshot!(trace);
+    }
+}
@@ -0,0 +1,343 @@
+//! Builtin functions of notes and chords, which compute values instead of
+//! transforming sequences: `midi(c4)`, `invert(cmaj7, 2)`. They are thin
+//! wrappers of the [theory](crate::theory) module.
+
+use std::path::Path;
+
+use crate::{
+    syntax::location::Span,
+    theory::{
+        note::Note,
+        numeral::progression,
+        recognize::recognize,
+        scale::{Scale, ScaleKind},
+        tuning::{Tuning, TuningError},
+        voicing::{drop2, invert, voice_lead, MAX_VOICES},
+    },
+};
+
+use super::{
+    builtins::Builtin,
+    interpreter::{expect_count, expect_f64, notes, RuntimeError},
+    limits::resolve_file,
+    value::Value,
+};
+
+/// Lowest and highest MIDI note numbers, which bound voicings.
+const MIDI_RANGE: (i32, i32) = (0, 127);
+
+/// Calls a function, which doesn't take a sequence, with the arguments
+/// ordered like its parameters. `tuning` is the tuning set by the last
+/// `tuning` statement, and `files` the directory files may be read from.
+pub(crate) fn call(
+    function: Builtin,
+    arguments: &[(Value, Span)],
+    tuning: &Tuning,
+    files: Option<&Path>,
+) -> Result<Value, RuntimeError> {
+    let value = match (function, arguments) {
+        (Builtin::Transpose, [(value, span), (semitones, semitones_span)]) => {
+            transpose(value, expect_integer(semitones, *semitones_span)?, *span)?
+        }
+        (Builtin::Octave, [(value, span), (k, k_span)]) => {
+            let semitones = expect_integer(k, *k_span)?
+                .checked_mul(12)
+                .ok_or(RuntimeError::NoteOutOfRange { span: *span })?;
+
+            transpose(value, semitones, *span)?
+        }
+        (Builtin::Invert, [(chord, chord_span), (n, n_span)]) => note_list(
+            invert(
+                &expect_notes(chord, *chord_span)?,
+                expect_integer(n, *n_span)?,
+            )
+            .ok_or(RuntimeError::NoteOutOfRange { span: *chord_span })?,
+        ),
+        (Builtin::Drop2, [(chord, span)]) => note_list(
+            drop2(&expect_notes(chord, *span)?)
+                .ok_or(RuntimeError::NoteOutOfRange { span: *span })?,
+        ),
+        (Builtin::ChordTones, [(chord, span)]) => note_list(expect_notes(chord, *span)?),
+        (Builtin::Voicing, [(chord, chord_span), (low, low_span), (high, high_span)]) => {
+            let Value::Chord { chord } = chord else {
+                return Err(mismatch("chord", chord, *chord_span));
+            };
+
+            let (low, high) =
+                midi_range(expect_note(low, *low_span)?, expect_note(high, *high_span)?);
+
+            note_list(chord.voicing(low, high))
+        }
+        (Builtin::Midi, [(note, span)]) => Value::Integer {
+            value: expect_note(note, *span)?.midi().into(),
+        },
+        (Builtin::Freq, [(note, span)]) => {
+            let note = expect_note(note, *span)?;
+
+            Value::Float {
+                float: tuning
+                    .frequency(note)
+                    .ok_or(RuntimeError::UnmappedNote { note, span: *span })?,
+            }
+        }
+        (Builtin::NoteFromMidi, [(n, span)]) => Value::Note {
+            note: Note::from_midi(expect_integer(n, *span)?)
+                .ok_or(RuntimeError::NoteOutOfRange { span: *span })?,
+        },
+        (Builtin::Interval, [(a, a_span), (b, b_span)]) => Value::Integer {
+            value: expect_note(a, *a_span)?
+                .interval(expect_note(b, *b_span)?)
+                .into(),
+        },
+        (Builtin::Scale, [(root, root_span), (name, name_span)]) => Value::Scale {
+            scale: Scale::new(
+                expect_note(root, *root_span)?,
+                scale_kind(name, *name_span)?,
+            ),
+        },
+        (Builtin::Quantize, [(notes, notes_span), (scale, scale_span)]) => {
+            let Value::Scale { scale } = scale else {
+                return Err(mismatch("scale", scale, *scale_span));
+            };
+
+            quantize(notes, scale, *notes_span)?
+        }
+        (Builtin::Prog, [(numerals, numerals_span), (key, key_span)]) => {
+            let Value::String { string } = numerals else {
+                return Err(mismatch("string", numerals, *numerals_span));
+            };
+            let Value::Key { key } = key else {
+                return Err(mismatch("key", key, *key_span));
+            };
+
+            let chords =
+                progression(string, *key).map_err(|numeral| RuntimeError::InvalidNumeral {
+                    numeral: numeral.to_owned(),
+                    span: *numerals_span,
+                })?;
+
+            Value::List {
+                values: chords
+                    .into_iter()
+                    .map(|chord| Value::Chord { chord })
+                    .collect(),
+            }
+        }
+        (
+            Builtin::VoiceLead,
+            [(chords, chords_span), (voices, voices_span), (low, low_span), (high, high_span)],
+        ) => {
+            let chords = match chords {
+                Value::List { values } => values
+                    .iter()
+                    .map(|value| match value {
+                        Value::Chord { chord } => Ok(*chord),
+                        _ => Err(mismatch("list of chords", chords, *chords_span)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                _ => return Err(mismatch("list of chords", chords, *chords_span)),
+            };
+
+            let voices = expect_integer(voices, *voices_span)?;
+            if !(1..=MAX_VOICES as i32).contains(&voices) {
+                return Err(RuntimeError::InvalidCount { span: *voices_span });
+            }
+
+            let (low, high) =
+                midi_range(expect_note(low, *low_span)?, expect_note(high, *high_span)?);
+
+            let voicings = voice_lead(&chords, voices as usize, low, high)
+                .ok_or(RuntimeError::NoVoicing { span: *chords_span })?;
+
+            Value::List {
+                values: voicings.into_iter().map(note_list).collect(),
+            }
+        }
+        (Builtin::Recognize, [(value, span)]) => {
+            let notes = notes(value).ok_or_else(|| mismatch("notes", value, *span))?;
+
+            Value::List {
+                values: recognize(&notes)
+                    .into_iter()
+                    .map(|chord| Value::Chord { chord })
+                    .collect(),
+            }
+        }
+        (
+            Builtin::Edo,
+            [(steps, steps_span), (reference, reference_span), (frequency, frequency_span)],
+        ) => tuning_value(
+            Tuning::equal(
+                expect_count(steps, *steps_span)?,
+                expect_note(reference, *reference_span)?,
+                expect_f64(frequency, *frequency_span)?,
+            ),
+            *steps_span,
+        )?,
+        (
+            Builtin::Just,
+            [(ratios, ratios_span), (reference, reference_span), (frequency, frequency_span)],
+        ) => {
+            let ratios = match ratios {
+                Value::List { values } => values
+                    .iter()
+                    .map(|value| expect_f64(value, *ratios_span))
+                    .collect::<Result<Vec<_>, _>>()?,
+                _ => return Err(mismatch("list of ratios", ratios, *ratios_span)),
+            };
+
+            tuning_value(
+                Tuning::just(
+                    &ratios,
+                    expect_note(reference, *reference_span)?,
+                    expect_f64(frequency, *frequency_span)?,
+                ),
+                *ratios_span,
+            )?
+        }
+        (Builtin::Scala, [(scale, scale_span), (mapping, mapping_span)]) => {
+            let Value::String { string: scale } = scale else {
+                return Err(mismatch("string", scale, *scale_span));
+            };
+            let Value::String { string: mapping } = mapping else {
+                return Err(mismatch("string", mapping, *mapping_span));
+            };
+
+            let scale = resolve_file(files, scale, *scale_span)?;
+            // An empty path stands for the default mapping.
+            let mapping = match mapping.is_empty() {
+                true => None,
+                false => Some(resolve_file(files, mapping, *mapping_span)?),
+            };
+
+            tuning_value(Tuning::load(&scale, mapping.as_deref()), *scale_span)?
+        }
+        _ => unreachable!("arguments are bound to the parameters of the function"),
+    };
+
+    Ok(value)
+}
+
+/// Limits a range of notes to the range of MIDI notes.
+fn midi_range(low: Note, high: Note) -> (Note, Note) {
+    let (min, max) = MIDI_RANGE;
+    let clamp = |midi: i32| Note::from_midi(midi).expect("MIDI notes are in range");
+
+    (clamp(low.midi().max(min)), clamp(high.midi().min(max)))
+}
+
+/// Transposes a note, a chord, or every element of a list.
+fn transpose(value: &Value, semitones: i32, span: Span) -> Result<Value, RuntimeError> {
+    match value {
+        Value::Note { note } => Ok(Value::Note {
+            note: note
+                .transpose(semitones)
+                .ok_or(RuntimeError::NoteOutOfRange { span })?,
+        }),
+        Value::Chord { chord } => {
+            let mut chord = *chord;
+            chord.root = chord
+                .root
+                .transpose(semitones)
+                .ok_or(RuntimeError::NoteOutOfRange { span })?;
+
+            Ok(Value::Chord { chord })
+        }
+        Value::List { values } => Ok(Value::List {
+            values: values
+                .iter()
+                .map(|value| transpose(value, semitones, span))
+                .collect::<Result<_, _>>()?,
+        }),
+        _ => Err(mismatch("note, chord or list", value, span)),
+    }
+}
+
+/// Reads the kind of a scale: its name, or a list of intervals above the
+/// root in semitones.
+fn scale_kind(value: &Value, span: Span) -> Result<ScaleKind, RuntimeError> {
+    match value {
+        Value::String { string } => {
+            ScaleKind::parse(string).ok_or_else(|| RuntimeError::UnknownScale {
+                name: string.clone(),
+                span,
+            })
+        }
+        Value::List { values } => {
+            let intervals = values
+                .iter()
+                .map(|value| expect_integer(value, span))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            ScaleKind::custom(&intervals).ok_or_else(|| RuntimeError::UnknownScale {
+                name: value.to_string(),
+                span,
+            })
+        }
+        _ => Err(mismatch("string or list", value, span)),
+    }
+}
+
+/// Moves a note, or every note of a list, to the nearest scale tone.
+fn quantize(value: &Value, scale: &Scale, span: Span) -> Result<Value, RuntimeError> {
+    match value {
+        Value::Note { note } => Ok(Value::Note {
+            note: scale.quantize(*note),
+        }),
+        Value::List { values } => Ok(Value::List {
+            values: values
+                .iter()
+                .map(|value| quantize(value, scale, span))
+                .collect::<Result<_, _>>()?,
+        }),
+        _ => Err(mismatch("note or list", value, span)),
+    }
+}
+
+fn tuning_value(tuning: Result<Tuning, TuningError>, span: Span) -> Result<Value, RuntimeError> {
+    tuning
+        .map(|tuning| Value::Tuning {
+            tuning: Box::new(tuning),
+        })
+        .map_err(|error| RuntimeError::Tuning { error, span })
+}
+
+fn note_list(notes: Vec<Note>) -> Value {
+    Value::List {
+        values: notes.into_iter().map(|note| Value::Note { note }).collect(),
+    }
+}
+
+fn expect_note(value: &Value, span: Span) -> Result<Note, RuntimeError> {
+    match value {
+        Value::Note { note } => Ok(*note),
+        _ => Err(mismatch("note", value, span)),
+    }
+}
+
+/// Reads the notes of a chord, or a voicing given as a list of notes.
+fn expect_notes(value: &Value, span: Span) -> Result<Vec<Note>, RuntimeError> {
+    match value {
+        Value::Chord { .. } | Value::List { .. } => {
+            notes(value).ok_or_else(|| mismatch("chord", value, span))
+        }
+        _ => Err(mismatch("chord", value, span)),
+    }
+}
+
+fn expect_integer(value: &Value, span: Span) -> Result<i32, RuntimeError> {
+    match value {
+        Value::Integer { value: integer } => {
+            i32::try_from(*integer).map_err(|_| mismatch("integer", value, span))
+        }
+        _ => Err(mismatch("integer", value, span)),
+    }
+}
+
+fn mismatch(expected: &'static str, value: &Value, span: Span) -> RuntimeError {
+    RuntimeError::TypeMismatch {
+        expected,
+        found: value.type_name(),
+        span,
+    }
+}
@@ -1,11 +1,188 @@
+use std::fmt::Display;
+
+use crate::{
+    analysis::resolve::SymbolId,
+    pattern::Pattern,
+    rational::{Rational, MAX_DENOMINATOR},
+    syntax::string_id::StringId,
+    theory::{chord::Chord, key::Key, note::Note, scale::Scale, tuning::Tuning, Name},
+};
+
+use super::builtins::Builtin;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Integer {
         value: i64,
     },
     Float {
-        float: f64
+        float: f64,
+    },
+    /// Exact fraction, such as the result of `1 / 3`.
+    Rational {
+        rational: Rational,
     },
     String {
-        string: String
+        string: String,
+    },
+    Bool {
+        value: bool,
+    },
+    Note {
+        note: Note,
+    },
+    Chord {
+        chord: Chord,
+    },
+    /// Key named like `d_minor`.
+    Key {
+        key: Key,
+    },
+    /// Scale created with `scale(c4, "dorian")`, whose degrees are read
+    /// with `s[5]`.
+    Scale {
+        scale: Scale,
+    },
+    /// Tuning created with `edo(19)`, `just(...)` or `scala(...)`.
+    Tuning {
+        tuning: Box<Tuning>,
+    },
+    /// Time in seconds: `8ms`, `1.5s`.
+    Duration {
+        seconds: f64,
+    },
+    List {
+        values: Vec<Value>,
+    },
+    Sample {
+        sample: SampleHandle,
+    },
+    Sequence {
+        sequence: Sequence,
+    },
+    Function {
+        function: Builtin,
+    },
+    /// Function the embedding application registered.
+    Native {
+        name: StringId,
+    },
+}
+
+impl Value {
+    /// Creates a number, which is an integer if possible.
+    /// Returns the value of a builtin name: a note, a chord or a key.
+    pub fn name(name: Name) -> Self {
+        match name {
+            Name::Note(note) => Self::Note { note },
+            Name::Chord(chord) => Self::Chord { chord },
+            Name::Key(key) => Self::Key { key },
+        }
+    }
+
+    pub fn number(rational: Rational) -> Self {
+        if rational.is_integer() {
+            Self::Integer {
+                value: rational.numerator(),
+            }
+        } else {
+            Self::Rational { rational }
+        }
+    }
+
+    /// Returns the value of a number as a rational, approximating floats.
+    pub fn as_rational(&self) -> Option<Rational> {
+        match self {
+            Self::Integer { value } => Some(Rational::integer(*value)),
+            Self::Rational { rational } => Some(*rational),
+            Self::Float { float } if float.is_finite() => {
+                Some(Rational::approximate(*float, MAX_DENOMINATOR))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Integer { value } => Some(*value as f64),
+            Self::Float { float } => Some(*float),
+            Self::Rational { rational } => Some(rational.to_f64()),
+            _ => None,
+        }
     }
+
+    /// Returns the name of the type of the value, used in errors.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Integer { .. } | Self::Float { .. } | Self::Rational { .. } => "number",
+            Self::String { .. } => "string",
+            Self::Bool { .. } => "bool",
+            Self::Note { .. } => "note",
+            Self::Chord { .. } => "chord",
+            Self::Key { .. } => "key",
+            Self::Scale { .. } => "scale",
+            Self::Tuning { .. } => "tuning",
+            Self::Duration { .. } => "duration",
+            Self::List { .. } => "list",
+            Self::Sample { .. } => "sample",
+            Self::Sequence { .. } => "sequence",
+            Self::Function { .. } | Self::Native { .. } => "function",
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Integer { value } => write!(f, "{value}"),
+            Self::Float { float } => write!(f, "{float}"),
+            Self::Rational { rational } => write!(f, "{rational}"),
+            Self::String { string } => write!(f, "{string:?}"),
+            Self::Bool { value } => write!(f, "{value}"),
+            Self::Note { note } => write!(f, "{note}"),
+            Self::Chord { chord } => write!(f, "{chord}"),
+            Self::Key { key } => write!(f, "{key}"),
+            Self::Scale { scale } => write!(f, "{scale}"),
+            Self::Tuning { tuning } => write!(f, "{tuning}"),
+            Self::Duration { seconds } => write!(f, "{seconds}s"),
+            Self::List { values } => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            Self::Sample { sample } => write!(f, "sample({:?})", sample.path.resolve()),
+            Self::Sequence { .. } => write!(f, "sequence"),
+            Self::Function { function } => write!(f, "{}", function.name()),
+            Self::Native { name } => write!(f, "{}", name.resolve()),
+        }
+    }
+}
+
+/// Sample loaded with `load_sample`. Samples loaded from the same path
+/// share their handle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SampleHandle {
+    pub id: usize,
+    pub path: StringId,
+}
+
+/// Something a sequence plays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sound {
+    Note(Note),
+    Sample(SampleHandle),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sequence {
+    /// Sequence defined with `sequence name { ... }`, played by running
+    /// its statements.
+    Defined(SymbolId),
+    /// Sequence produced by a builtin function: `fast(2, drums)`.
+    Pattern(Pattern<Sound>),
 }
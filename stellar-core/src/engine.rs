@@ -0,0 +1,229 @@
+//! Embedding API: compiles and runs scripts inside of another Rust
+//! application, which can extend the language with its own functions and
+//! values.
+//!
+//! ```
+//! use stellar_core::{ctx::value::Value, Engine};
+//!
+//! let mut engine = Engine::new();
+//! engine.set_global("bars", Value::Integer { value: 2 }).unwrap();
+//! engine
+//!     .register_fn("double", |arguments| match arguments {
+//!         [Value::Integer { value }] => Ok(Value::Integer { value: value * 2 }),
+//!         _ => Err("expected a number".to_string()),
+//!     })
+//!     .unwrap();
+//!
+//! let script = engine.compile("repeat double(bars) {\n play c4\n wait 1\n}").unwrap();
+//! let ir = engine.run(&script).unwrap();
+//! assert_eq!(ir.end(), 4.into());
+//! ```
+
+use crate::{
+    analysis::resolve::{resolve_with, ResolveError},
+    ctx::{
+        host::{Host, HostError},
+        interpreter::{Options, RuntimeError},
+        value::Value,
+    },
+    ir::Ir,
+    syntax::{
+        parse::{parse, ParseError},
+        scan::{scan, ScanError},
+    },
+    vm::{compile::compile_with, machine::run_with, Program},
+};
+
+#[derive(Debug)]
+pub enum EngineError {
+    Scan(ScanError),
+    Parse(ParseError),
+    Resolve(Vec<ResolveError>),
+    Runtime(RuntimeError),
+}
+
+/// Script compiled by an [`Engine`], which can be run many times.
+#[derive(Debug, Clone)]
+pub struct Script {
+    program: Program,
+}
+
+impl Script {
+    /// Returns the bytecode of the script.
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+}
+
+#[derive(Default)]
+pub struct Engine {
+    /// Options every script is run with.
+    pub options: Options,
+    host: Host,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a function callable by scripts compiled afterwards. Arguments
+    /// are passed in order and can't be named. Names of builtin functions,
+    /// notes, chords and keys are rejected.
+    pub fn register_fn(
+        &mut self,
+        name: &str,
+        function: impl Fn(&[Value]) -> Result<Value, String> + 'static,
+    ) -> Result<(), HostError> {
+        self.host.register_fn(name, function)
+    }
+
+    /// Sets a global, which scripts can read, but not assign. Scripts see
+    /// the value it has when they are run, but only globals set before
+    /// they are compiled. Names of builtin functions, notes, chords and
+    /// keys are rejected.
+    pub fn set_global(&mut self, name: &str, value: Value) -> Result<(), HostError> {
+        self.host.set_global(name, value)
+    }
+
+    pub fn compile(&self, source: &str) -> Result<Script, EngineError> {
+        let tokens = scan(source).map_err(EngineError::Scan)?;
+        let statements = parse(tokens).map_err(EngineError::Parse)?;
+
+        let resolution = resolve_with(&statements, &|name| self.host.contains(name));
+        if !resolution.errors.is_empty() {
+            return Err(EngineError::Resolve(resolution.errors));
+        }
+
+        Ok(Script {
+            program: compile_with(&statements, &resolution, &self.host),
+        })
+    }
+
+    /// Runs a script and returns the events it plays.
+    pub fn run(&self, script: &Script) -> Result<Ir, EngineError> {
+        run_with(&script.program, &self.options, &self.host).map_err(EngineError::Runtime)
+    }
+
+    /// Compiles and runs a script once.
+    pub fn eval(&self, source: &str) -> Result<Ir, EngineError> {
+        self.run(&self.compile(source)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use insta::assert_snapshot;
+
+    use crate::{
+        ctx::{interpreter::RuntimeError, value::Value},
+        theory::note::Note,
+    };
+
+    use super::{Engine, EngineError, HostError};
+
+    fn engine() -> Engine {
+        let mut engine = Engine::new();
+        engine
+            .set_global(
+                "root",
+                Value::Note {
+                    note: Note::parse("c4").unwrap(),
+                },
+            )
+            .unwrap();
+        engine
+            .register_fn("fifth", |arguments| match arguments {
+                [Value::Note { note }] => note
+                    .transpose(7)
+                    .map(|note| Value::Note { note })
+                    .ok_or_else(|| "note out of range".to_string()),
+                _ => Err("expected a note".to_string()),
+            })
+            .unwrap();
+
+        engine
+    }
+
+    #[test]
+    fn host() {
+        let source =
+            "sequence a {\n play [root, fifth(root)]\n wait 1\n}\nplay! a\nplay fifth(fifth(root))";
+
+        assert_snapshot!(engine().eval(source).unwrap().to_string());
+    }
+
+    #[test]
+    fn globals() {
+        let mut engine = engine();
+        let script = engine.compile("play root").unwrap();
+        let first = engine.run(&script).unwrap();
+
+        engine
+            .set_global(
+                "root",
+                Value::Note {
+                    note: Note::parse("d4").unwrap(),
+                },
+            )
+            .unwrap();
+        let second = engine.run(&script).unwrap();
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn calls() {
+        let calls = Rc::new(Cell::new(0));
+        let mut engine = Engine::new();
+
+        let counter = calls.clone();
+        engine
+            .register_fn("count", move |_| {
+                counter.set(counter.get() + 1);
+                Ok(Value::Integer { value: 1 })
+            })
+            .unwrap();
+
+        engine.eval("repeat 3 {\n wait count()\n}").unwrap();
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn errors() {
+        let engine = engine();
+
+        assert!(matches!(
+            engine.eval("play missing"),
+            Err(EngineError::Resolve(errors)) if errors.len() == 1
+        ));
+        assert!(matches!(
+            engine.eval("play fifth(1)"),
+            Err(EngineError::Runtime(RuntimeError::Native { message, .. }))
+                if message == "expected a note"
+        ));
+        assert!(matches!(
+            engine.eval("play fifth(note: root)"),
+            Err(EngineError::Runtime(RuntimeError::Native { .. }))
+        ));
+    }
+
+    #[test]
+    fn reserved_names() {
+        let mut engine = Engine::new();
+
+        for name in ["invert", "c4", "cmaj7", "c_major"] {
+            assert_eq!(
+                engine.set_global(name, Value::Integer { value: 1 }),
+                Err(HostError::ReservedName {
+                    name: name.to_owned()
+                })
+            );
+        }
+        assert!(engine
+            .register_fn("fast", |_| Ok(Value::Integer { value: 1 }))
+            .is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! Event timeline: the intermediate representation every backend (audio
+//! renderer, MIDI export, visualisation) consumes instead of walking the
+//! syntax tree.
+//!
+//! A program is flattened into [`Channel`]s of [`Event`]s placed at
+//! rational beat positions. The first channel is the main one, and every
+//! `play!` adds another. The [interpreter](crate::ctx::interpreter) is the
+//! only producer of the timeline, so that backends play exactly what `run`
+//! prints.
+
+use std::fmt::Display;
+
+use crate::{
+    analysis::fold::Constant,
+    audio::tempo::{Curve, Ramp},
+    rational::Rational,
+    syntax::{location::Span, string_id::StringId},
+    theory::{key::Key, note::Note, tuning::Tuning},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    NoteOn {
+        note: Note,
+        /// Loudness in range `[0, 1]`.
+        velocity: f64,
+        /// Key of the block, which played the note.
+        key: Option<Key>,
+    },
+    NoteOff {
+        note: Note,
+    },
+    SampleTrigger {
+        /// Path of the sample file, as written in `load_sample`.
+        path: StringId,
+        velocity: f64,
+    },
+    /// Change of a property, which is applied by backends rather than
+    /// baked into the events: `with synth: pad { ... }`. `None` restores
+    /// the default value at the end of the block.
+    ParamChange {
+        name: StringId,
+        value: Option<Constant>,
+    },
+    TempoChange {
+        bpm: f64,
+        ramp: Option<Ramp>,
+    },
+    /// Change of the tuning, which backends convert notes to frequencies
+    /// with: `tuning edo(19)`.
+    TuningChange {
+        tuning: Box<Tuning>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub beat: Rational,
+    pub kind: EventKind,
+    /// Span of the statement, which produced the event.
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Channel {
+    /// Events ordered by their beat. Events at the same beat keep the
+    /// order they were produced in, except that notes are released before
+    /// new ones start.
+    pub events: Vec<Event>,
+}
+
+impl Channel {
+    pub fn new(mut events: Vec<Event>) -> Self {
+        events.sort_by_key(|event| {
+            let starts = !matches!(event.kind, EventKind::NoteOff { .. });
+            (event.beat, starts)
+        });
+
+        Self { events }
+    }
+
+    /// Returns the beat, at which the last event happens.
+    pub fn end(&self) -> Rational {
+        self.events
+            .last()
+            .map_or(Rational::ZERO, |event| event.beat)
+    }
+}
+
+/// Flattened program.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ir {
+    pub channels: Vec<Channel>,
+}
+
+impl Ir {
+    /// Returns the beat, at which the last event of any channel happens.
+    pub fn end(&self) -> Rational {
+        self.channels
+            .iter()
+            .map(Channel::end)
+            .max()
+            .unwrap_or(Rational::ZERO)
+    }
+}
+
+impl Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoteOn { note, velocity, .. } => write!(f, "note_on {note} {velocity}"),
+            Self::NoteOff { note } => write!(f, "note_off {note}"),
+            Self::SampleTrigger { path, velocity } => {
+                write!(f, "sample {:?} {velocity}", path.resolve())
+            }
+            Self::ParamChange { name, value } => match value {
+                Some(value) => write!(f, "param {} {value}", name.resolve()),
+                None => write!(f, "param {} default", name.resolve()),
+            },
+            Self::TempoChange { bpm, ramp } => {
+                write!(f, "tempo {bpm}")?;
+
+                if let Some(ramp) = ramp {
+                    let curve = match ramp.curve {
+                        Curve::Linear => "linear",
+                        Curve::Exponential => "exponential",
+                    };
+                    write!(f, " -> {} over {} {curve}", ramp.target, ramp.length)?;
+                }
+
+                Ok(())
+            }
+            Self::TuningChange { tuning } => write!(f, "tuning {tuning}"),
+        }
+    }
+}
+
+impl Display for Ir {
+    /// Lists events of every channel, one per line: `1/2: note_on c4 1`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, channel) in self.channels.iter().enumerate() {
+            writeln!(f, "channel {index}:")?;
+
+            for event in &channel.events {
+                writeln!(f, "  {}: {}", event.beat, event.kind)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1 +1,14 @@
+pub mod analysis;
+pub mod audio;
+pub mod ctx;
+pub mod engine;
+pub mod ir;
+pub mod lint;
+pub mod pattern;
+pub mod random;
+pub mod rational;
 pub mod syntax;
+pub mod theory;
+pub mod vm;
+
+pub use engine::Engine;
@@ -0,0 +1,154 @@
+//! Project-wide lint configuration, read from `stellar.toml` in the
+//! directory of the program or any of its parents:
+//!
+//! ```toml
+//! [lints]
+//! unused = "allow"
+//! shadowed = "deny"
+//! ```
+//!
+//! Only the `[lints]` table is read, other tables are ignored. The file is
+//! read as a subset of TOML: one `rule = "level"` pair per line, with the
+//! rule written bare or quoted, the level quoted as a basic (`"deny"`) or
+//! literal (`'deny'`) string without escapes, and comments starting with
+//! `#` outside of quotes.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{Level, Rule};
+
+/// Name of the project config file.
+pub const CONFIG_FILE: &str = "stellar.toml";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LintConfig {
+    levels: HashMap<Rule, Level>,
+}
+
+impl LintConfig {
+    /// Returns the configured level of a rule.
+    pub fn level(&self, rule: Rule) -> Level {
+        self.levels
+            .get(&rule)
+            .copied()
+            .unwrap_or(rule.default_level())
+    }
+
+    pub fn set(&mut self, rule: Rule, level: Level) {
+        self.levels.insert(rule, level);
+    }
+
+    /// Sets the level of a rule, unless the config file sets it already.
+    pub fn set_default(&mut self, rule: Rule, level: Level) {
+        self.levels.entry(rule).or_insert(level);
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ConfigError> {
+        let mut config = Self::default();
+        let mut in_lints = false;
+
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(table) = line.strip_prefix('[') {
+                in_lints = table.strip_suffix(']').map(str::trim) == Some("lints");
+                continue;
+            }
+
+            if !in_lints {
+                continue;
+            }
+
+            let invalid = ConfigError::InvalidLine { line: number };
+            let (name, level) = line.split_once('=').ok_or(invalid.clone())?;
+            let name = name.trim();
+            let name = match name.starts_with(['"', '\'']) {
+                true => unquote(name).ok_or(invalid.clone())?,
+                false => name,
+            };
+            let level = unquote(level.trim()).ok_or(invalid)?;
+
+            let rule = Rule::from_id(name).ok_or_else(|| ConfigError::UnknownRule {
+                name: name.to_owned(),
+                line: number,
+            })?;
+            let level = Level::parse(level).ok_or_else(|| ConfigError::InvalidLevel {
+                level: level.to_owned(),
+                line: number,
+            })?;
+
+            config.set(rule, level);
+        }
+
+        Ok(config)
+    }
+
+    /// Reads the config file closest to a given directory, or returns the
+    /// default configuration if there is none.
+    pub fn discover(directory: &Path) -> Result<Self, ConfigError> {
+        let Some(path) = directory
+            .ancestors()
+            .map(|directory| directory.join(CONFIG_FILE))
+            .find(|path| path.is_file())
+        else {
+            return Ok(Self::default());
+        };
+
+        let source = fs::read_to_string(&path).map_err(|_| ConfigError::NotReadable { path })?;
+
+        Self::parse(&source)
+    }
+}
+
+/// Removes a comment from the end of a line, unless `#` is quoted.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (index, char) in line.char_indices() {
+        match (quote, char) {
+            // Basic strings may contain escaped quotes.
+            (Some('"'), _) if escaped => escaped = false,
+            (Some('"'), '\\') => escaped = true,
+            (Some(open), _) if char == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(char),
+            (None, '#') => return &line[..index],
+            (None, _) => {}
+        }
+    }
+
+    line
+}
+
+/// Reads a basic or literal string without escapes, which takes up the
+/// whole text.
+fn unquote(text: &str) -> Option<&str> {
+    let quote = text
+        .chars()
+        .next()
+        .filter(|char| ['"', '\''].contains(char))?;
+    let inner = text.strip_prefix(quote)?.strip_suffix(quote)?;
+
+    match inner.contains([quote, '\\']) {
+        true => None,
+        false => Some(inner),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    NotReadable { path: PathBuf },
+    InvalidLine { line: usize },
+    UnknownRule { name: String, line: usize },
+    InvalidLevel { level: String, line: usize },
+}
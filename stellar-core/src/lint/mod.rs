@@ -0,0 +1,426 @@
+//! Lints: warnings about programs, which are valid but most likely don't
+//! do what their author intended.
+//!
+//! Every rule has an ID and a default [`Level`], which can be changed for
+//! a whole project in the [config file](config), and for a single statement
+//! with an attribute:
+//!
+//! ```text
+//! #[allow(unused)]
+//! let sketch = [c4, e4]
+//! ```
+
+pub mod config;
+mod rules;
+
+use std::fmt::Display;
+
+use crate::{
+    analysis::resolve::Resolution,
+    ctx::interpreter::Options,
+    syntax::{
+        ast::Statement,
+        location::{Span, Spanned},
+        string_id::StringId,
+        token::Identifier,
+    },
+    theory::{key::Key, note::Note},
+};
+
+use self::config::LintConfig;
+
+/// What to do when a rule is broken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(Self::Allow),
+            "warn" => Some(Self::Warn),
+            "deny" => Some(Self::Deny),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Allow => write!(f, "allow"),
+            Self::Warn => write!(f, "warn"),
+            Self::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// `let` value, which is never used.
+    Unused,
+    /// Statement after an endless `repeat`.
+    Unreachable,
+    /// `wait 0`.
+    ZeroWait,
+    /// `let` hiding a variable or a sequence with the same name.
+    Shadowed,
+    /// Note, which the synth of the enclosing `with` block can't play.
+    OutOfRange,
+    /// `with` block without statements.
+    EmptyWith,
+    /// Attribute or rule name, which doesn't exist.
+    UnknownLint,
+    /// Two voices moving in parallel fifths.
+    ParallelFifths,
+    /// Two voices moving in parallel octaves or unisons.
+    ParallelOctaves,
+    /// Voice moving above or below a neighbouring voice.
+    VoiceCrossing,
+    /// Note, which doesn't belong to the key of the enclosing `with` block.
+    OutOfKey,
+}
+
+impl Rule {
+    pub const ALL: [Self; 11] = [
+        Self::Unused,
+        Self::Unreachable,
+        Self::ZeroWait,
+        Self::Shadowed,
+        Self::OutOfRange,
+        Self::EmptyWith,
+        Self::UnknownLint,
+        Self::ParallelFifths,
+        Self::ParallelOctaves,
+        Self::VoiceCrossing,
+        Self::OutOfKey,
+    ];
+
+    /// Music theory rules, which are allowed unless enabled explicitly.
+    pub const THEORY: [Self; 4] = [
+        Self::ParallelFifths,
+        Self::ParallelOctaves,
+        Self::VoiceCrossing,
+        Self::OutOfKey,
+    ];
+
+    /// Returns the name used in attributes and the config file.
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Unused => "unused",
+            Self::Unreachable => "unreachable",
+            Self::ZeroWait => "zero_wait",
+            Self::Shadowed => "shadowed",
+            Self::OutOfRange => "out_of_range",
+            Self::EmptyWith => "empty_with",
+            Self::UnknownLint => "unknown_lint",
+            Self::ParallelFifths => "parallel_fifths",
+            Self::ParallelOctaves => "parallel_octaves",
+            Self::VoiceCrossing => "voice_crossing",
+            Self::OutOfKey => "out_of_key",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|rule| rule.id() == id)
+    }
+
+    /// Returns the level used, unless configured otherwise.
+    pub fn default_level(self) -> Level {
+        match self {
+            // Shadowing is a common way to refine a value step by step.
+            Self::Shadowed => Level::Allow,
+            // Rules of common-practice harmony are a matter of style.
+            _ if Self::THEORY.contains(&self) => Level::Allow,
+            _ => Level::Warn,
+        }
+    }
+}
+
+/// Broken rule together with the code breaking it.
+#[derive(Debug, PartialEq)]
+pub enum LintKind {
+    UnusedVariable {
+        name: Identifier,
+    },
+    UnreachableStatement {
+        span: Span,
+    },
+    ZeroWait {
+        span: Span,
+    },
+    ShadowedName {
+        name: Identifier,
+        previous: Identifier,
+    },
+    NoteOutOfRange {
+        note: Note,
+        synth: StringId,
+        span: Span,
+    },
+    EmptyWith {
+        span: Span,
+    },
+    UnknownAttribute {
+        name: Identifier,
+    },
+    UnknownRule {
+        name: Identifier,
+    },
+    ParallelFifths {
+        span: Span,
+        previous: Span,
+    },
+    ParallelOctaves {
+        span: Span,
+        previous: Span,
+    },
+    VoiceCrossing {
+        span: Span,
+    },
+    OutOfKey {
+        note: Note,
+        key: Key,
+        span: Span,
+    },
+}
+
+impl LintKind {
+    pub fn rule(&self) -> Rule {
+        match self {
+            Self::UnusedVariable { .. } => Rule::Unused,
+            Self::UnreachableStatement { .. } => Rule::Unreachable,
+            Self::ZeroWait { .. } => Rule::ZeroWait,
+            Self::ShadowedName { .. } => Rule::Shadowed,
+            Self::NoteOutOfRange { .. } => Rule::OutOfRange,
+            Self::EmptyWith { .. } => Rule::EmptyWith,
+            Self::UnknownAttribute { .. } | Self::UnknownRule { .. } => Rule::UnknownLint,
+            Self::ParallelFifths { .. } => Rule::ParallelFifths,
+            Self::ParallelOctaves { .. } => Rule::ParallelOctaves,
+            Self::VoiceCrossing { .. } => Rule::VoiceCrossing,
+            Self::OutOfKey { .. } => Rule::OutOfKey,
+        }
+    }
+}
+
+impl Spanned for LintKind {
+    fn span(&self) -> Span {
+        match self {
+            Self::UnusedVariable { name }
+            | Self::ShadowedName { name, .. }
+            | Self::UnknownAttribute { name }
+            | Self::UnknownRule { name } => name.span(),
+            Self::UnreachableStatement { span }
+            | Self::ZeroWait { span }
+            | Self::NoteOutOfRange { span, .. }
+            | Self::EmptyWith { span }
+            | Self::ParallelFifths { span, .. }
+            | Self::ParallelOctaves { span, .. }
+            | Self::VoiceCrossing { span }
+            | Self::OutOfKey { span, .. } => *span,
+        }
+    }
+}
+
+impl Display for LintKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnusedVariable { name } => {
+                write!(f, "variable `{}` is never used", name.name().resolve())
+            }
+            Self::UnreachableStatement { .. } => {
+                write!(f, "statement is unreachable after an endless `repeat`")
+            }
+            Self::ZeroWait { .. } => write!(f, "`wait 0` doesn't wait"),
+            Self::ShadowedName { name, previous } => write!(
+                f,
+                "`{}` shadows the definition at {}:{}",
+                name.name().resolve(),
+                previous.span().start().line(),
+                previous.span().start().column()
+            ),
+            Self::NoteOutOfRange { note, synth, .. } => write!(
+                f,
+                "note `{note}` is outside of the range of `{}`",
+                synth.resolve()
+            ),
+            Self::EmptyWith { .. } => write!(f, "`with` block is empty"),
+            Self::UnknownAttribute { name } => {
+                write!(f, "unknown attribute `{}`", name.name().resolve())
+            }
+            Self::UnknownRule { name } => write!(f, "unknown lint `{}`", name.name().resolve()),
+            Self::ParallelFifths { previous, .. } => write!(
+                f,
+                "parallel fifths after the notes at {}:{}",
+                previous.start().line(),
+                previous.start().column()
+            ),
+            Self::ParallelOctaves { previous, .. } => write!(
+                f,
+                "parallel octaves after the notes at {}:{}",
+                previous.start().line(),
+                previous.start().column()
+            ),
+            Self::VoiceCrossing { .. } => write!(f, "voices cross"),
+            Self::OutOfKey { note, key, .. } => write!(f, "note `{note}` is outside of `{key}`"),
+        }
+    }
+}
+
+/// Broken rule reported at a given level.
+#[derive(Debug, PartialEq)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub level: Level,
+}
+
+impl Spanned for Lint {
+    fn span(&self) -> Span {
+        self.kind.span()
+    }
+}
+
+/// Checks a resolved program against every rule, which is not allowed in
+/// the config or with attributes. Rules about the notes the program plays
+/// run it with the options. Lints are ordered by their position.
+pub fn lint(
+    statements: &[Statement],
+    resolution: &Resolution,
+    config: &LintConfig,
+    options: &Options,
+) -> Vec<Lint> {
+    let (kinds, overrides) = rules::check(statements, resolution, options);
+
+    kinds
+        .into_iter()
+        .filter_map(|kind| {
+            let rule = kind.rule();
+            let span = kind.span();
+
+            // Overrides are ordered from outer to inner statements.
+            let level = overrides
+                .iter()
+                .rev()
+                .find(|attribute| attribute.rule == rule && attribute.span.contains(span))
+                .map_or(config.level(rule), |attribute| attribute.level);
+
+            (level != Level::Allow).then_some(Lint { kind, level })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_snapshot;
+
+    use crate::{
+        analysis::resolve::resolve,
+        ctx::interpreter::Options,
+        syntax::{location::Spanned, parse::parse, scan::scan},
+        test_snapshots,
+    };
+
+    use super::{
+        config::{ConfigError, LintConfig},
+        lint, Level, Rule,
+    };
+
+    fn render(source: &str) -> String {
+        render_with(source, &LintConfig::default())
+    }
+
+    fn render_with(source: &str, config: &LintConfig) -> String {
+        let statements = parse(scan(source).unwrap()).unwrap();
+        let lints = lint(
+            &statements,
+            &resolve(&statements),
+            config,
+            &Options::default(),
+        );
+
+        lints
+            .iter()
+            .map(|lint| {
+                let start = lint.span().start();
+                format!(
+                    "{}:{}: {}[{}]: {}\n",
+                    start.line(),
+                    start.column(),
+                    lint.level,
+                    lint.kind.rule().id(),
+                    lint.kind
+                )
+            })
+            .collect()
+    }
+
+    test_snapshots!(
+        (unused, "let a = 1\nlet _b = 2\nlet c = 3\nplay [c4 + c]"),
+        (
+            unreachable,
+            "sequence a {\n repeat {\n  play c4\n  wait 1\n }\n play e4\n wait 1\n}"
+        ),
+        (zero_wait, "wait 0\nwait 1/2 - 1/2\nwait 1"),
+        (
+            out_of_range,
+            "with synth: bass {\n play [c1, c3, g5]\n with transpose: 24 {\n  play e3\n }\n}"
+        ),
+        (
+            out_of_range_overflow,
+            "with synth: bass {\n play [c4 + 9999999999, c4 - 2147483648]\n with transpose: 2147483647 {\n  with transpose: 1 {\n   play [c1, e3 - 1]\n  }\n }\n}"
+        ),
+        (empty_with, "with synth: dsaw {}\nwith synth: dsaw {\n play c4\n}"),
+        (
+            attributes,
+            "#[allow(unused)]\nlet a = 1\n#[deny(zero_wait)]\nsequence s {\n #[allow(zero_wait)]\n wait 0\n wait 0\n}\n#[alow(unused)]\n#[warn(unusd)]\nlet b = 2\nplay s"
+        ),
+    );
+
+    #[test]
+    fn config() {
+        let config = LintConfig::parse("[lints]\nshadowed = \"deny\"\nunused = \"allow\"").unwrap();
+        assert_eq!(config.level(Rule::Shadowed), Level::Deny);
+        assert_eq!(config.level(Rule::EmptyWith), Level::Warn);
+
+        assert_snapshot!(render_with("let a = 1\nlet a = a + 1", &config));
+
+        let config = LintConfig::parse(
+            "# Project\n[lints] # lints\n\"unused\" = 'deny' # \"#\"\n[tool]\nname = \"a # b\"",
+        )
+        .unwrap();
+        assert_eq!(config.level(Rule::Unused), Level::Deny);
+
+        for source in [
+            "[lints]\nunused = allow",
+            "[lints]\nunused = \"allow\" deny",
+        ] {
+            assert_eq!(
+                LintConfig::parse(source),
+                Err(ConfigError::InvalidLine { line: 2 })
+            );
+        }
+    }
+
+    #[test]
+    fn theory() {
+        let source = "with key: c_major {\n play [c4, g4]\n wait 1\n play [d4, a4]\n wait 1\n #[allow(out_of_key)]\n play [f4, ab4]\n}";
+        assert_snapshot!(render(source));
+
+        let mut config = LintConfig::default();
+        for rule in Rule::THEORY {
+            config.set(rule, Level::Warn);
+        }
+        assert_snapshot!(render_with(source, &config));
+
+        // Rules allowed by the config file stay allowed.
+        let mut config = LintConfig::parse("[lints]\nparallel_fifths = \"allow\"").unwrap();
+        for rule in Rule::THEORY {
+            config.set_default(rule, Level::Warn);
+        }
+        assert_eq!(config.level(Rule::ParallelFifths), Level::Allow);
+        assert_eq!(config.level(Rule::OutOfKey), Level::Warn);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::{
+    analysis::{
+        harmony::{check_harmony, HarmonyWarning},
+        resolve::{Resolution, SymbolKind},
+        timeline::{timeline, TimedNote},
+    },
+    audio::preset::Preset,
+    ctx::{
+        interpreter::{interpret, Options},
+        properties::literal_rational,
+    },
+    rational::Rational,
+    syntax::{
+        ast::Statement,
+        location::{Span, Spanned},
+    },
+};
+
+use super::{Level, LintKind, Rule};
+
+/// Level set for a rule with an attribute of a statement.
+pub(super) struct Override {
+    pub span: Span,
+    pub rule: Rule,
+    pub level: Level,
+}
+
+/// Finds every broken rule and every level set with attributes.
+pub(super) fn check(
+    statements: &[Statement],
+    resolution: &Resolution,
+    options: &Options,
+) -> (Vec<LintKind>, Vec<Override>) {
+    let mut linter = Linter {
+        resolution,
+        lints: Vec::new(),
+        overrides: Vec::new(),
+    };
+
+    linter.check_statements(statements);
+    linter.check_symbols();
+
+    // Notes played are only known for programs, which run.
+    if let Ok(ir) = interpret(statements, resolution, options) {
+        let notes = timeline(&ir);
+
+        linter.check_range(&notes);
+        linter.check_harmony(&notes);
+    }
+
+    let mut lints = linter.lints;
+    lints.sort_by_key(|lint| lint.span().start().index());
+
+    (lints, linter.overrides)
+}
+
+struct Linter<'r> {
+    resolution: &'r Resolution,
+    lints: Vec<LintKind>,
+    overrides: Vec<Override>,
+}
+
+impl Linter<'_> {
+    fn check_statements(&mut self, statements: &[Statement]) {
+        let mut endless = false;
+
+        for statement in statements {
+            if endless {
+                self.lints.push(LintKind::UnreachableStatement {
+                    span: statement.span(),
+                });
+                endless = false;
+            }
+
+            if let Statement::Repeat { count: None, .. } = statement.unattributed() {
+                endless = true;
+            }
+
+            self.check_statement(statement);
+        }
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Attributed {
+                attributes,
+                statement: inner,
+            } => {
+                for attribute in attributes {
+                    let Some(level) = Level::parse(&attribute.name.name().resolve()) else {
+                        self.lints.push(LintKind::UnknownAttribute {
+                            name: attribute.name,
+                        });
+                        continue;
+                    };
+
+                    for name in &attribute.arguments {
+                        match Rule::from_id(&name.name().resolve()) {
+                            Some(rule) => self.overrides.push(Override {
+                                span: statement.span(),
+                                rule,
+                                level,
+                            }),
+                            None => self.lints.push(LintKind::UnknownRule { name: *name }),
+                        }
+                    }
+                }
+
+                self.check_statement(inner);
+            }
+            Statement::Wait { expression } => {
+                if literal_rational(expression) == Some(Rational::ZERO) {
+                    self.lints.push(LintKind::ZeroWait {
+                        span: expression.span(),
+                    });
+                }
+            }
+            Statement::With { block, .. } => {
+                if block.statements.is_empty() {
+                    self.lints.push(LintKind::EmptyWith {
+                        span: statement.span(),
+                    });
+                }
+
+                self.check_statements(&block.statements);
+            }
+            Statement::Sequence { block, .. }
+            | Statement::Poly { block, .. }
+            | Statement::Repeat { block, .. } => self.check_statements(&block.statements),
+            Statement::Play { .. }
+            | Statement::Let { .. }
+            | Statement::Tempo { .. }
+            | Statement::Tuning { .. }
+            | Statement::Expression(_) => {}
+        }
+    }
+
+    /// Checks notes the program plays against the range of their synth.
+    /// Every note is reported once, even if it is played many times.
+    fn check_range(&mut self, notes: &[TimedNote]) {
+        for note in notes {
+            let Some(synth) = note.synth else {
+                continue;
+            };
+            let Some(preset) = Preset::find(&synth.resolve()) else {
+                continue;
+            };
+
+            let lint = LintKind::NoteOutOfRange {
+                note: note.note,
+                synth,
+                span: note.span,
+            };
+            if !preset.contains(note.note) && !self.lints.contains(&lint) {
+                self.lints.push(lint);
+            }
+        }
+    }
+
+    /// Checks voice leading and keys of the notes the program plays.
+    fn check_harmony(&mut self, notes: &[TimedNote]) {
+        self.lints.extend(
+            check_harmony(notes)
+                .into_iter()
+                .map(|warning| match warning {
+                    HarmonyWarning::ParallelFifths { span, previous } => {
+                        LintKind::ParallelFifths { span, previous }
+                    }
+                    HarmonyWarning::ParallelOctaves { span, previous } => {
+                        LintKind::ParallelOctaves { span, previous }
+                    }
+                    HarmonyWarning::VoiceCrossing { span } => LintKind::VoiceCrossing { span },
+                    HarmonyWarning::OutOfKey { note, key, span } => {
+                        LintKind::OutOfKey { note, key, span }
+                    }
+                }),
+        );
+    }
+
+    /// Checks rules about `let` definitions.
+    fn check_symbols(&mut self) {
+        for (id, symbol) in self.resolution.symbols() {
+            if symbol.kind != SymbolKind::Let {
+                continue;
+            }
+
+            let name = symbol.name.name();
+
+            if self.resolution.usages(id) == 0 && !name.resolve().starts_with('_') {
+                self.lints
+                    .push(LintKind::UnusedVariable { name: symbol.name });
+            }
+
+            // Earlier definitions in the same scope or in enclosing ones.
+            let mut scope = Some(symbol.scope);
+            let previous = std::iter::from_fn(|| {
+                let current = self.resolution.scope(scope?);
+                scope = current.parent;
+                Some(current)
+            })
+            .flat_map(|scope| scope.symbols.iter().rev())
+            .map(|id| self.resolution.symbol(*id))
+            .find(|other| {
+                other.name.name() == name
+                    && other.name.span() != symbol.name.span()
+                    && (other.kind == SymbolKind::Sequence
+                        || other.name.span().start().index() < symbol.name.span().start().index())
+            });
+
+            if let Some(previous) = previous {
+                self.lints.push(LintKind::ShadowedName {
+                    name: symbol.name,
+                    previous: previous.name,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Breaking chords into timed notes.
+
+use crate::{random::Rng, rational::Rational, theory::note::Note};
+
+use super::{Event, Pattern, PatternError};
+
+/// Default length of a single arpeggiated note: a sixteenth note.
+pub const DEFAULT_RATE: Rational = Rational::new(1, 4);
+
+/// Order, in which an arpeggiator plays the chord tones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArpMode {
+    /// From the lowest note to the highest one.
+    Up,
+    /// From the highest note to the lowest one.
+    Down,
+    /// Up and then down, without repeating the outer notes.
+    UpDown,
+    /// Random chord tone on every step.
+    Random,
+    /// All chord tones together on every step.
+    Chord,
+}
+
+impl ArpMode {
+    /// Parses a mode name as written in `with arp: <mode>`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "up" => Some(Self::Up),
+            "down" => Some(Self::Down),
+            "updown" => Some(Self::UpDown),
+            "random" => Some(Self::Random),
+            "chord" => Some(Self::Chord),
+            _ => None,
+        }
+    }
+}
+
+/// Breaks chords into sequences of notes:
+/// `with arp: up, rate: 1/16, octaves: 2 { play cmaj7 }`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arpeggiator {
+    pub mode: ArpMode,
+    /// Length of a single step in beats.
+    pub rate: Rational,
+    /// Number of octaves the chord tones are spread over.
+    pub octaves: u32,
+}
+
+impl Arpeggiator {
+    pub fn new(mode: ArpMode) -> Self {
+        Self {
+            mode,
+            rate: DEFAULT_RATE,
+            octaves: 1,
+        }
+    }
+
+    /// Fills `length` beats with steps built from the chord tones.
+    pub fn arpeggiate(
+        &self,
+        notes: &[Note],
+        length: Rational,
+        rng: &mut Rng,
+    ) -> Result<Pattern<Note>, PatternError> {
+        if self.rate <= Rational::ZERO {
+            return Err(PatternError::InvalidFactor { factor: self.rate });
+        }
+
+        if self.octaves == 0 {
+            return Err(PatternError::InvalidCount { count: 0 });
+        }
+
+        let mut pattern = Pattern::silence(length);
+
+        // Spreading the tones stops at the first one above the range of
+        // notes, before allocating the rest.
+        let tones: Option<Vec<_>> = (0..self.octaves)
+            .flat_map(|octave| {
+                notes.iter().map(move |note| {
+                    let semitones = i32::try_from(octave).ok()?.checked_mul(12)?;
+
+                    note.transpose(semitones)
+                })
+            })
+            .collect();
+        let Some(mut tones) = tones else {
+            return Err(PatternError::InvalidCount {
+                count: self.octaves as usize,
+            });
+        };
+        tones.sort();
+
+        if tones.is_empty() {
+            return Ok(pattern);
+        }
+
+        let order: Vec<_> = match self.mode {
+            ArpMode::Up | ArpMode::Random | ArpMode::Chord => tones.clone(),
+            ArpMode::Down => tones.iter().rev().copied().collect(),
+            ArpMode::UpDown => {
+                let inner = tones.len().saturating_sub(2);
+
+                tones
+                    .iter()
+                    .copied()
+                    .chain(tones.iter().rev().skip(1).take(inner).copied())
+                    .collect()
+            }
+        };
+
+        let mut start = Rational::ZERO;
+        let mut step = 0;
+
+        while start < length {
+            let duration = self.rate.min(length - start);
+
+            match self.mode {
+                ArpMode::Chord => {
+                    for tone in &tones {
+                        pattern.push(Event::new(start, duration, *tone));
+                    }
+                }
+                ArpMode::Random => {
+                    let tone = tones[rng.below(tones.len() as u64) as usize];
+
+                    pattern.push(Event::new(start, duration, tone));
+                }
+                _ => pattern.push(Event::new(start, duration, order[step % order.len()])),
+            }
+
+            start += self.rate;
+            step += 1;
+        }
+
+        Ok(pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        random::Rng,
+        rational::Rational,
+        theory::{chord::Chord, note::Note},
+    };
+
+    use super::{ArpMode, Arpeggiator};
+
+    fn arpeggiate(mode: ArpMode, octaves: u32, steps: i64) -> Vec<String> {
+        let arpeggiator = Arpeggiator {
+            octaves,
+            ..Arpeggiator::new(mode)
+        };
+        let notes = Chord::parse("cmaj").unwrap().notes();
+        let length = arpeggiator.rate * Rational::integer(steps);
+
+        arpeggiator
+            .arpeggiate(&notes, length, &mut Rng::new(0))
+            .unwrap()
+            .events()
+            .iter()
+            .map(|event| event.value.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn modes() {
+        assert_eq!(arpeggiate(ArpMode::Up, 1, 4), ["c4", "e4", "g4", "c4"]);
+        assert_eq!(arpeggiate(ArpMode::Down, 1, 3), ["g4", "e4", "c4"]);
+        assert_eq!(
+            arpeggiate(ArpMode::UpDown, 2, 10),
+            ["c4", "e4", "g4", "c5", "e5", "g5", "e5", "c5", "g4", "e4"]
+        );
+        assert_eq!(arpeggiate(ArpMode::Chord, 1, 2).len(), 6);
+    }
+
+    #[test]
+    fn last_step_is_cut() {
+        let arpeggiator = Arpeggiator::new(ArpMode::Up);
+        let notes = [Note::parse("c4").unwrap()];
+        let pattern = arpeggiator
+            .arpeggiate(&notes, Rational::new(3, 8), &mut Rng::new(0))
+            .unwrap();
+
+        assert_eq!(pattern.events().len(), 2);
+        assert_eq!(pattern.events()[1].duration, Rational::new(1, 8));
+    }
+}
@@ -0,0 +1,446 @@
+//! Swing, groove templates and humanisation: shifting event times and
+//! velocities away from the strict grid.
+//!
+//! Groove templates are stored in a line-based text format:
+//!
+//! ```text
+//! # Comments start with '#'.
+//! step 1/16
+//! 0.0   1.0
+//! 0.16  0.65
+//! ```
+//!
+//! The `step` header sets the grid the template is applied to (as a note
+//! value), and every following line describes one grid step: a timing
+//! offset as a fraction of the step and a velocity multiplier. The template
+//! repeats once its steps run out.
+
+use std::{fmt::Display, fs, path::Path};
+
+use crate::{audio::tempo::TempoMap, random::Rng, rational::Rational};
+
+use super::{Pattern, PatternError, BEATS_PER_WHOLE_NOTE};
+
+/// Number of ticks in a beat, which timing shifts are rounded to, as in
+/// MIDI files.
+pub const TICKS_PER_BEAT: i64 = 960;
+
+/// Default grid of swing and groove templates: a sixteenth note.
+pub const DEFAULT_GROOVE_STEP: Rational = Rational::new(1, 4);
+
+/// Extension of groove template files.
+pub const GROOVE_EXTENSION: &str = "groove";
+
+const BUILTIN_GROOVES: [(&str, &str); 3] = [
+    ("straight", "step 1/16\n0 1\n"),
+    ("mpc_8", "step 1/8\n0 1\n0.16 0.7\n"),
+    ("mpc_16", "step 1/16\n0 1\n0.16 0.55\n0 0.8\n0.16 0.55\n"),
+];
+
+/// Rounds a number of beats to the closest tick.
+fn to_ticks(beats: f64) -> Rational {
+    Rational::new(
+        (beats * TICKS_PER_BEAT as f64).round() as i64,
+        TICKS_PER_BEAT,
+    )
+}
+
+/// Delays every second step of the grid, so that pairs of steps are played
+/// in `amount : 1 - amount` ratio: `swing(pattern, 0.6, ..)` is 60% swing,
+/// and `0.5` leaves the pattern straight. Events off the grid are left
+/// untouched.
+pub fn swing<T>(
+    pattern: Pattern<T>,
+    amount: f64,
+    step: Rational,
+) -> Result<Pattern<T>, PatternError> {
+    if !(amount > 0.0 && amount < 1.0) {
+        return Err(PatternError::InvalidSwing { amount });
+    }
+
+    let length = pattern.length();
+    let events = pattern
+        .into_events()
+        .into_iter()
+        .map(|mut event| {
+            event.start += swing_delay(event.start, amount, step);
+            event
+        })
+        .collect();
+
+    Ok(Pattern::from_events(length, events))
+}
+
+/// Returns how much [`swing`] delays an event starting at `beat`: nothing,
+/// unless it is on every second step of the grid.
+pub fn swing_delay(beat: Rational, amount: f64, step: Rational) -> Rational {
+    match beat.checked_div(step) {
+        Some(position) if position.is_integer() && position.floor() % 2 != 0 => {
+            to_ticks((amount - 0.5) * 2.0 * step.to_f64())
+        }
+        _ => Rational::ZERO,
+    }
+}
+
+/// Timing offset and velocity multiplier of a single groove template step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GrooveStep {
+    /// Offset as a fraction of a step, positive values being late.
+    pub offset: f64,
+    pub velocity: f64,
+}
+
+impl Default for GrooveStep {
+    fn default() -> Self {
+        Self {
+            offset: 0.0,
+            velocity: 1.0,
+        }
+    }
+}
+
+/// Represents a groove template: a repeating list of timing and velocity
+/// deviations from a grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Groove {
+    /// Length of a grid step in beats.
+    pub step: Rational,
+    pub steps: Vec<GrooveStep>,
+}
+
+impl Groove {
+    /// Parses a groove template written in the format described in the
+    /// [module documentation](self).
+    pub fn parse(source: &str) -> Result<Self, GrooveError> {
+        let mut step = None;
+        let mut steps = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let invalid = || GrooveError::InvalidLine { line: index + 1 };
+            let fields: Vec<_> = line.split_whitespace().collect();
+
+            match fields.as_slice() {
+                ["step", value] => step = Some(parse_note_value(value).ok_or_else(invalid)?),
+                [offset, velocity] => steps.push(GrooveStep {
+                    offset: offset.parse().map_err(|_| invalid())?,
+                    velocity: velocity.parse().map_err(|_| invalid())?,
+                }),
+                _ => return Err(invalid()),
+            }
+        }
+
+        if steps.is_empty() {
+            return Err(GrooveError::Empty);
+        }
+
+        Ok(Self {
+            step: step.ok_or(GrooveError::MissingStep)?,
+            steps,
+        })
+    }
+
+    /// Returns one of the templates shipped with Stellar.
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN_GROOVES
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, source)| Self::parse(source).expect("builtin grooves are valid"))
+    }
+
+    /// Returns the name of the file storing a template which isn't a
+    /// builtin one: `<name>.groove`. Names can't contain paths.
+    pub fn file_name(name: &str) -> Result<String, GrooveError> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+            return Err(GrooveError::InvalidName {
+                name: name.to_owned(),
+            });
+        }
+
+        Ok(format!("{name}.{GROOVE_EXTENSION}"))
+    }
+
+    /// Reads the template `name` stored at `path`.
+    pub fn read(name: &str, path: &Path) -> Result<Self, GrooveError> {
+        let source = fs::read_to_string(path).map_err(|_| GrooveError::NotFound {
+            name: name.to_owned(),
+        })?;
+
+        Self::parse(&source)
+    }
+
+    /// Builds a template of `length` steps from onsets of a reference loop,
+    /// given as `(beat, velocity)` pairs. Every onset is assigned to the
+    /// closest grid step, and deviations of onsets sharing the same step of
+    /// the template are averaged.
+    pub fn extract(
+        step: Rational,
+        length: usize,
+        onsets: &[(f64, f64)],
+    ) -> Result<Self, GrooveError> {
+        if step <= Rational::ZERO {
+            return Err(GrooveError::InvalidStep);
+        }
+        if length == 0 {
+            return Err(GrooveError::Empty);
+        }
+
+        let mut sums = vec![(0.0, 0.0, 0usize); length];
+
+        for (beat, velocity) in onsets {
+            let position = beat / step.to_f64();
+            let index = position.round();
+            let slot = &mut sums[(index as i64).rem_euclid(length as i64) as usize];
+
+            slot.0 += position - index;
+            slot.1 += velocity;
+            slot.2 += 1;
+        }
+
+        let steps = sums
+            .into_iter()
+            .map(|(offset, velocity, count)| match count {
+                0 => GrooveStep::default(),
+                _ => GrooveStep {
+                    offset: offset / count as f64,
+                    velocity: velocity / count as f64,
+                },
+            })
+            .collect();
+
+        Ok(Self { step, steps })
+    }
+
+    /// Shifts events lying on the template grid and scales their velocity.
+    pub fn apply<T>(&self, pattern: Pattern<T>) -> Pattern<T> {
+        let length = pattern.length();
+        let events = pattern
+            .into_events()
+            .into_iter()
+            .map(|mut event| {
+                let (shift, velocity) = self.deviation(event.start);
+
+                event.start = (event.start + shift).max(Rational::ZERO);
+                event.velocity = (event.velocity * velocity).clamp(0.0, 1.0);
+                event
+            })
+            .collect();
+
+        Pattern::from_events(length, events)
+    }
+
+    /// Returns the timing shift in beats and the velocity multiplier of an
+    /// event starting at `beat`. Events off the grid are left untouched.
+    pub fn deviation(&self, beat: Rational) -> (Rational, f64) {
+        let Some(position) = beat.checked_div(self.step).filter(Rational::is_integer) else {
+            return (Rational::ZERO, 1.0);
+        };
+
+        let index = position.floor().rem_euclid(self.steps.len() as i64);
+        let step = self.steps[index as usize];
+
+        (to_ticks(step.offset * self.step.to_f64()), step.velocity)
+    }
+}
+
+/// Parses a note value: `1/16`, `1/8`, `1`.
+fn parse_note_value(value: &str) -> Option<Rational> {
+    let (numerator, denominator) = value.split_once('/').unwrap_or((value, "1"));
+    let numerator: i64 = numerator.parse().ok()?;
+    let denominator: i64 = denominator.parse().ok()?;
+
+    if numerator <= 0 || denominator <= 0 {
+        return None;
+    }
+
+    Rational::new(numerator, denominator).checked_mul(BEATS_PER_WHOLE_NOTE)
+}
+
+impl Display for Groove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "step {}", self.step / BEATS_PER_WHOLE_NOTE)?;
+
+        for step in &self.steps {
+            writeln!(f, "{} {}", step.offset, step.velocity)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrooveError {
+    NotFound {
+        name: String,
+    },
+    /// Name of a template file, which contains a path separator or `..`.
+    InvalidName {
+        name: String,
+    },
+    InvalidLine {
+        line: usize,
+    },
+    MissingStep,
+    /// Grid step, which is not positive.
+    InvalidStep,
+    Empty,
+}
+
+/// Randomly shifts event times by up to `timing` seconds and scales
+/// velocities by up to `velocity` in both directions:
+/// `humanize(timing: 8ms, velocity: 0.1)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Humanize {
+    /// Maximum timing shift in seconds.
+    pub timing: f64,
+    /// Maximum relative velocity change.
+    pub velocity: f64,
+}
+
+impl Humanize {
+    /// Humanizes a pattern, which starts at beat `offset` of a piece with
+    /// a given tempo map (needed to convert seconds into beats).
+    pub fn apply<T>(
+        &self,
+        pattern: Pattern<T>,
+        tempo: &TempoMap,
+        offset: Rational,
+        rng: &mut Rng,
+    ) -> Result<Pattern<T>, PatternError> {
+        if self.timing < 0.0 || !(0.0..=1.0).contains(&self.velocity) {
+            return Err(PatternError::InvalidHumanize);
+        }
+
+        let length = pattern.length();
+        let events = pattern
+            .into_events()
+            .into_iter()
+            .map(|mut event| {
+                let seconds = (rng.next_f64() * 2.0 - 1.0) * self.timing;
+                let beats = seconds * tempo.bpm_at(offset + event.start) / 60.0;
+                let velocity = 1.0 + (rng.next_f64() * 2.0 - 1.0) * self.velocity;
+
+                event.start = (event.start + to_ticks(beats)).max(Rational::ZERO);
+                event.velocity = (event.velocity * velocity).clamp(0.0, 1.0);
+                event
+            })
+            .collect();
+
+        Ok(Pattern::from_events(length, events))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        audio::tempo::TempoMap,
+        pattern::{Pattern, PatternError},
+        random::Rng,
+        rational::Rational,
+    };
+
+    use super::{swing, Groove, GrooveError, Humanize, DEFAULT_GROOVE_STEP};
+
+    fn sixteenths(count: usize) -> Pattern<()> {
+        Pattern::steps(vec![(); count], DEFAULT_GROOVE_STEP)
+    }
+
+    #[test]
+    fn swing_delays_off_beats() {
+        let swung = swing(sixteenths(4), 0.6, DEFAULT_GROOVE_STEP).unwrap();
+        let starts: Vec<_> = swung.events().iter().map(|event| event.start).collect();
+
+        // 60% of a pair of sixteenths (half a beat) is 0.3 beats.
+        assert_eq!(
+            starts,
+            [
+                Rational::ZERO,
+                Rational::new(3, 10),
+                Rational::new(1, 2),
+                Rational::new(4, 5)
+            ]
+        );
+
+        assert_eq!(
+            swing(sixteenths(1), 1.0, DEFAULT_GROOVE_STEP),
+            Err(PatternError::InvalidSwing { amount: 1.0 })
+        );
+    }
+
+    #[test]
+    fn groove_templates() {
+        let groove = Groove::builtin("mpc_16").unwrap();
+        assert_eq!(groove.step, DEFAULT_GROOVE_STEP);
+        assert_eq!(Groove::parse(&groove.to_string()), Ok(groove.clone()));
+
+        let grooved = groove.apply(sixteenths(2));
+        // 0.16 of a sixteenth rounded to ticks.
+        assert_eq!(grooved.events()[1].start, Rational::new(240 + 38, 960));
+        assert_eq!(grooved.events()[1].velocity, 0.55);
+
+        assert_eq!(Groove::parse("0 1"), Err(GrooveError::MissingStep));
+        assert_eq!(
+            Groove::parse("step 1/16\n0 loud"),
+            Err(GrooveError::InvalidLine { line: 2 })
+        );
+        assert_eq!(
+            Groove::parse("step 0/16\n0 1"),
+            Err(GrooveError::InvalidLine { line: 1 })
+        );
+
+        assert_eq!(Groove::file_name("mpc.v2"), Ok("mpc.v2.groove".to_owned()));
+        for name in ["../secret", "grooves/mpc", "..", ""] {
+            assert_eq!(
+                Groove::file_name(name),
+                Err(GrooveError::InvalidName {
+                    name: name.to_owned()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn extract_groove() {
+        let onsets = [(0.0, 1.0), (0.3, 0.5), (1.0, 0.9), (1.3, 0.7)];
+        let groove = Groove::extract(Rational::new(1, 2), 2, &onsets).unwrap();
+
+        assert!((groove.steps[0].velocity - 0.95).abs() < 1e-9);
+        assert!((groove.steps[1].offset + 0.4).abs() < 1e-9);
+        assert!((groove.steps[1].velocity - 0.6).abs() < 1e-9);
+
+        assert_eq!(
+            Groove::extract(Rational::ZERO, 2, &onsets),
+            Err(GrooveError::InvalidStep)
+        );
+        assert_eq!(
+            Groove::extract(Rational::new(1, 2), 0, &onsets),
+            Err(GrooveError::Empty)
+        );
+    }
+
+    #[test]
+    fn humanize_is_deterministic() {
+        let tempo = TempoMap::new(120.0).unwrap();
+        let humanize = Humanize {
+            timing: 0.008,
+            velocity: 0.1,
+        };
+
+        let apply = |seed| {
+            humanize
+                .apply(sixteenths(8), &tempo, Rational::ZERO, &mut Rng::new(seed))
+                .unwrap()
+        };
+
+        assert_eq!(apply(7), apply(7));
+        assert_ne!(apply(7), apply(8));
+
+        for event in apply(7).events() {
+            assert!((0.9..=1.0).contains(&event.velocity));
+        }
+    }
+}
@@ -0,0 +1,377 @@
+//! Sequence values and transformations over them.
+//!
+//! A [`Pattern`] is a finite, time-ordered collection of [`Event`]s. All the
+//! transformers here (`fast`, `slow`, `rev`, `rotate`, `palindrome`, `degrade`,
+//! `every`) take a pattern and produce a new one, leaving the original intact,
+//! so they can be freely combined.
+
+pub mod arp;
+pub mod groove;
+pub mod rhythm;
+
+use crate::{random::Rng, rational::Rational};
+
+/// Number of beats in a whole note. Time is measured in beats (quarter
+/// notes), while note values, such as `1/16`, are fractions of a whole note.
+pub const BEATS_PER_WHOLE_NOTE: Rational = Rational::integer(4);
+
+/// Number of beats in a bar. Only 4/4 time signature is supported for now.
+pub const BEATS_PER_BAR: Rational = Rational::integer(4);
+
+/// Maximal number of repetitions or steps a pattern is built of, so that
+/// huge counts are reported instead of exhausting memory.
+pub const MAX_REPEATS: usize = 1 << 16;
+
+/// Represents a single value placed on a timeline (a note, a chord, a
+/// sample trigger, etc.).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event<T> {
+    /// Offset of the event from the beginning of the pattern in beats.
+    pub start: Rational,
+    /// Length of the event in beats.
+    pub duration: Rational,
+    /// Loudness of the event in range `[0, 1]`.
+    pub velocity: f64,
+    pub value: T,
+}
+
+impl<T> Event<T> {
+    pub fn new(start: Rational, duration: Rational, value: T) -> Self {
+        Self {
+            start,
+            duration,
+            velocity: 1.0,
+            value,
+        }
+    }
+
+    /// Returns a copy of the event with a different value.
+    pub fn with_value<U>(self, value: U) -> Event<U> {
+        Event {
+            start: self.start,
+            duration: self.duration,
+            velocity: self.velocity,
+            value,
+        }
+    }
+
+    /// Returns the offset at which the event stops sounding.
+    pub fn end(&self) -> Rational {
+        self.start + self.duration
+    }
+}
+
+/// Represents a finite sequence of events spanning `length` beats.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern<T> {
+    length: Rational,
+    events: Vec<Event<T>>,
+}
+
+impl<T> Pattern<T> {
+    /// Creates an empty pattern of a given length.
+    pub fn silence(length: Rational) -> Self {
+        Self {
+            length,
+            events: Vec::new(),
+        }
+    }
+
+    /// Creates a pattern from a list of events. Events are sorted by their
+    /// start offset.
+    pub fn from_events(length: Rational, mut events: Vec<Event<T>>) -> Self {
+        events.sort_by_key(|event| event.start);
+
+        Self { length, events }
+    }
+
+    /// Creates a pattern, in which every value occupies exactly one step.
+    pub fn steps(values: impl IntoIterator<Item = T>, step: Rational) -> Self {
+        let events: Vec<_> = values
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| Event::new(step * Rational::integer(index as i64), step, value))
+            .collect();
+
+        Self {
+            length: step * Rational::integer(events.len() as i64),
+            events,
+        }
+    }
+
+    /// Returns length of the pattern in beats.
+    pub fn length(&self) -> Rational {
+        self.length
+    }
+
+    /// Returns events of the pattern ordered by their start offset.
+    pub fn events(&self) -> &[Event<T>] {
+        &self.events
+    }
+
+    pub fn into_events(self) -> Vec<Event<T>> {
+        self.events
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Adds an event to the pattern, keeping events ordered.
+    pub fn push(&mut self, event: Event<T>) {
+        let index = self
+            .events
+            .partition_point(|other| other.start <= event.start);
+
+        self.events.insert(index, event);
+    }
+
+    /// Applies a function to every value of the pattern.
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> Pattern<U> {
+        Pattern {
+            length: self.length,
+            events: self
+                .events
+                .into_iter()
+                .map(|event| Event {
+                    start: event.start,
+                    duration: event.duration,
+                    velocity: event.velocity,
+                    value: f(event.value),
+                })
+                .collect(),
+        }
+    }
+
+    /// Speeds the pattern up by a given factor: `fast(2)` plays the
+    /// pattern in half of its original length.
+    pub fn fast(self, factor: Rational) -> Result<Self, PatternError> {
+        if factor <= Rational::ZERO {
+            return Err(PatternError::InvalidFactor { factor });
+        }
+
+        Ok(self.scale(Rational::ONE / factor))
+    }
+
+    /// Slows the pattern down by a given factor: `slow(2)` plays the
+    /// pattern in twice its original length.
+    pub fn slow(self, factor: Rational) -> Result<Self, PatternError> {
+        if factor <= Rational::ZERO {
+            return Err(PatternError::InvalidFactor { factor });
+        }
+
+        Ok(self.scale(factor))
+    }
+
+    /// Multiplies every offset and duration in the pattern by `ratio`.
+    fn scale(self, ratio: Rational) -> Self {
+        Self {
+            length: self.length * ratio,
+            events: self
+                .events
+                .into_iter()
+                .map(|event| Event {
+                    start: event.start * ratio,
+                    duration: event.duration * ratio,
+                    ..event
+                })
+                .collect(),
+        }
+    }
+
+    /// Plays the pattern backwards.
+    pub fn rev(self) -> Self {
+        let length = self.length;
+        let events = self
+            .events
+            .into_iter()
+            .map(|event| Event {
+                start: length - event.end(),
+                ..event
+            })
+            .collect();
+
+        Self::from_events(length, events)
+    }
+
+    /// Shifts the pattern `amount` beats earlier, wrapping events that fall
+    /// off the beginning around to the end. Negative amounts shift the
+    /// pattern later.
+    pub fn rotate(self, amount: Rational) -> Self {
+        if self.length.is_zero() {
+            return self;
+        }
+
+        let length = self.length;
+        let events = self
+            .events
+            .into_iter()
+            .map(|event| Event {
+                start: (event.start - amount).rem_euclid(length),
+                ..event
+            })
+            .collect();
+
+        Self::from_events(length, events)
+    }
+
+    /// Appends `other` pattern after the end of this one.
+    pub fn then(mut self, other: Self) -> Self {
+        let offset = self.length;
+
+        self.events
+            .extend(other.events.into_iter().map(|event| Event {
+                start: event.start + offset,
+                ..event
+            }));
+        self.length += other.length;
+
+        self
+    }
+
+    /// Plays both patterns at the same time. The result is as long as the
+    /// longest of them.
+    pub fn stack(self, other: Self) -> Self {
+        let length = self.length.max(other.length);
+        let mut events = self.events;
+        events.extend(other.events);
+
+        Self::from_events(length, events)
+    }
+
+    /// Removes every event with probability `probability`, using the
+    /// given random number generator.
+    pub fn degrade(self, probability: f64, rng: &mut Rng) -> Result<Self, PatternError> {
+        if !(0.0..=1.0).contains(&probability) {
+            return Err(PatternError::InvalidProbability { probability });
+        }
+
+        let length = self.length;
+        let events = self
+            .events
+            .into_iter()
+            .filter(|_| rng.next_f64() >= probability)
+            .collect();
+
+        Ok(Self { length, events })
+    }
+}
+
+impl<T: Clone> Pattern<T> {
+    /// Repeats the pattern `times` times.
+    pub fn repeat(self, times: usize) -> Self {
+        let mut result = Self::silence(Rational::ZERO);
+
+        for _ in 0..times {
+            result = result.then(self.clone());
+        }
+
+        result
+    }
+
+    /// Plays the pattern followed by its reversed copy.
+    pub fn palindrome(self) -> Self {
+        let reversed = self.clone().rev();
+
+        self.then(reversed)
+    }
+
+    /// Repeats the pattern `n` times, applying `f` to the first repetition
+    /// only, so the transformation is heard once every `n` cycles.
+    pub fn every(
+        self,
+        n: usize,
+        f: impl FnOnce(Self) -> Result<Self, PatternError>,
+    ) -> Result<Self, PatternError> {
+        if n == 0 || n > MAX_REPEATS {
+            return Err(PatternError::InvalidCount { count: n });
+        }
+
+        let first = f(self.clone())?;
+
+        Ok(first.then(self.repeat(n - 1)))
+    }
+}
+
+/// Represents an error caused by invalid arguments of a pattern transformer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternError {
+    InvalidFactor { factor: Rational },
+    InvalidProbability { probability: f64 },
+    InvalidCount { count: usize },
+    InvalidEuclid { hits: usize, steps: usize },
+    MismatchedPoly { patterns: usize, counts: usize },
+    EmptyPattern,
+    InvalidSwing { amount: f64 },
+    InvalidHumanize,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{random::Rng, rational::Rational};
+
+    use super::{Event, Pattern, PatternError};
+
+    fn abcd() -> Pattern<char> {
+        Pattern::steps(['a', 'b', 'c', 'd'], Rational::ONE)
+    }
+
+    fn values(pattern: &Pattern<char>) -> String {
+        pattern.events().iter().map(|event| event.value).collect()
+    }
+
+    #[test]
+    fn fast_and_slow() {
+        let fast = abcd().fast(Rational::integer(2)).unwrap();
+        assert_eq!(fast.length(), Rational::integer(2));
+        assert_eq!(
+            fast.events()[1],
+            Event::new(Rational::new(1, 2), Rational::new(1, 2), 'b')
+        );
+
+        let slow = abcd().slow(Rational::integer(2)).unwrap();
+        assert_eq!(slow.length(), Rational::integer(8));
+        assert_eq!(slow.events()[3].start, Rational::integer(6));
+
+        assert_eq!(
+            abcd().fast(Rational::ZERO),
+            Err(PatternError::InvalidFactor {
+                factor: Rational::ZERO
+            })
+        );
+    }
+
+    #[test]
+    fn rev_rotate_palindrome() {
+        assert_eq!(values(&abcd().rev()), "dcba");
+        assert_eq!(values(&abcd().rotate(Rational::ONE)), "bcda");
+        assert_eq!(values(&abcd().rotate(-Rational::ONE)), "dabc");
+
+        let palindrome = abcd().palindrome();
+        assert_eq!(values(&palindrome), "abcddcba");
+        assert_eq!(palindrome.length(), Rational::integer(8));
+    }
+
+    #[test]
+    fn every() {
+        let pattern = abcd().every(3, |pattern| Ok(pattern.rev())).unwrap();
+
+        assert_eq!(values(&pattern), "dcbaabcdabcd");
+        assert_eq!(pattern.length(), Rational::integer(12));
+
+        assert_eq!(
+            abcd().every(usize::MAX, Ok),
+            Err(PatternError::InvalidCount { count: usize::MAX })
+        );
+    }
+
+    #[test]
+    fn degrade_is_deterministic() {
+        let first = abcd().repeat(8).degrade(0.5, &mut Rng::new(42)).unwrap();
+        let second = abcd().repeat(8).degrade(0.5, &mut Rng::new(42)).unwrap();
+
+        assert_eq!(first, second);
+        assert!(first.events().len() < 32);
+        assert_eq!(first.length(), Rational::integer(32));
+    }
+}
@@ -0,0 +1,172 @@
+//! Generators of rhythmic patterns.
+
+use crate::rational::Rational;
+
+use super::{Event, Pattern, PatternError, MAX_REPEATS};
+
+/// Default length of a single step of generated rhythms: a sixteenth note,
+/// so that 16 steps fill a bar of 4/4.
+pub const DEFAULT_STEP: Rational = Rational::new(1, 4);
+
+/// Default span of a polyrhythm: one bar of 4/4.
+pub const DEFAULT_POLY_SPAN: Rational = Rational::integer(4);
+
+/// Distributes `hits` onsets as evenly as possible over `steps` steps
+/// (Bjorklund's algorithm) and rotates the result `rotation` steps to the
+/// left: `euclid(3, 8, 0)` is `x..x..x.`.
+pub fn euclid(
+    hits: usize,
+    steps: usize,
+    rotation: i64,
+    step: Rational,
+) -> Result<Pattern<()>, PatternError> {
+    if steps == 0 || steps > MAX_REPEATS {
+        return Err(PatternError::InvalidCount { count: steps });
+    }
+
+    if hits > steps {
+        return Err(PatternError::InvalidEuclid { hits, steps });
+    }
+
+    let events = bjorklund(hits, steps)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, hit)| *hit)
+        .map(|(index, _)| Event::new(step * Rational::integer(index as i64), step, ()))
+        .collect();
+
+    let pattern = Pattern::from_events(step * Rational::integer(steps as i64), events);
+
+    let rotation = rotation.rem_euclid(steps as i64);
+
+    Ok(pattern.rotate(step * Rational::integer(rotation)))
+}
+
+fn bjorklund(hits: usize, steps: usize) -> Vec<bool> {
+    if hits == 0 {
+        return vec![false; steps];
+    }
+
+    let mut front = vec![vec![true]; hits];
+    let mut back = vec![vec![false]; steps - hits];
+
+    while back.len() > 1 {
+        let paired = front.len().min(back.len());
+        let remainder = if front.len() > paired {
+            front.split_off(paired)
+        } else {
+            back.split_off(paired)
+        };
+
+        for (group, tail) in front.iter_mut().zip(back) {
+            group.extend(tail);
+        }
+
+        back = remainder;
+    }
+
+    front.into_iter().chain(back).flatten().collect()
+}
+
+/// Fits patterns of different lengths into the same `span`. The `i`-th
+/// pattern is repeated `counts[i]` times before being stretched, so
+/// `poly([kick, snare], &[3, 4], ..)` plays three kicks against four
+/// snares. Empty `counts` means that every pattern is played once.
+pub fn poly<T: Clone>(
+    patterns: Vec<Pattern<T>>,
+    counts: &[usize],
+    span: Rational,
+) -> Result<Pattern<T>, PatternError> {
+    if !counts.is_empty() && counts.len() != patterns.len() {
+        return Err(PatternError::MismatchedPoly {
+            patterns: patterns.len(),
+            counts: counts.len(),
+        });
+    }
+
+    let mut result = Pattern::silence(span);
+
+    for (index, pattern) in patterns.into_iter().enumerate() {
+        let count = counts.get(index).copied().unwrap_or(1);
+        if count == 0 || count > MAX_REPEATS {
+            return Err(PatternError::InvalidCount { count });
+        }
+
+        let repeated = pattern.repeat(count);
+        if repeated.length().is_zero() {
+            return Err(PatternError::EmptyPattern);
+        }
+
+        let ratio = span / repeated.length();
+        result = result.stack(repeated.slow(ratio)?);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        pattern::{Pattern, PatternError},
+        rational::Rational,
+    };
+
+    use super::{euclid, poly, DEFAULT_STEP};
+
+    fn render(pattern: &Pattern<()>, steps: usize) -> String {
+        (0..steps)
+            .map(|index| {
+                let start = DEFAULT_STEP * Rational::integer(index as i64);
+
+                if pattern.events().iter().any(|event| event.start == start) {
+                    'x'
+                } else {
+                    '.'
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn euclidean_rhythms() {
+        assert_eq!(
+            render(&euclid(3, 8, 0, DEFAULT_STEP).unwrap(), 8),
+            "x..x..x."
+        );
+        assert_eq!(
+            render(&euclid(5, 8, 0, DEFAULT_STEP).unwrap(), 8),
+            "x.xx.xx."
+        );
+        assert_eq!(
+            render(&euclid(3, 8, 2, DEFAULT_STEP).unwrap(), 8),
+            ".x..x.x."
+        );
+        assert_eq!(render(&euclid(0, 4, 0, DEFAULT_STEP).unwrap(), 4), "....");
+        assert!(euclid(5, 4, 0, DEFAULT_STEP).is_err());
+        assert_eq!(
+            euclid(1, 100_000_000_000, 0, DEFAULT_STEP),
+            Err(PatternError::InvalidCount {
+                count: 100_000_000_000
+            })
+        );
+    }
+
+    #[test]
+    fn polyrhythm() {
+        let hit = Pattern::steps([()], Rational::ONE);
+        let pattern = poly(vec![hit.clone(), hit], &[3, 4], Rational::integer(4)).unwrap();
+
+        let starts: Vec<_> = pattern.events().iter().map(|event| event.start).collect();
+
+        assert_eq!(pattern.length(), Rational::integer(4));
+        assert_eq!(pattern.events().len(), 7);
+        assert!(starts.contains(&Rational::new(4, 3)));
+        assert!(starts.contains(&Rational::integer(3)));
+
+        let hit = Pattern::steps([()], Rational::ONE);
+        assert_eq!(
+            poly(vec![hit], &[1 << 20], Rational::ONE),
+            Err(PatternError::InvalidCount { count: 1 << 20 })
+        );
+    }
+}
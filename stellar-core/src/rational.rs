@@ -0,0 +1,316 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Debug, Display},
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
+};
+
+/// Maximal denominator of fractions approximating floats, such as float
+/// literals and beats converted from seconds.
+pub const MAX_DENOMINATOR: i64 = 1 << 16;
+
+/// Represents an exact fraction, used for measuring musical time in beats.
+///
+/// The fraction is always stored in its lowest terms with a positive
+/// denominator, so two equal values are also structurally equal.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numerator: i64,
+    denominator: i64,
+}
+
+impl Rational {
+    /// Zero beats.
+    pub const ZERO: Self = Self {
+        numerator: 0,
+        denominator: 1,
+    };
+
+    /// One beat.
+    pub const ONE: Self = Self {
+        numerator: 1,
+        denominator: 1,
+    };
+
+    /// Creates a new fraction and reduces it to its lowest terms.
+    ///
+    /// # Panics
+    /// Panics if `denominator` is zero.
+    pub const fn new(numerator: i64, denominator: i64) -> Self {
+        assert!(denominator != 0, "rational with zero denominator");
+
+        let divisor = match gcd(numerator as i128, denominator as i128) {
+            0 => 1,
+            divisor => divisor as i64,
+        };
+        let sign = denominator.signum();
+
+        Self {
+            numerator: sign * (numerator / divisor),
+            denominator: sign * (denominator / divisor),
+        }
+    }
+
+    /// Creates a whole number.
+    pub const fn integer(value: i64) -> Self {
+        Self {
+            numerator: value,
+            denominator: 1,
+        }
+    }
+
+    /// Approximates a float with a fraction, whose denominator does not
+    /// exceed `max_denominator`.
+    pub fn approximate(value: f64, max_denominator: i64) -> Self {
+        let scaled = (value * max_denominator as f64).round() as i64;
+
+        Self::new(scaled, max_denominator)
+    }
+
+    pub fn numerator(&self) -> i64 {
+        self.numerator
+    }
+
+    pub fn denominator(&self) -> i64 {
+        self.denominator
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numerator == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.numerator < 0
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denominator == 1
+    }
+
+    /// Returns the largest whole number less than or equal to the fraction.
+    pub fn floor(&self) -> i64 {
+        self.numerator.div_euclid(self.denominator)
+    }
+
+    /// Returns the remainder of euclidean division by `modulus`, which is
+    /// always in range `[0, modulus)`.
+    pub fn rem_euclid(&self, modulus: Self) -> Self {
+        let quotient = (*self / modulus).floor();
+
+        *self - modulus * Self::integer(quotient)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// Adds two fractions, returning `None` if the result doesn't fit.
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        Self::reduce(
+            self.numerator as i128 * rhs.denominator as i128
+                + rhs.numerator as i128 * self.denominator as i128,
+            self.denominator as i128 * rhs.denominator as i128,
+        )
+    }
+
+    /// Subtracts two fractions, returning `None` if the result doesn't fit.
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.checked_add(rhs.checked_neg()?)
+    }
+
+    /// Multiplies two fractions, returning `None` if the result doesn't fit.
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        Self::reduce(
+            self.numerator as i128 * rhs.numerator as i128,
+            self.denominator as i128 * rhs.denominator as i128,
+        )
+    }
+
+    /// Divides two fractions, returning `None` if `rhs` is zero or the
+    /// result doesn't fit.
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        Self::reduce(
+            self.numerator as i128 * rhs.denominator as i128,
+            self.denominator as i128 * rhs.numerator as i128,
+        )
+    }
+
+    pub fn checked_neg(self) -> Option<Self> {
+        Some(Self {
+            numerator: self.numerator.checked_neg()?,
+            denominator: self.denominator,
+        })
+    }
+
+    /// Reduces a fraction computed with wider integers to its lowest terms.
+    fn reduce(numerator: i128, denominator: i128) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+
+        let divisor = match gcd(numerator, denominator) {
+            0 => 1,
+            divisor => divisor,
+        };
+        let sign = denominator.signum();
+
+        Some(Self {
+            numerator: i64::try_from(sign * numerator / divisor).ok()?,
+            denominator: i64::try_from(sign * denominator / divisor).ok()?,
+        })
+    }
+}
+
+const fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a as i128
+}
+
+impl Default for Rational {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(value: i64) -> Self {
+        Self::integer(value)
+    }
+}
+
+impl Add for Rational {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        self.checked_add(rhs).expect("rational overflow")
+    }
+}
+
+impl AddAssign for Rational {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Rational {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self.checked_sub(rhs).expect("rational overflow")
+    }
+}
+
+impl SubAssign for Rational {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul for Rational {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.checked_mul(rhs).expect("rational overflow")
+    }
+}
+
+impl Div for Rational {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        assert!(!rhs.is_zero(), "rational with zero denominator");
+
+        self.checked_div(rhs).expect("rational overflow")
+    }
+}
+
+impl Neg for Rational {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self.checked_neg().expect("rational overflow")
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.numerator as i128 * other.denominator as i128)
+            .cmp(&(other.numerator as i128 * self.denominator as i128))
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+impl Debug for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Rational({self})")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rational;
+
+    #[test]
+    fn normalize() {
+        assert_eq!(Rational::new(2, 4), Rational::new(1, 2));
+        assert_eq!(Rational::new(1, -2), Rational::new(-1, 2));
+        assert_eq!(Rational::new(0, 5), Rational::ZERO);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let half = Rational::new(1, 2);
+        let third = Rational::new(1, 3);
+
+        assert_eq!(half + third, Rational::new(5, 6));
+        assert_eq!(half - third, Rational::new(1, 6));
+        assert_eq!(half * third, Rational::new(1, 6));
+        assert_eq!(half / third, Rational::new(3, 2));
+        assert!(third < half);
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        let tiny = Rational::new(1, 1 << 32);
+        let max = Rational::integer(i64::MAX);
+
+        assert_eq!(tiny.checked_mul(tiny), None);
+        assert_eq!(max.checked_add(Rational::ONE), None);
+        assert_eq!(max.checked_sub(-Rational::ONE), None);
+        assert_eq!(Rational::ONE.checked_div(Rational::ZERO), None);
+        assert_eq!(Rational::integer(i64::MIN).checked_neg(), None);
+
+        // Intermediate products may overflow, as long as the result fits.
+        assert_eq!(
+            Rational::new(i64::MAX, 3).checked_mul(Rational::new(3, i64::MAX)),
+            Some(Rational::ONE)
+        );
+    }
+
+    #[test]
+    fn rem_euclid() {
+        let four = Rational::integer(4);
+
+        assert_eq!(Rational::new(9, 2).rem_euclid(four), Rational::new(1, 2));
+        assert_eq!(Rational::new(-1, 2).rem_euclid(four), Rational::new(7, 2));
+    }
+}
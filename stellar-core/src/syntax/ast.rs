@@ -1,6 +1,10 @@
 use crate::syntax::location::Span;
 
-use super::{location::Spanned, string_id::StringId, token::Identifier};
+use super::{
+    location::Spanned,
+    string_id::StringId,
+    token::{Identifier, Unit},
+};
 
 #[derive(Debug, PartialEq)]
 pub struct Property {
@@ -14,13 +18,69 @@ impl Spanned for Property {
     }
 }
 
+/// Represents an argument of a call expression, which may be named:
+/// `fast(2, drums)` or `humanize(timing: 8)`.
+#[derive(Debug, PartialEq)]
+pub struct Argument {
+    pub name: Option<Identifier>,
+    pub value: Expression,
+}
+
+impl Spanned for Argument {
+    fn span(&self) -> Span {
+        match &self.name {
+            Some(name) => Span::new(name.span().start(), self.value.span().end()),
+            None => self.value.span(),
+        }
+    }
+}
+
+/// Attribute attached to a statement: `#[allow(unused)]`.
+#[derive(Debug, PartialEq)]
+pub struct Attribute {
+    pub name: Identifier,
+    pub arguments: Vec<Identifier>,
+    pub span: Span,
+}
+
+impl Spanned for Attribute {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// Unit of a length written in the source code.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TimeUnit {
+    Beats,
+    Bars,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RampCurve {
+    Linear,
+    Exponential,
+}
+
+/// Gradual part of a tempo change: `-> 90 over 8 bars [exponential]`.
+#[derive(Debug, PartialEq)]
+pub struct TempoRamp {
+    pub target: Expression,
+    pub length: Expression,
+    pub unit: TimeUnit,
+    pub curve: RampCurve,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Statement {
     Wait {
         expression: Expression,
     },
+    /// Plays a value: `play c4`. Sequences are played to the end before
+    /// the next statement, unless started on a new channel with `play!`.
     Play {
         expression: Expression,
+        parallel: bool,
     },
     Sequence {
         name: Identifier,
@@ -34,9 +94,116 @@ pub enum Statement {
         name: Identifier,
         value: Expression,
     },
+    /// Plays patterns of the block simultaneously, fitting them into the
+    /// same span: `poly(3, 4) { play kick play snare }`.
+    Poly {
+        arguments: Vec<Argument>,
+        block: Block,
+    },
+    /// Changes tempo, immediately (`tempo 120`) or gradually
+    /// (`tempo 120 -> 90 over 8 bars`).
+    Tempo {
+        bpm: Expression,
+        ramp: Option<TempoRamp>,
+    },
+    /// Changes the tuning notes are played in: `tuning edo(19)`.
+    Tuning {
+        tuning: Expression,
+    },
+    /// Plays the block `count` times, or forever if there is no count:
+    /// `repeat 4 { ... }`, `repeat { ... }`.
+    Repeat {
+        count: Option<Expression>,
+        block: Block,
+    },
+    /// Statement preceded by attributes.
+    Attributed {
+        attributes: Vec<Attribute>,
+        statement: Box<Statement>,
+    },
     Expression(Expression),
 }
 
+impl Statement {
+    /// Returns the statement without its attributes.
+    pub fn unattributed(&self) -> &Statement {
+        match self {
+            Self::Attributed { statement, .. } => statement.unattributed(),
+            _ => self,
+        }
+    }
+
+    /// Calls `visit` with every statement, including the ones nested in
+    /// blocks, without their attributes. Properties of `with` blocks
+    /// enclosing a statement are passed along with it, outermost first.
+    pub fn walk<'a>(
+        statements: &'a [Statement],
+        visit: &mut impl FnMut(&'a Statement, &[&'a [Property]]),
+    ) {
+        fn walk_nested<'a>(
+            statements: &'a [Statement],
+            enclosing: &mut Vec<&'a [Property]>,
+            visit: &mut impl FnMut(&'a Statement, &[&'a [Property]]),
+        ) {
+            for statement in statements {
+                let statement = statement.unattributed();
+                visit(statement, enclosing);
+
+                match statement {
+                    Statement::With { properties, block } => {
+                        enclosing.push(properties);
+                        walk_nested(&block.statements, enclosing, visit);
+                        enclosing.pop();
+                    }
+                    Statement::Sequence { block, .. }
+                    | Statement::Poly { block, .. }
+                    | Statement::Repeat { block, .. } => {
+                        walk_nested(&block.statements, enclosing, visit)
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        walk_nested(statements, &mut Vec::new(), visit);
+    }
+}
+
+impl Spanned for Statement {
+    /// Returns the span of the statement contents. Leading keywords are not
+    /// stored in the tree, so `wait 1` is spanned by `1`.
+    fn span(&self) -> Span {
+        match self {
+            Self::Wait { expression }
+            | Self::Play { expression, .. }
+            | Self::Expression(expression) => expression.span(),
+            Self::Let { name, value } => Span::new(name.span().start(), value.span().end()),
+            Self::Sequence { name, block } => Span::new(name.span().start(), block.span.end()),
+            Self::With { properties, block } => match properties.first() {
+                Some(property) => Span::new(property.span().start(), block.span.end()),
+                None => block.span,
+            },
+            Self::Poly { arguments, block } => match arguments.first() {
+                Some(argument) => Span::new(argument.span().start(), block.span.end()),
+                None => block.span,
+            },
+            Self::Repeat { count, block } => match count {
+                Some(count) => Span::new(count.span().start(), block.span.end()),
+                None => block.span,
+            },
+            Self::Tempo { bpm, ramp } => match ramp {
+                Some(ramp) => Span::new(bpm.span().start(), ramp.length.span().end()),
+                None => bpm.span(),
+            },
+            Self::Tuning { tuning } => tuning.span(),
+            Self::Attributed {
+                attributes,
+                statement,
+            } => Span::new(attributes[0].span.start(), statement.span().end()),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Expression {
     Float {
@@ -47,6 +214,12 @@ pub enum Expression {
         value: i64,
         span: Span,
     },
+    /// Number with a unit: `60%`, `8ms`.
+    Quantity {
+        value: f64,
+        unit: Unit,
+        span: Span,
+    },
     String {
         value: StringId,
         span: Span,
@@ -73,6 +246,22 @@ pub enum Expression {
         sample: Box<Expression>,
         span: Span,
     },
+    Call {
+        callee: Box<Expression>,
+        arguments: Vec<Argument>,
+        span: Span,
+    },
+    /// Degree of a scale or element of a list: `s[5]`, `chords[0]`.
+    Index {
+        target: Box<Expression>,
+        index: Box<Expression>,
+        span: Span,
+    },
+    /// Scale degree of the current key: `^1`, `^5`.
+    Degree {
+        degree: i64,
+        span: Span,
+    },
 }
 
 impl Spanned for Expression {
@@ -87,15 +276,20 @@ impl Spanned for Expression {
             | Self::Float { span, .. }
             | Self::String { span, .. }
             | Self::Integer { span, .. }
-            | Self::LoadSample { span, .. } => *span,
+            | Self::Quantity { span, .. }
+            | Self::LoadSample { span, .. }
+            | Self::Call { span, .. }
+            | Self::Index { span, .. }
+            | Self::Degree { span, .. } => *span,
             Self::Identifier(identifier) => identifier.span(),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PrefixOperatorKind {
     Exclamation,
+    Minus,
 }
 
 #[derive(Debug, PartialEq)]
@@ -110,7 +304,7 @@ impl Spanned for PrefixOperator {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BinaryOperatorKind {
     Plus,
     Minus,
@@ -29,6 +29,11 @@ impl<'a> Cursor<'a> {
         self.input.peek().copied()
     }
 
+    /// Returns the character after the next one without consuming anything.
+    pub fn peek_second(&self) -> Option<char> {
+        self.input.clone().nth(1)
+    }
+
     pub fn next(&mut self) -> Option<char> {
         if let Some(&next_char) = self.input.peek() {
             self.location = if next_char == '\n' {
@@ -53,7 +53,12 @@ impl Span {
 
     /// Returns length of the span in bytes.
     pub fn len(&self) -> u32 {
-        self.start.index - self.end.index
+        self.end.index - self.start.index
+    }
+
+    /// Returns `true` if the span covers no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
     /// Returns location of the first byte in the span.
@@ -65,6 +70,11 @@ impl Span {
     pub fn end(&self) -> Location {
         self.end
     }
+
+    /// Returns `true` if the span covers every byte of another one.
+    pub fn contains(&self, other: Span) -> bool {
+        self.start.index <= other.start.index && other.end.index <= self.end.index
+    }
 }
 
 /// Represents any object localized in a specific byte span 
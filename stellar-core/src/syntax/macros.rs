@@ -1,30 +1,32 @@
 // All heavy looking macros moved here.
 
+#[cfg(test)]
+use std::fmt::Debug;
+
+#[cfg(test)]
+use super::location::Spanned;
+
+/// Generates a snapshot test for every named source, which snapshots what
+/// the `render` function of the calling module returns for the source.
 #[cfg(test)]
 #[macro_export]
-macro_rules! test_parse {
+macro_rules! test_snapshots {
     ($(($name:ident, $source:expr)),* $(,)?) => {
         $(
             #[test]
             fn $name() {
-                let token_stream = scan($source).expect("Scanning failed");
-                assert_debug_snapshot!(parse(token_stream));
+                insta::assert_snapshot!(render($source));
             }
         )*
     };
 }
 
+/// Renders an error with the line and column it starts at: `2:7: ...`.
 #[cfg(test)]
-#[macro_export]
-macro_rules! test_scan {
-    ($(($name:ident, $source:expr)),* $(,)?) => {
-        $(
-            #[test]
-            fn $name() {
-                assert_debug_snapshot!(scan($source));
-            }
-        )*
-    };
+pub(crate) fn render_error(error: &(impl Spanned + Debug)) -> String {
+    let start = error.span().start();
+
+    format!("{}:{}: {:?}", start.line(), start.column(), error)
 }
 
 #[macro_export]
@@ -1,5 +1,5 @@
 mod cursor;
-mod macros;
+pub(crate) mod macros;
 
 pub mod ast;
 pub mod location;
@@ -1,14 +1,22 @@
 use crate::syntax::{
     ast::{Block, Expression, Statement},
-    location::{Span, Spanned},
+    location::{Location, Span, Spanned},
     token::{Keyword, Punctuator, Token, TokenStream, TokenStreamCursor},
 };
 
 use super::{
-    ast::{BinaryOperator, PrefixOperator, Property},
+    ast::{
+        Argument, Attribute, BinaryOperator, PrefixOperator, Property, RampCurve, TempoRamp,
+        TimeUnit,
+    },
     token::{Identifier, Operator},
 };
 
+/// Number of expressions and blocks, which may be nested inside each other.
+/// Every later pass walks the syntax tree recursively, so deeper programs
+/// are rejected before they could overflow the stack.
+pub const MAX_NESTING: usize = 128;
+
 /// Processes a given token stream and converts into an Abstract Syntax Tree.
 pub fn parse(stream: TokenStream) -> Result<Vec<Statement>, ParseError> {
     let Some(mut cursor) = stream.into_cursor() else {
@@ -30,6 +38,9 @@ pub fn parse(stream: TokenStream) -> Result<Vec<Statement>, ParseError> {
 }
 
 fn parse_block(cursor: &mut TokenStreamCursor) -> Result<Block, ParseError> {
+    let depth = cursor.depth();
+    enter(cursor)?;
+
     let start = parse_punctuator(cursor, Punctuator::LeftBrace)?
         .span()
         .start(); // '{'
@@ -46,6 +57,7 @@ fn parse_block(cursor: &mut TokenStreamCursor) -> Result<Block, ParseError> {
     }
 
     let end = cursor.next().span().end(); // '}'
+    cursor.set_depth(depth);
 
     Ok(Block {
         statements,
@@ -58,8 +70,22 @@ fn parse_statement(cursor: &mut TokenStreamCursor) -> Result<Statement, ParseErr
         token if token.is_keyword(Keyword::Play) => {
             cursor.next(); // 'play' keyword
 
+            // 'play!' - the exclamation mark must follow the keyword
+            // immediately, otherwise it is a prefix operator.
+            let parallel = match cursor.peek() {
+                Token::Operator {
+                    operator: Operator::Exclamation,
+                    span,
+                } if span.start() == token.span().end() => {
+                    cursor.next();
+                    true
+                }
+                _ => false,
+            };
+
             Ok(Statement::Play {
                 expression: parse_expression(cursor)?,
+                parallel,
             })
         }
         token if token.is_keyword(Keyword::Wait) => {
@@ -72,7 +98,18 @@ fn parse_statement(cursor: &mut TokenStreamCursor) -> Result<Statement, ParseErr
         token if token.is_keyword(Keyword::Sequence) => parse_sequence_statement(cursor),
         token if token.is_keyword(Keyword::With) => parse_with_statement(cursor),
         token if token.is_keyword(Keyword::Let) => parse_let_statement(cursor),
-        _ => parse_expression(cursor).map(|e| Statement::Expression(e)),
+        token if token.is_keyword(Keyword::Poly) => parse_poly_statement(cursor),
+        token if token.is_keyword(Keyword::Tempo) => parse_tempo_statement(cursor),
+        token if token.is_keyword(Keyword::Tuning) => {
+            cursor.next(); // 'tuning' keyword
+
+            Ok(Statement::Tuning {
+                tuning: parse_expression(cursor)?,
+            })
+        }
+        token if token.is_keyword(Keyword::Repeat) => parse_repeat_statement(cursor),
+        token if token.is_punctuator(Punctuator::Hash) => parse_attributed_statement(cursor),
+        _ => parse_expression(cursor).map(Statement::Expression),
     }
 }
 
@@ -84,6 +121,7 @@ fn parse_expression_with_precedence(
     cursor: &mut TokenStreamCursor,
     precedence: usize,
 ) -> Result<Expression, ParseError> {
+    let depth = cursor.depth();
     let mut left = parse_prefix_expression(cursor)?;
 
     while let Token::Operator {
@@ -92,7 +130,7 @@ fn parse_expression_with_precedence(
     } = cursor.peek()
     {
         let Some(binary_operator_kind) = operator.into_binary_operator_kind() else {
-            continue;
+            break;
         };
 
         let binary_operator_precedence = binary_operator_kind.precedence();
@@ -104,6 +142,8 @@ fn parse_expression_with_precedence(
 
         skip_end_of_lines(cursor);
 
+        // The left operand is nested one level deeper with every operator.
+        enter(cursor)?;
         let right = parse_expression_with_precedence(cursor, binary_operator_precedence + 1)?;
         left = Expression::Binary {
             left: Box::new(left),
@@ -115,17 +155,149 @@ fn parse_expression_with_precedence(
         };
     }
 
+    cursor.set_depth(depth);
+
     Ok(left)
 }
 
 /// Parses a left hand side of the potential binary expression.
 fn parse_prefix_expression(cursor: &mut TokenStreamCursor) -> Result<Expression, ParseError> {
+    let depth = cursor.depth();
+    enter(cursor)?;
+
+    let mut expression = parse_primary_expression(cursor)?;
+
+    loop {
+        let token = cursor.peek();
+
+        if token.is_punctuator(Punctuator::LeftParen) {
+            enter(cursor)?;
+            expression = parse_call_expression(cursor, expression)?;
+        } else if token.is_punctuator(Punctuator::LeftBracket) {
+            enter(cursor)?;
+            expression = parse_index_expression(cursor, expression)?;
+        } else {
+            break;
+        }
+    }
+
+    cursor.set_depth(depth);
+
+    Ok(expression)
+}
+
+/// Parses an index expression: `<target> '[' <index> ']'`.
+fn parse_index_expression(
+    cursor: &mut TokenStreamCursor,
+    target: Expression,
+) -> Result<Expression, ParseError> {
+    parse_punctuator(cursor, Punctuator::LeftBracket)?; // '['
+
+    let index = parse_expression(cursor)?;
+    let end = parse_punctuator(cursor, Punctuator::RightBracket)?
+        .span()
+        .end(); // ']'
+
+    Ok(Expression::Index {
+        span: Span::new(target.span().start(), end),
+        target: Box::new(target),
+        index: Box::new(index),
+    })
+}
+
+/// Parses a call expression: `<callee> '(' <arguments> ')'`.
+fn parse_call_expression(
+    cursor: &mut TokenStreamCursor,
+    callee: Expression,
+) -> Result<Expression, ParseError> {
+    let (arguments, end) = parse_arguments(cursor)?;
+
+    Ok(Expression::Call {
+        span: Span::new(callee.span().start(), end),
+        callee: Box::new(callee),
+        arguments,
+    })
+}
+
+/// Parses a parenthesized list of arguments and returns it together with
+/// the location of the closing parenthesis.
+fn parse_arguments(
+    cursor: &mut TokenStreamCursor,
+) -> Result<(Vec<Argument>, Location), ParseError> {
+    parse_punctuator(cursor, Punctuator::LeftParen)?; // '('
+
+    // <argument> ::= [<name> ':'] <value>
+    fn parse_argument(cursor: &mut TokenStreamCursor) -> Result<Argument, ParseError> {
+        let value = parse_expression(cursor)?;
+
+        match value {
+            Expression::Identifier(name) if cursor.peek().is_punctuator(Punctuator::Colon) => {
+                cursor.next(); // ':'
+
+                skip_end_of_lines(cursor);
+
+                Ok(Argument {
+                    name: Some(name),
+                    value: parse_expression(cursor)?,
+                })
+            }
+            value => Ok(Argument { name: None, value }),
+        }
+    }
+
+    let mut arguments = Vec::new();
+
+    skip_end_of_lines(cursor);
+
+    if !cursor.peek().is_punctuator(Punctuator::RightParen) {
+        arguments.push(parse_argument(cursor)?);
+
+        skip_end_of_lines(cursor);
+
+        while cursor.peek().is_punctuator(Punctuator::Comma) {
+            cursor.next();
+
+            skip_end_of_lines(cursor);
+
+            if cursor.peek().is_punctuator(Punctuator::RightParen) {
+                break; // f(a, b,) - still counts
+            }
+
+            arguments.push(parse_argument(cursor)?);
+
+            skip_end_of_lines(cursor);
+        }
+    }
+
+    let end = parse_punctuator(cursor, Punctuator::RightParen)?
+        .span()
+        .end(); // ')'
+
+    Ok((arguments, end))
+}
+
+/// Parses an expression, which can't be split into smaller ones
+/// by operators or calls.
+fn parse_primary_expression(cursor: &mut TokenStreamCursor) -> Result<Expression, ParseError> {
     match cursor.next() {
         // Literals.
         Token::Integer { value, span } => Ok(Expression::Integer { value, span }),
         Token::Float { value, span } => Ok(Expression::Float { value, span }),
+        Token::Quantity { value, unit, span } => Ok(Expression::Quantity { value, unit, span }),
         Token::String { value, span } => Ok(Expression::String { value, span }),
+        Token::Bool { value, span } => Ok(Expression::Bool { value, span }),
         Token::Identifier(identifier) => Ok(Expression::Identifier(identifier)),
+        // Scale degree.
+        token if token.is_punctuator(Punctuator::Caret) => match cursor.next() {
+            Token::Integer { value, span } => Ok(Expression::Degree {
+                degree: value,
+                span: Span::new(token.span().start(), span.end()),
+            }),
+            got => Err(ParseError::UnexpectedToken {
+                got,
+                expected: ExpectedItem::Degree,
+            }),
+        },
         // Parenthesized expression.
         token if token.is_punctuator(Punctuator::LeftParen) => {
             let expression = parse_expression(cursor)?;
@@ -224,6 +396,124 @@ fn parse_let_statement(cursor: &mut TokenStreamCursor) -> Result<Statement, Pars
     Ok(Statement::Let { name, value })
 }
 
+fn parse_poly_statement(cursor: &mut TokenStreamCursor) -> Result<Statement, ParseError> {
+    cursor.next(); // 'poly' keyword
+
+    // poly { ... } - no step counts
+    let arguments = if cursor.peek().is_punctuator(Punctuator::LeftParen) {
+        parse_arguments(cursor)?.0
+    } else {
+        Vec::new()
+    };
+
+    let block = parse_block(cursor)?;
+
+    Ok(Statement::Poly { arguments, block })
+}
+
+fn parse_repeat_statement(cursor: &mut TokenStreamCursor) -> Result<Statement, ParseError> {
+    cursor.next(); // 'repeat' keyword
+
+    // repeat { ... } - repeats forever
+    let count = if cursor.peek().is_punctuator(Punctuator::LeftBrace) {
+        None
+    } else {
+        Some(parse_expression(cursor)?)
+    };
+
+    let block = parse_block(cursor)?;
+
+    Ok(Statement::Repeat { count, block })
+}
+
+fn parse_attributed_statement(cursor: &mut TokenStreamCursor) -> Result<Statement, ParseError> {
+    let mut attributes = Vec::new();
+
+    while cursor.peek().is_punctuator(Punctuator::Hash) {
+        attributes.push(parse_attribute(cursor)?);
+
+        skip_end_of_lines(cursor);
+    }
+
+    Ok(Statement::Attributed {
+        attributes,
+        statement: Box::new(parse_statement(cursor)?),
+    })
+}
+
+// <attribute> ::= '#' '[' <name> ['(' <name> (',' <name>)* ')'] ']'
+fn parse_attribute(cursor: &mut TokenStreamCursor) -> Result<Attribute, ParseError> {
+    let start = cursor.next().span().start(); // '#'
+
+    parse_punctuator(cursor, Punctuator::LeftBracket)?; // '['
+
+    let name = parse_identifier(cursor)?;
+    let mut arguments = Vec::new();
+
+    if cursor.peek().is_punctuator(Punctuator::LeftParen) {
+        cursor.next(); // '('
+
+        while !cursor.peek().is_punctuator(Punctuator::RightParen) {
+            arguments.push(parse_identifier(cursor)?);
+
+            if !cursor.peek().is_punctuator(Punctuator::Comma) {
+                break;
+            }
+
+            cursor.next(); // ','
+        }
+
+        parse_punctuator(cursor, Punctuator::RightParen)?; // ')'
+    }
+
+    let end = parse_punctuator(cursor, Punctuator::RightBracket)?
+        .span()
+        .end(); // ']'
+
+    Ok(Attribute {
+        name,
+        arguments,
+        span: Span::new(start, end),
+    })
+}
+
+fn parse_tempo_statement(cursor: &mut TokenStreamCursor) -> Result<Statement, ParseError> {
+    cursor.next(); // 'tempo' keyword
+
+    let bpm = parse_expression(cursor)?;
+
+    if !cursor.peek().is_operator(Operator::Arrow) {
+        return Ok(Statement::Tempo { bpm, ramp: None });
+    }
+
+    cursor.next(); // '->'
+
+    // <ramp> ::= '->' <target> 'over' <length> [<unit>] [<curve>]
+    let target = parse_expression(cursor)?;
+    parse_keyword(cursor, Keyword::Over)?;
+    let length = parse_expression(cursor)?;
+
+    let unit = match parse_name_in(cursor, &["beat", "beats", "bar", "bars"]) {
+        Some("bar" | "bars") => TimeUnit::Bars,
+        _ => TimeUnit::Beats,
+    };
+
+    let curve = match parse_name_in(cursor, &["linear", "exponential"]) {
+        Some("exponential") => RampCurve::Exponential,
+        _ => RampCurve::Linear,
+    };
+
+    Ok(Statement::Tempo {
+        bpm,
+        ramp: Some(TempoRamp {
+            target,
+            length,
+            unit,
+            curve,
+        }),
+    })
+}
+
 fn parse_with_statement(cursor: &mut TokenStreamCursor) -> Result<Statement, ParseError> {
     cursor.next(); // 'with' keyword
 
@@ -272,6 +562,40 @@ fn parse_identifier(cursor: &mut TokenStreamCursor) -> Result<Identifier, ParseE
     Ok(identifier)
 }
 
+/// Checks if the next token in cursor is an identifier with one of the
+/// given names, used for contextual keywords:
+/// - If it is, consumes it and returns the name.
+/// - If it is not, returns `None` without moving the cursor.
+fn parse_name_in(cursor: &mut TokenStreamCursor, names: &[&'static str]) -> Option<&'static str> {
+    let Token::Identifier(identifier) = cursor.peek() else {
+        return None;
+    };
+
+    let name = identifier.name().resolve();
+    let found = names.iter().find(|candidate| **candidate == name)?;
+
+    cursor.next();
+
+    Some(found)
+}
+
+/// Checks if the next token in cursor is a keyword given in a function argument `keyword`:
+/// - If it is, returns a [`Token`] object.
+/// - If it is not, returns a [`ParseError`].
+///
+/// In both cases, cursor is moved to the next token.
+fn parse_keyword(cursor: &mut TokenStreamCursor, keyword: Keyword) -> Result<Token, ParseError> {
+    let got = cursor.next();
+    if !got.is_keyword(keyword) {
+        return Err(ParseError::UnexpectedToken {
+            expected: ExpectedItem::Keyword(keyword),
+            got,
+        });
+    }
+
+    Ok(got)
+}
+
 /// Checks if the next token in cursor is a punctuator given in a function argument `punctuator`:
 /// - If it is, returns a [`Token`] object.
 /// - If it is not, returns a [`ParseError`].
@@ -310,6 +634,18 @@ fn parse_operator(cursor: &mut TokenStreamCursor, operator: Operator) -> Result<
 }
 
 /// Moves cursor to the next non-EOL token.
+/// Counts one more level of expressions and blocks nested inside each
+/// other. Callers restore the depth once they're parsed.
+fn enter(cursor: &mut TokenStreamCursor) -> Result<(), ParseError> {
+    if cursor.depth() >= MAX_NESTING {
+        return Err(ParseError::NestedTooDeep { got: cursor.peek() });
+    }
+
+    cursor.set_depth(cursor.depth() + 1);
+
+    Ok(())
+}
+
 fn skip_end_of_lines(cursor: &mut TokenStreamCursor) {
     while cursor.peek().is_end_of_line() {
         cursor.next();
@@ -319,7 +655,9 @@ fn skip_end_of_lines(cursor: &mut TokenStreamCursor) {
 #[derive(Debug)]
 pub enum ExpectedItem {
     Expression,
+    Degree,
     Identifier,
+    Keyword(Keyword),
     Punctuator(Punctuator),
     Operator(Operator),
 }
@@ -327,25 +665,80 @@ pub enum ExpectedItem {
 #[derive(Debug)]
 pub enum ParseError {
     InvalidTokenStream,
-    UnexpectedToken { got: Token, expected: ExpectedItem },
+    UnexpectedToken {
+        got: Token,
+        expected: ExpectedItem,
+    },
+    /// Expressions or blocks nested inside each other deeper than
+    /// [`MAX_NESTING`].
+    NestedTooDeep {
+        got: Token,
+    },
 }
 
 #[cfg(test)]
 mod tests {
-    use insta::assert_debug_snapshot;
+    use crate::{syntax::scan::scan, test_snapshots};
+
+    use super::{parse, ParseError, MAX_NESTING};
+
+    #[test]
+    fn nesting() {
+        let nested = |depth: usize| parse(scan(&format!("play {}", "[".repeat(depth))).unwrap());
+
+        assert!(matches!(
+            nested(3000),
+            Err(ParseError::NestedTooDeep { .. })
+        ));
+        assert!(matches!(
+            nested(MAX_NESTING),
+            Err(ParseError::NestedTooDeep { .. })
+        ));
+        assert!(matches!(
+            nested(MAX_NESTING - 1),
+            Err(ParseError::UnexpectedToken { .. })
+        ));
+    }
 
-    use crate::{syntax::scan::scan, test_parse};
+    fn render(source: &str) -> String {
+        let token_stream = scan(source).expect("Scanning failed");
 
-    use super::parse;
+        format!("{:#?}", parse(token_stream))
+    }
 
-    test_parse!(
+    test_snapshots!(
         (empty, ""),
         (with, "with a: 3, b: 4, {}"),
         (sequence, "sequence test {}"),
         (binary_expr, "a + \n 2 * (3 + b) - 3\n\"hello\" + \"world\""),
+        (non_binary_operator, "a == b"),
         (play_and_wait, "play c4 wait 1"),
         (list, "[1, 2]\n[1, \n2]\n[\n1, \n2]\n[1,\n2,]"),
         (let_stmt, "let a = 3 + 2"),
-        (load_sample, "let kick = load_sample \"test.mp3\"")
+        (load_sample, "let kick = load_sample \"test.mp3\""),
+        (
+            call,
+            "every(3, rev, fast(2, drums))\ndegrade(p: 0.5,\n seq,)"
+        ),
+        (poly, "poly(3, 4) {\n play kick\n play snare\n}\npoly {}"),
+        (
+            tempo,
+            "tempo 120\ntempo 120 -> 90 over 8 bars\ntempo 60 -> 180 over 16 exponential"
+        ),
+        (tuning, "tuning edo(19)\ntuning scala(\"meantone.scl\")"),
+        (
+            degree,
+            "with key: d_minor, transpose: -5 {\n play [^1, ^3, ^5]\n}"
+        ),
+        (index, "play s[1 + 2]\nplay scale(c4, \"dorian\")[-1]"),
+        (bool, "let loop = true\nlet muted = false"),
+        (
+            repeat,
+            "repeat 4 {\n play! drums\n play !muted\n}\nrepeat {}"
+        ),
+        (
+            attribute,
+            "# comment\n#[allow(unused, shadowed)]\n#[deny(zero_wait)]\nlet a = 3"
+        )
     );
 }
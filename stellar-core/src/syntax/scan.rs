@@ -4,7 +4,7 @@ use crate::{
         cursor::Cursor,
         location::{Span, Spanned},
         string_id::StringId,
-        token::{Keyword, Operator, Punctuator, Token, TokenStream},
+        token::{Keyword, Operator, Punctuator, Token, TokenStream, Unit},
     },
 };
 
@@ -42,8 +42,9 @@ fn scan_next_token(cursor: &mut Cursor) -> Result<Token, ScanError> {
             c if c.is_whitespace() && c != '\n' => {
                 cursor.next();
             }
-            // Skip single-line comments starting with '#'.
-            '#' => {
+            // Skip single-line comments starting with '#', but not
+            // attributes: '#[allow(unused)]'.
+            '#' if cursor.peek_second() != Some('[') => {
                 while let Some(c) = cursor.next() {
                     if c == '\n' {
                         break; // Stop skipping at the end of the line.
@@ -71,7 +72,7 @@ fn scan_next_token(cursor: &mut Cursor) -> Result<Token, ScanError> {
         }
         '"' => scan_string(cursor),
         c if c.is_alphabetic() || c == '_' => Ok(scan_name(cursor)),
-        c if c.is_numeric() || c == '.' => Ok(scan_number_or_dot(cursor)),
+        c if c.is_numeric() || c == '.' => scan_number_or_dot(cursor),
         _ => {
             let start = cursor.location();
             cursor.next();
@@ -90,6 +91,8 @@ fn scan_next_token(cursor: &mut Cursor) -> Result<Token, ScanError> {
                     ':' => Punctuator::Colon,
                     '.' => Punctuator::Dot,
                     ',' => Punctuator::Comma,
+                    '^' => Punctuator::Caret,
+                    '#' => Punctuator::Hash,
                 },
                 {
                     '-' => Operator::Minus,
@@ -97,9 +100,11 @@ fn scan_next_token(cursor: &mut Cursor) -> Result<Token, ScanError> {
                     '*' => Operator::Star,
                     '/' => Operator::Slash,
                     '=' => Operator::Assign,
+                    '!' => Operator::Exclamation,
                 },
                 {
                     '-', '=' => Operator::MinusEq,
+                    '-', '>' => Operator::Arrow,
                     '+', '=' => Operator::PlusEq,
                     '=', '=' => Operator::Eq,
                 }
@@ -132,6 +137,11 @@ fn scan_name(cursor: &mut Cursor) -> Token {
             "sequence" => Some(Keyword::Sequence),
             "play" => Some(Keyword::Play),
             "let" => Some(Keyword::Let),
+            "poly" => Some(Keyword::Poly),
+            "tempo" => Some(Keyword::Tempo),
+            "over" => Some(Keyword::Over),
+            "tuning" => Some(Keyword::Tuning),
+            "repeat" => Some(Keyword::Repeat),
             "load_sample" => Some(Keyword::LoadSample),
             _ => None,
         }
@@ -150,8 +160,9 @@ fn scan_name(cursor: &mut Cursor) -> Token {
     }
 }
 
-/// Scans a number or a dot (`.`) from the source text.
-fn scan_number_or_dot(cursor: &mut Cursor) -> Token {
+/// Scans a number or a dot (`.`) from the source text. Numbers may be
+/// followed by a unit: `60%`, `8ms`.
+fn scan_number_or_dot(cursor: &mut Cursor) -> Result<Token, ScanError> {
     let start = cursor.location();
     let mut has_dot = false;
 
@@ -169,24 +180,66 @@ fn scan_number_or_dot(cursor: &mut Cursor) -> Token {
     let end = cursor.location();
 
     if end.index() - start.index() == 1 && has_dot {
-        return Token::Punctuator {
+        return Ok(Token::Punctuator {
             punctuator: Punctuator::Dot,
             span: Span::new(start, end),
-        };
+        });
     }
 
     let lexeme = &cursor.source()[(start.index() as usize)..(end.index() as usize)];
 
+    if let Some(unit) = scan_unit(cursor)? {
+        return Ok(Token::Quantity {
+            value: lexeme.parse::<f64>().unwrap(),
+            unit,
+            span: Span::new(start, cursor.location()),
+        });
+    }
+
     if has_dot {
-        Token::Float {
+        Ok(Token::Float {
             value: lexeme.parse::<f64>().unwrap(),
             span: Span::new(start, end),
-        }
+        })
     } else {
-        Token::Integer {
+        Ok(Token::Integer {
             value: lexeme.parse::<i64>().unwrap(),
             span: Span::new(start, end),
+        })
+    }
+}
+
+/// Scans a unit written right after a number, if there is one.
+fn scan_unit(cursor: &mut Cursor) -> Result<Option<Unit>, ScanError> {
+    let start = cursor.location();
+
+    match cursor.peek() {
+        Some('%') => {
+            cursor.next();
+
+            Ok(Some(Unit::Percent))
+        }
+        Some(c) if c.is_alphabetic() => {
+            let mut suffix = String::new();
+
+            while let Some(c) = cursor.peek() {
+                if !c.is_alphanumeric() && c != '_' {
+                    break;
+                }
+
+                suffix.push(c);
+                cursor.next();
+            }
+
+            match suffix.as_str() {
+                "ms" => Ok(Some(Unit::Milliseconds)),
+                "s" => Ok(Some(Unit::Seconds)),
+                _ => Err(ScanError::UnknownUnit {
+                    span: Span::new(start, cursor.location()),
+                }),
+            }
         }
+        _ => Ok(None),
     }
 }
 
@@ -246,6 +299,7 @@ pub enum ScanError {
     UnexpectedCharacter { character: char, span: Span },
     InvalidEscapeSequence { character: char, span: Span },
     UnterminatedString { span: Span },
+    UnknownUnit { span: Span },
 }
 
 impl Spanned for ScanError {
@@ -253,25 +307,30 @@ impl Spanned for ScanError {
         match self {
             Self::UnexpectedCharacter { span, .. }
             | Self::InvalidEscapeSequence { span, .. }
-            | Self::UnterminatedString { span } => *span,
+            | Self::UnterminatedString { span }
+            | Self::UnknownUnit { span } => *span,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use insta::assert_debug_snapshot;
-
-    use crate::test_scan;
+    use crate::test_snapshots;
 
     use super::scan;
 
-    test_scan!(
+    fn render(source: &str) -> String {
+        format!("{:#?}", scan(source))
+    }
+
+    test_snapshots!(
         (eof, ""),
-        (unexpected_char, "!"),
+        (unexpected_char, "$"),
         (punctuation, "("),
         (number_and_dot, "3 3.2."),
         (name, "wait time"),
+        (units, "60% 8ms 1.5s"),
+        (unknown_unit, "3px"),
         (string, r#""\"Hello,\n \t world\"""#),
     );
 }
@@ -10,6 +10,11 @@ pub enum Keyword {
     Sequence,
     LoadSample,
     Let,
+    Poly,
+    Tempo,
+    Over,
+    Tuning,
+    Repeat,
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -24,6 +29,16 @@ pub enum Punctuator {
     Dot,
     Colon,
     Comma,
+    Caret,
+    Hash,
+}
+
+/// Unit of a number literal: `60%`, `8ms`, `2s`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Unit {
+    Percent,
+    Milliseconds,
+    Seconds,
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -37,6 +52,7 @@ pub enum Operator {
     Assign,
     Eq,
     Exclamation,
+    Arrow,
 }
 
 impl Operator {
@@ -54,6 +70,7 @@ impl Operator {
     pub fn into_prefix_operator_kind(&self) -> Option<PrefixOperatorKind> {
         match self {
             Self::Exclamation => Some(PrefixOperatorKind::Exclamation),
+            Self::Minus => Some(PrefixOperatorKind::Minus),
             _ => None,
         }
     }
@@ -108,6 +125,11 @@ pub enum Token {
         value: i64,
         span: Span,
     },
+    Quantity {
+        value: f64,
+        unit: Unit,
+        span: Span,
+    },
     Bool {
         value: bool,
         span: Span,
@@ -178,6 +200,7 @@ impl Spanned for Token {
             | Self::Keyword { span, .. }
             | Self::Integer { span, .. }
             | Self::Float { span, .. }
+            | Self::Quantity { span, .. }
             | Self::String { span, .. }
             | Self::Bool { span, .. }
             | Self::EndOfLine { span } => *span,
@@ -185,7 +208,7 @@ impl Spanned for Token {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Default)]
 pub struct TokenStream(Vec<Token>);
 
 impl TokenStream {
@@ -217,7 +240,7 @@ impl TokenStream {
         if self
             .0
             .last()
-            .map_or(true, |maybe_eof| !maybe_eof.is_end_of_file())
+            .is_none_or(|maybe_eof| !maybe_eof.is_end_of_file())
         {
             return None;
         }
@@ -237,6 +260,7 @@ impl TokenStream {
 pub struct TokenStreamCursor {
     stream: TokenStream,
     location: usize,
+    depth: usize,
 }
 
 impl TokenStreamCursor {
@@ -244,12 +268,24 @@ impl TokenStreamCursor {
         Self {
             stream,
             location: 0,
+            depth: 0,
         }
     }
 
+    /// Number of expressions and blocks being parsed, which the next token
+    /// is nested inside of.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+    }
+
     /// Retrieves the next token from the stream, advancing the cursor
     /// to the subsequent position. If no more tokens are available,
     /// an EOF (End Of File) token is returned.
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Token {
         self.location += 1;
 
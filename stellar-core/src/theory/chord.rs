@@ -0,0 +1,204 @@
+use std::fmt::Display;
+
+use super::{
+    note::{Note, PitchClass},
+    voicing::invert_chord,
+};
+
+/// Octave of chords written without one, e.g. `cmaj7`.
+pub const DEFAULT_CHORD_OCTAVE: i32 = 4;
+
+/// Represents a chord quality along with the intervals (in semitones above
+/// the root) of its tones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChordQuality {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    Suspended2,
+    Suspended4,
+    Major6,
+    Minor6,
+    Dominant7,
+    Major7,
+    Minor7,
+    MinorMajor7,
+    HalfDiminished7,
+    Diminished7,
+    Dominant9,
+    Major9,
+    Minor9,
+}
+
+impl ChordQuality {
+    /// Every quality, from the simplest one.
+    pub const ALL: [Self; 17] = [
+        Self::Major,
+        Self::Minor,
+        Self::Diminished,
+        Self::Augmented,
+        Self::Suspended2,
+        Self::Suspended4,
+        Self::Major6,
+        Self::Minor6,
+        Self::Dominant7,
+        Self::Major7,
+        Self::Minor7,
+        Self::MinorMajor7,
+        Self::HalfDiminished7,
+        Self::Diminished7,
+        Self::Dominant9,
+        Self::Major9,
+        Self::Minor9,
+    ];
+
+    /// Every supported quality with its name suffix. When a quality has
+    /// several spellings, the first one is used for printing. Suffixes are
+    /// never bare digits, since names like `c6` or `g7` are notes.
+    const SUFFIXES: [(&'static str, Self); 20] = [
+        ("maj", Self::Major),
+        ("m", Self::Minor),
+        ("min", Self::Minor),
+        ("dim", Self::Diminished),
+        ("aug", Self::Augmented),
+        ("sus2", Self::Suspended2),
+        ("sus4", Self::Suspended4),
+        ("maj6", Self::Major6),
+        ("m6", Self::Minor6),
+        ("dom7", Self::Dominant7),
+        ("maj7", Self::Major7),
+        ("m7", Self::Minor7),
+        ("min7", Self::Minor7),
+        ("mmaj7", Self::MinorMajor7),
+        ("m7b5", Self::HalfDiminished7),
+        ("dim7", Self::Diminished7),
+        ("dom9", Self::Dominant9),
+        ("maj9", Self::Major9),
+        ("m9", Self::Minor9),
+        ("min9", Self::Minor9),
+    ];
+
+    /// Parses a chord quality suffix, e.g. `maj7` or `m`.
+    pub fn parse(suffix: &str) -> Option<Self> {
+        Self::SUFFIXES
+            .iter()
+            .find(|(name, _)| *name == suffix)
+            .map(|(_, quality)| *quality)
+    }
+
+    /// Returns the canonical suffix of the quality.
+    pub fn suffix(self) -> &'static str {
+        Self::SUFFIXES
+            .iter()
+            .find(|(_, quality)| *quality == self)
+            .map_or("", |(name, _)| name)
+    }
+
+    /// Returns intervals of the chord tones in semitones above the root.
+    pub fn intervals(self) -> &'static [i32] {
+        match self {
+            Self::Major => &[0, 4, 7],
+            Self::Minor => &[0, 3, 7],
+            Self::Diminished => &[0, 3, 6],
+            Self::Augmented => &[0, 4, 8],
+            Self::Suspended2 => &[0, 2, 7],
+            Self::Suspended4 => &[0, 5, 7],
+            Self::Major6 => &[0, 4, 7, 9],
+            Self::Minor6 => &[0, 3, 7, 9],
+            Self::Dominant7 => &[0, 4, 7, 10],
+            Self::Major7 => &[0, 4, 7, 11],
+            Self::Minor7 => &[0, 3, 7, 10],
+            Self::MinorMajor7 => &[0, 3, 7, 11],
+            Self::HalfDiminished7 => &[0, 3, 6, 10],
+            Self::Diminished7 => &[0, 3, 6, 9],
+            Self::Dominant9 => &[0, 4, 7, 10, 14],
+            Self::Major9 => &[0, 4, 7, 11, 14],
+            Self::Minor9 => &[0, 3, 7, 10, 14],
+        }
+    }
+}
+
+/// Represents a chord built from a root note and a quality.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub root: Note,
+    pub quality: ChordQuality,
+    /// Number of the lowest tones moved an octave up, so `1` puts the
+    /// third in the bass.
+    pub inversion: u8,
+}
+
+impl Chord {
+    pub fn new(root: Note, quality: ChordQuality) -> Self {
+        Self {
+            root,
+            quality,
+            inversion: 0,
+        }
+    }
+
+    pub fn inverted(self, inversion: u8) -> Self {
+        Self { inversion, ..self }
+    }
+
+    /// Parses a chord name: a pitch class followed by a quality suffix,
+    /// e.g. `cmaj7`, `am`, `fsdim`. A bare pitch class (`c`) is not
+    /// considered to be a chord, so it doesn't shadow short identifiers.
+    pub fn parse(name: &str) -> Option<Self> {
+        // Try the longest pitch class name first, so that `bbm` is parsed
+        // as B flat minor.
+        (1..=name.len().min(2)).rev().find_map(|split| {
+            let (root, suffix) = name.split_at_checked(split)?;
+            if suffix.is_empty() {
+                return None;
+            }
+
+            Some(Self::new(
+                Note::new(PitchClass::parse(root)?, DEFAULT_CHORD_OCTAVE),
+                ChordQuality::parse(suffix)?,
+            ))
+        })
+    }
+
+    /// Expands the chord into its tones, ordered from the lowest one.
+    pub fn notes(&self) -> Vec<Note> {
+        let notes: Vec<_> = self
+            .quality
+            .intervals()
+            .iter()
+            .map(|interval| self.root.shift(*interval))
+            .collect();
+
+        invert_chord(&notes, self.inversion)
+    }
+
+    /// Returns `true` if a pitch class is one of the chord tones.
+    pub fn contains(&self, pitch_class: PitchClass) -> bool {
+        self.notes()
+            .into_iter()
+            .any(|note| note.pitch_class() == pitch_class)
+    }
+
+    /// Returns every note from `low` to `high`, which is a chord tone in
+    /// any octave, ordered from the lowest one.
+    pub fn voicing(&self, low: Note, high: Note) -> Vec<Note> {
+        (low.midi()..=high.midi())
+            .filter_map(Note::from_midi)
+            .filter(|note| self.contains(note.pitch_class()))
+            .collect()
+    }
+}
+
+impl Display for Chord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.root.pitch_class(), self.quality.suffix())?;
+
+        // Inversions are written as slash chords: `cmaj/e`.
+        if self.inversion > 0 {
+            write!(f, "/{}", self.notes()[0].pitch_class())?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use std::fmt::Display;
+
+use super::note::{Note, PitchClass};
+
+/// Octave, in which the tonic of a key is placed when resolving scale
+/// degrees, so `^1` in `c_major` is `c4`.
+pub const DEFAULT_TONIC_OCTAVE: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyMode {
+    Major,
+    Minor,
+}
+
+impl KeyMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "major" => Some(Self::Major),
+            "minor" => Some(Self::Minor),
+            _ => None,
+        }
+    }
+
+    /// Returns intervals of the scale degrees in semitones above the tonic.
+    pub fn intervals(self) -> &'static [i32; 7] {
+        match self {
+            Self::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Self::Minor => &[0, 2, 3, 5, 7, 8, 10],
+        }
+    }
+}
+
+impl Display for KeyMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Major => write!(f, "major"),
+            Self::Minor => write!(f, "minor"),
+        }
+    }
+}
+
+/// Represents a musical key, e.g. `d_minor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub tonic: PitchClass,
+    pub mode: KeyMode,
+}
+
+impl Key {
+    pub fn new(tonic: PitchClass, mode: KeyMode) -> Self {
+        Self { tonic, mode }
+    }
+
+    /// Parses a key name: a pitch class and a mode separated with an
+    /// underscore, e.g. `c_major`, `fs_minor`.
+    pub fn parse(name: &str) -> Option<Self> {
+        let (tonic, mode) = name.split_once('_')?;
+
+        Some(Self::new(PitchClass::parse(tonic)?, KeyMode::parse(mode)?))
+    }
+
+    /// Returns the note of a given scale degree. Degrees start from `1`
+    /// (the tonic), and the ones above `7` continue into the next octaves,
+    /// so `^8` is the tonic an octave higher. Returns `None` for degrees
+    /// below `1` or above the range of notes.
+    pub fn degree(&self, degree: i64) -> Option<Note> {
+        if degree < 1 {
+            return None;
+        }
+
+        let index = degree - 1;
+        let octave = i32::try_from(index / 7).ok()?;
+        let interval = self.mode.intervals()[(index % 7) as usize];
+
+        let midi = Note::new(self.tonic, DEFAULT_TONIC_OCTAVE)
+            .midi()
+            .checked_add(octave.checked_mul(12)?)?
+            .checked_add(interval)?;
+
+        Note::from_midi(midi)
+    }
+
+    /// Returns `true` if a pitch class is one of the scale degrees.
+    pub fn contains(&self, pitch_class: PitchClass) -> bool {
+        let interval = (pitch_class.semitones() - self.tonic.semitones()).rem_euclid(12);
+
+        self.mode.intervals().contains(&interval)
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}_{}", self.tonic, self.mode)
+    }
+}
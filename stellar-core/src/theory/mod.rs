@@ -0,0 +1,385 @@
+//! Music theory primitives: notes, pitch classes, chords, keys, scales and
+//! Roman numerals.
+
+pub mod chord;
+pub mod key;
+pub mod note;
+pub mod numeral;
+pub mod recognize;
+pub mod scale;
+pub mod tuning;
+pub mod voicing;
+
+use self::{chord::Chord, key::Key, note::Note};
+
+/// Builtin value named by an identifier.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Name {
+    Note(Note),
+    Chord(Chord),
+    Key(Key),
+}
+
+/// Parses the name of a note (`c4`), a chord (`cmaj7`) or a key
+/// (`d_minor`). No name is both a note and a chord, and chords are spelled
+/// the way [`recognize`](recognize::recognize) names them.
+pub fn parse_name(name: &str) -> Option<Name> {
+    Note::parse(name)
+        .map(Name::Note)
+        .or_else(|| Chord::parse(name).map(Name::Chord))
+        .or_else(|| Key::parse(name).map(Name::Key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        chord::{Chord, ChordQuality, DEFAULT_CHORD_OCTAVE},
+        key::Key,
+        note::{Note, PitchClass},
+        numeral::{progression, RomanNumeral},
+        parse_name,
+        recognize::recognize,
+        scale::{Scale, ScaleKind},
+        tuning::{KeyboardMapping, Tuning, TuningError},
+        voicing::{drop2, invert, voice_lead},
+        Name,
+    };
+
+    fn names(notes: &[Note]) -> Vec<String> {
+        notes.iter().map(Note::to_string).collect()
+    }
+
+    #[test]
+    fn parse_notes() {
+        assert_eq!(Note::parse("c4").map(Note::midi), Some(60));
+        assert_eq!(Note::parse("a4").map(Note::midi), Some(69));
+        assert_eq!(Note::parse("fs3").map(Note::midi), Some(54));
+        assert_eq!(Note::parse("bb2").map(Note::midi), Some(46));
+        assert_eq!(
+            Note::parse("cb4").map(|note| note.to_string()),
+            Some("b3".to_owned())
+        );
+        assert_eq!(Note::parse("h4"), None);
+        assert_eq!(Note::parse("c"), None);
+    }
+
+    #[test]
+    fn parse_chords() {
+        let cmaj7 = Chord::parse("cmaj7").unwrap();
+        assert_eq!(cmaj7.quality, ChordQuality::Major7);
+        assert_eq!(
+            cmaj7
+                .notes()
+                .into_iter()
+                .map(Note::midi)
+                .collect::<Vec<_>>(),
+            [60, 64, 67, 71]
+        );
+
+        assert_eq!(Chord::parse("bbm").unwrap().to_string(), "asm");
+        assert_eq!(Chord::parse("am").unwrap().quality, ChordQuality::Minor);
+        assert_eq!(
+            Chord::parse("gdom7").unwrap().quality,
+            ChordQuality::Dominant7
+        );
+        assert_eq!(Chord::parse("g7"), None);
+        assert_eq!(Chord::parse("c"), None);
+        assert_eq!(Chord::parse("kick"), None);
+    }
+
+    #[test]
+    fn scale_degrees() {
+        let d_minor = Key::parse("d_minor").unwrap();
+        let degrees: Vec<_> = [1, 3, 5, 8]
+            .into_iter()
+            .map(|degree| d_minor.degree(degree).unwrap().to_string())
+            .collect();
+
+        assert_eq!(degrees, ["d4", "f4", "a4", "d5"]);
+        assert_eq!(d_minor.degree(0), None);
+        assert!(d_minor.contains(PitchClass::parse("bb").unwrap()));
+        assert!(!d_minor.contains(PitchClass::parse("b").unwrap()));
+        assert_eq!(Key::parse("bb_major").unwrap().to_string(), "as_major");
+        assert_eq!(Key::parse("d_lydian"), None);
+    }
+
+    #[test]
+    fn scales() {
+        let c4 = Note::parse("c4").unwrap();
+        let dorian = Scale::new(c4, ScaleKind::parse("dorian").unwrap());
+        let degrees: Vec<_> = [1, 3, 7, 8, 15, -1, -7, -8]
+            .into_iter()
+            .map(|degree| dorian.degree(degree).unwrap())
+            .collect();
+
+        assert_eq!(
+            names(&degrees),
+            ["c4", "ds4", "as4", "c5", "c6", "as3", "c3", "as2"]
+        );
+        assert_eq!(dorian.degree(0), None);
+        assert_eq!(dorian.to_string(), "c4 dorian");
+        assert_eq!(
+            Scale::new(c4, ScaleKind::parse("aeolian").unwrap()).to_string(),
+            "c4 minor"
+        );
+        assert_eq!(ScaleKind::parse("bebop"), None);
+
+        let pentatonic = Scale::new(c4, ScaleKind::parse("major_pentatonic").unwrap());
+        let quantized: Vec<_> = ["cs4", "f4", "as4", "b4"]
+            .into_iter()
+            .map(|name| pentatonic.quantize(Note::parse(name).unwrap()))
+            .collect();
+        assert_eq!(names(&quantized), ["c4", "e4", "a4", "c5"]);
+
+        let custom = ScaleKind::custom(&[7, 0, 19, -9]).unwrap();
+        assert_eq!(custom.intervals(), [0, 3, 7]);
+        assert_eq!(Scale::new(c4, custom).to_string(), "c4 [0, 3, 7]");
+        assert_eq!(ScaleKind::custom(&[]), None);
+    }
+
+    #[test]
+    fn numerals() {
+        let chords = |numerals, key| {
+            progression(numerals, Key::parse(key).unwrap())
+                .unwrap()
+                .iter()
+                .map(Chord::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            chords("I vi IV V7", "c_major"),
+            ["cmaj", "am", "fmaj", "gdom7"]
+        );
+        assert_eq!(
+            chords("ii7 V7 Imaj7", "bb_major"),
+            ["cm7", "fdom7", "asmaj7"]
+        );
+        assert_eq!(
+            chords("V/V V7/ii viio7/V bVII bVI iv", "c_major"),
+            ["dmaj", "adom7", "fsdim7", "asmaj", "gsmaj", "fm"]
+        );
+        assert_eq!(
+            chords("I6 I64 V65 V43 V42 iiø7", "c_major"),
+            ["cmaj/e", "cmaj/g", "gdom7/b", "gdom7/d", "gdom7/f", "dm7b5"]
+        );
+        assert_eq!(chords("i III V", "a_minor"), ["am", "cmaj", "emaj"]);
+
+        let v65 = RomanNumeral::parse("V65")
+            .unwrap()
+            .chord(Key::parse("c_major").unwrap());
+        assert_eq!(names(&v65.notes()), ["b4", "d5", "f5", "g5"]);
+
+        assert_eq!(progression("I X", Key::parse("c_major").unwrap()), Err("X"));
+        assert_eq!(RomanNumeral::parse("Iv"), None);
+        assert_eq!(RomanNumeral::parse("V8"), None);
+        assert_eq!(RomanNumeral::parse("iiø"), None);
+    }
+
+    #[test]
+    fn recognition() {
+        let recognized = |names: &[&str]| {
+            let notes: Vec<_> = names
+                .iter()
+                .map(|name| Note::parse(name).unwrap())
+                .collect();
+
+            recognize(&notes)
+                .iter()
+                .map(Chord::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(recognized(&["e3", "g3", "c4"]), ["cmaj/e"]);
+        assert_eq!(recognized(&["c4", "e4", "g4", "a4"]), ["cmaj6", "am7/c"]);
+        assert_eq!(recognized(&["g3", "b3", "f4"]), ["gdom7"]);
+        assert_eq!(recognized(&["c4", "d4", "g4"]), ["csus2", "gsus4/c"]);
+        assert_eq!(
+            recognized(&["b3", "d4", "f4", "gs4"]),
+            ["bdim7", "ddim7/b", "fdim7/b", "gsdim7/b"]
+        );
+        assert!(recognized(&["c4", "cs4", "d4"]).is_empty());
+        assert!(recognized(&[]).is_empty());
+
+        let inverted = recognize(&[
+            Note::parse("e3").unwrap(),
+            Note::parse("g3").unwrap(),
+            Note::parse("c4").unwrap(),
+        ]);
+        assert_eq!(names(&inverted[0].notes()), ["e3", "g3", "c4"]);
+    }
+
+    #[test]
+    fn parse_names() {
+        assert_eq!(parse_name("c6"), Note::parse("c6").map(Name::Note));
+        assert_eq!(parse_name("g7"), Note::parse("g7").map(Name::Note));
+        assert_eq!(parse_name("d_minor"), Key::parse("d_minor").map(Name::Key));
+        assert_eq!(parse_name("cmaj9x"), None);
+
+        // Every chord, which can be recognized, is named in a way that
+        // parses back to it.
+        for root in 0..12 {
+            for quality in ChordQuality::ALL {
+                let chord = Chord::new(
+                    Note::new(PitchClass::new(root), DEFAULT_CHORD_OCTAVE),
+                    quality,
+                );
+                let name = chord.to_string();
+
+                assert_eq!(Note::parse(&name), None, "{name}");
+                assert_eq!(parse_name(&name), Some(Name::Chord(chord)), "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn note_operations() {
+        let a4 = Note::parse("a4").unwrap();
+
+        assert_eq!(a4.frequency(), 440.0);
+        assert!((Note::parse("c4").unwrap().frequency() - 261.626).abs() < 1e-3);
+        assert_eq!(Note::parse("c4").unwrap().interval(a4), 9);
+        assert_eq!(a4.interval(Note::parse("e4").unwrap()), -5);
+
+        assert_eq!(Note::parse("c200000000"), None);
+        assert_eq!(Note::from_midi(i32::MIN), None);
+        assert_eq!(a4.transpose(i32::MAX), None);
+        assert_eq!(Note::HIGHEST.transpose(1), None);
+    }
+
+    #[test]
+    fn tunings() {
+        let note = |name| Note::parse(name).unwrap();
+        let assert_close = |left: Option<f64>, right: f64| {
+            let left = left.unwrap();
+            assert!((left - right).abs() < 1e-6, "{left} != {right}");
+        };
+
+        let standard = Tuning::default();
+        for name in ["c4", "a4", "fs2", "b7"] {
+            assert_close(standard.frequency(note(name)), note(name).frequency());
+        }
+        assert_eq!(standard.to_string(), "12-edo, a4 = 440 Hz");
+
+        let edo19 = Tuning::equal(19, note("a4"), 432.0).unwrap();
+        assert_close(edo19.frequency(note("a4")), 432.0);
+        assert_close(edo19.frequency(note("a4").transpose(19).unwrap()), 864.0);
+        assert_close(edo19.frequency(note("a4").transpose(-19).unwrap()), 216.0);
+        assert_eq!(
+            Tuning::equal(0, note("a4"), 440.0),
+            Err(TuningError::InvalidSize)
+        );
+
+        let just = Tuning::just(&[9.0 / 8.0, 5.0 / 4.0, 2.0], note("c4"), 264.0).unwrap();
+        assert_close(just.frequency(note("d4")), 330.0);
+        assert_close(just.frequency(note("ds4")), 528.0);
+        assert_close(just.frequency(note("b3")), 165.0);
+        assert_eq!(
+            Tuning::just(&[-1.0], note("c4"), 264.0),
+            Err(TuningError::InvalidRatio { ratio: -1.0 })
+        );
+    }
+
+    #[test]
+    fn scala_files() {
+        let note = |name| Note::parse(name).unwrap();
+        let pentatonic = "! pentatonic.scl\n!\nPythagorean pentatonic\n 5\n!\n9/8\n81/64 ditone\n701.955\n27/16\n2\n";
+
+        let tuning = Tuning::parse_scl(pentatonic, None).unwrap();
+        assert_eq!(tuning.name, "Pythagorean pentatonic");
+        assert_eq!(tuning.pitches.len(), 5);
+        assert!((tuning.frequency(note("d4")).unwrap() - 261.6256 * 81.0 / 64.0).abs() < 1e-3);
+
+        // Five keys of the octave are mapped, leaving the black keys out.
+        let mapping = KeyboardMapping::parse(
+            "! white.kbm\n12\n0\n127\n60\n69\n440.0\n5\n0\nx\n1\nx\n2\nx\nx\n3\nx\n4\n",
+        )
+        .unwrap();
+        assert_eq!(mapping.keys[11], None);
+
+        let tuning = Tuning::parse_scl(pentatonic, Some(mapping)).unwrap();
+        assert_eq!(tuning.frequency(note("a4")), Some(440.0));
+        assert!((tuning.frequency(note("c5")).unwrap() - 440.0 * 32.0 / 27.0).abs() < 1e-9);
+        assert_eq!(tuning.frequency(note("cs4")), None);
+
+        assert_eq!(
+            Tuning::parse_scl("broken\n2\n3/2\n", None),
+            Err(TuningError::MissingField { name: "pitch" })
+        );
+        assert_eq!(
+            Tuning::parse_scl("broken\n1\nfifth\n", None),
+            Err(TuningError::InvalidLine { line: 3 })
+        );
+        assert_eq!(
+            KeyboardMapping::parse("12\n0\n127\n60\n69\n0\n12\n"),
+            Err(TuningError::InvalidFrequency { frequency: 0.0 })
+        );
+        assert_eq!(
+            KeyboardMapping::parse("12\n0\n127\n60\n128\n440.0\n12\n"),
+            Err(TuningError::InvalidNote {
+                name: "reference note"
+            })
+        );
+        assert_eq!(
+            KeyboardMapping::parse("12\n-1\n127\n60\n69\n440.0\n12\n"),
+            Err(TuningError::InvalidNote { name: "first note" })
+        );
+    }
+
+    #[test]
+    fn voicings() {
+        let cmaj7 = Chord::parse("cmaj7").unwrap();
+
+        let inversion = |n| invert(&cmaj7.notes(), n).unwrap();
+        assert_eq!(names(&inversion(1)), ["e4", "g4", "b4", "c5"]);
+        assert_eq!(names(&inversion(6)), ["g5", "b5", "c6", "e6"]);
+        assert_eq!(names(&inversion(-1)), ["b3", "c4", "e4", "g4"]);
+        assert_eq!(invert(&cmaj7.notes(), i32::MAX), None);
+        assert_eq!(
+            names(&drop2(&cmaj7.notes()).unwrap()),
+            ["g3", "c4", "e4", "b4"]
+        );
+        assert_eq!(
+            names(
+                &Chord::parse("am")
+                    .unwrap()
+                    .voicing(Note::parse("g3").unwrap(), Note::parse("c5").unwrap())
+            ),
+            ["a3", "c4", "e4", "a4", "c5"]
+        );
+        assert_eq!(invert(&[], 3), Some(Vec::new()));
+    }
+
+    #[test]
+    fn voice_leading() {
+        let chords: Vec<_> = ["cmaj", "am", "fmaj", "gdom7", "cmaj"]
+            .into_iter()
+            .map(|name| Chord::parse(name).unwrap())
+            .collect();
+        let (low, high) = (Note::parse("c3").unwrap(), Note::parse("c6").unwrap());
+
+        let voicings = voice_lead(&chords, 4, low, high).unwrap();
+        let voicings: Vec<_> = voicings.iter().map(|voicing| names(voicing)).collect();
+        assert_eq!(
+            voicings,
+            [
+                ["c4", "e4", "g4", "c5"],
+                ["c4", "e4", "a4", "c5"],
+                ["c4", "f4", "a4", "c5"],
+                ["d4", "f4", "g4", "b4"],
+                ["c4", "e4", "g4", "c5"],
+            ]
+        );
+
+        // Inverted chords keep their bass, and the fifth is left out first.
+        let g7 = Chord::parse("gdom7").unwrap().inverted(1);
+        assert_eq!(
+            voice_lead(&[g7], 3, low, high).map(|voicings| names(&voicings[0])),
+            Some(vec!["b3".to_owned(), "f4".to_owned(), "g4".to_owned()])
+        );
+        assert_eq!(
+            voice_lead(&chords, 4, low, Note::parse("e3").unwrap()),
+            None
+        );
+    }
+}
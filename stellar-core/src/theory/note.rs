@@ -0,0 +1,153 @@
+use std::fmt::Display;
+
+/// Frequency of `a4` in hertz, which other notes are tuned relative to.
+pub const A4_FREQUENCY: f64 = 440.0;
+
+/// Names of pitch classes, spelled with sharps.
+const PITCH_CLASS_NAMES: [&str; 12] = [
+    "c", "cs", "d", "ds", "e", "f", "fs", "g", "gs", "a", "as", "b",
+];
+
+/// Represents one of twelve pitch classes of the chromatic scale,
+/// `0` being `C` and `11` being `B`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PitchClass(u8);
+
+impl PitchClass {
+    /// Creates a pitch class from a number of semitones above `C`, wrapping
+    /// around the octave.
+    pub fn new(semitones: i32) -> Self {
+        Self(semitones.rem_euclid(12) as u8)
+    }
+
+    /// Parses a pitch class name: a letter, optionally followed by `s`
+    /// (sharp) or `b` (flat), e.g. `c`, `fs`, `bb`.
+    pub fn parse(name: &str) -> Option<Self> {
+        parse_pitch(name).map(Self::new)
+    }
+
+    /// Returns number of semitones above `C`.
+    pub fn semitones(self) -> i32 {
+        self.0 as i32
+    }
+}
+
+/// Parses a pitch class name into a number of semitones above `C` without
+/// wrapping, so `cb` is `-1` and `bs` is `12`.
+fn parse_pitch(name: &str) -> Option<i32> {
+    let mut chars = name.chars();
+    let base = match chars.next()? {
+        'c' => 0,
+        'd' => 2,
+        'e' => 4,
+        'f' => 5,
+        'g' => 7,
+        'a' => 9,
+        'b' => 11,
+        _ => return None,
+    };
+
+    let accidental = match chars.as_str() {
+        "" => 0,
+        "s" => 1,
+        "b" => -1,
+        _ => return None,
+    };
+
+    Some(base + accidental)
+}
+
+impl Display for PitchClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", PITCH_CLASS_NAMES[self.0 as usize])
+    }
+}
+
+/// Represents a note of the chromatic scale, stored as a MIDI note number,
+/// so `c4` (middle C) is `60`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Note(i32);
+
+impl Note {
+    /// Lowest note, far below audible ones. Notes are kept between it and
+    /// [`Note::HIGHEST`], so that moving them by a few octaves can't
+    /// overflow.
+    pub const LOWEST: Self = Self(-(1 << 16));
+
+    /// Highest note, far above audible ones.
+    pub const HIGHEST: Self = Self(1 << 16);
+
+    /// Creates a note from a MIDI note number, or returns `None` if it is
+    /// outside of the range of notes.
+    pub fn from_midi(number: i32) -> Option<Self> {
+        (Self::LOWEST.0..=Self::HIGHEST.0)
+            .contains(&number)
+            .then_some(Self(number))
+    }
+
+    /// Creates a note from a pitch class and an octave number.
+    pub fn new(pitch_class: PitchClass, octave: i32) -> Self {
+        Self((octave + 1) * 12 + pitch_class.semitones())
+    }
+
+    /// Parses a note name: a pitch class followed by an octave number,
+    /// e.g. `c4`, `fs3`, `bb2`.
+    pub fn parse(name: &str) -> Option<Self> {
+        let split = name.find(|c: char| c.is_ascii_digit())?;
+        let (pitch_class, octave) = name.split_at(split);
+
+        let octave: i32 = octave.parse().ok()?;
+
+        Self::from_midi(
+            octave
+                .checked_add(1)?
+                .checked_mul(12)?
+                .checked_add(parse_pitch(pitch_class)?)?,
+        )
+    }
+
+    /// Returns the MIDI note number.
+    pub fn midi(self) -> i32 {
+        self.0
+    }
+
+    pub fn pitch_class(self) -> PitchClass {
+        PitchClass::new(self.0)
+    }
+
+    pub fn octave(self) -> i32 {
+        self.0.div_euclid(12) - 1
+    }
+
+    /// Moves the note up (or down, if `semitones` is negative) by a given
+    /// number of semitones, or returns `None` if the result is outside of
+    /// the range of notes.
+    pub fn transpose(self, semitones: i32) -> Option<Self> {
+        Self::from_midi(self.0.checked_add(semitones)?)
+    }
+
+    /// Moves the note by a small interval, such as the tones of a chord
+    /// above its root. The result may lie slightly outside of the range of
+    /// notes, but doesn't overflow.
+    pub(crate) fn shift(self, semitones: i32) -> Self {
+        Self(self.0 + semitones)
+    }
+
+    /// Returns the frequency of the note in hertz in twelve-tone equal
+    /// temperament.
+    pub fn frequency(self) -> f64 {
+        A4_FREQUENCY * 2f64.powf(f64::from(self.0 - 69) / 12.0)
+    }
+
+    /// Returns the number of semitones from the note up to another one,
+    /// which is negative if the other note is lower.
+    pub fn interval(self, other: Self) -> i32 {
+        other.0 - self.0
+    }
+}
+
+impl Display for Note {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.pitch_class(), self.octave())
+    }
+}
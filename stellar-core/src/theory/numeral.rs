@@ -0,0 +1,153 @@
+//! Roman numerals, which name chords by the scale degree of their root in
+//! a key, so that progressions can be written once and played in any key:
+//! `I vi IV V7`, `ii7 V7 Imaj7`.
+
+use super::{
+    chord::{Chord, ChordQuality, DEFAULT_CHORD_OCTAVE},
+    key::{Key, KeyMode},
+    note::Note,
+};
+
+/// Numerals of the seven degrees, in order.
+const NUMERALS: [&str; 7] = ["I", "II", "III", "IV", "V", "VI", "VII"];
+
+/// Represents a chord written as a Roman numeral: `V7`, `bVII`, `V/V`,
+/// `I6`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomanNumeral {
+    /// Scale degree of the root, from `1` to `7`.
+    pub degree: u8,
+    /// Semitones the root is moved by accidentals, so `bVII` is `-1`.
+    pub alteration: i32,
+    pub quality: ChordQuality,
+    pub inversion: u8,
+    /// Chord, whose root is the tonic the numeral is read in, like the
+    /// `V` of `V/V`.
+    pub tonicization: Option<Box<RomanNumeral>>,
+}
+
+impl RomanNumeral {
+    /// Parses a numeral: accidentals (`b`, `#`), the numeral, whose case
+    /// tells if the chord is major or minor, a quality mark (`o`, `ø`,
+    /// `+`, `maj`), and a figure: `7`, `9`, or an inversion, like `6`,
+    /// `64`, `65`, `43` and `42`. A numeral after a slash is the chord
+    /// the rest is read relative to.
+    pub fn parse(name: &str) -> Option<Self> {
+        let (name, tonicization) = match name.split_once('/') {
+            Some((name, target)) => (name, Some(Box::new(Self::parse(target)?))),
+            None => (name, None),
+        };
+
+        let mut alteration = 0;
+        let mut rest = name;
+        loop {
+            if let Some(stripped) = rest.strip_prefix('b') {
+                alteration -= 1;
+                rest = stripped;
+            } else if let Some(stripped) = rest.strip_prefix('#') {
+                alteration += 1;
+                rest = stripped;
+            } else {
+                break;
+            }
+        }
+
+        let length = rest
+            .find(|c: char| !matches!(c, 'I' | 'V' | 'i' | 'v'))
+            .unwrap_or(rest.len());
+        let (numeral, suffix) = rest.split_at(length);
+
+        let upper = numeral.to_uppercase();
+        let major = if numeral == upper {
+            true
+        } else if numeral == numeral.to_lowercase() {
+            false
+        } else {
+            return None;
+        };
+        let degree = NUMERALS.iter().position(|candidate| *candidate == upper)? as u8 + 1;
+
+        let (mark, figure) = ["maj", "o", "°", "ø", "+"]
+            .into_iter()
+            .find_map(|mark| Some((mark, suffix.strip_prefix(mark)?)))
+            .unwrap_or(("", suffix));
+        let (size, inversion) = match figure {
+            "" => (3, 0),
+            "6" => (3, 1),
+            "64" => (3, 2),
+            "7" => (4, 0),
+            "65" => (4, 1),
+            "43" => (4, 2),
+            "42" | "2" => (4, 3),
+            "9" => (5, 0),
+            _ => return None,
+        };
+
+        let quality = match (size, mark, major) {
+            (3, "" | "maj", true) => ChordQuality::Major,
+            (3, "", false) => ChordQuality::Minor,
+            (3, "o" | "°", _) => ChordQuality::Diminished,
+            (3, "+", _) => ChordQuality::Augmented,
+            (4, "", true) => ChordQuality::Dominant7,
+            (4, "", false) => ChordQuality::Minor7,
+            (4, "maj", true) => ChordQuality::Major7,
+            (4, "maj", false) => ChordQuality::MinorMajor7,
+            (4, "o" | "°", _) => ChordQuality::Diminished7,
+            (4, "ø", _) => ChordQuality::HalfDiminished7,
+            (5, "", true) => ChordQuality::Dominant9,
+            (5, "", false) => ChordQuality::Minor9,
+            (5, "maj", true) => ChordQuality::Major9,
+            _ => return None,
+        };
+
+        Some(Self {
+            degree,
+            alteration,
+            quality,
+            inversion,
+            tonicization,
+        })
+    }
+
+    /// Returns the chord of the numeral in a key. Its root is placed in the
+    /// octave of chords written by name, so `IV` in `c_major` is `fmaj`.
+    pub fn chord(&self, key: Key) -> Chord {
+        // `V/ii` is read in the key of `ii`, which is minor.
+        let key = match &self.tonicization {
+            Some(target) => {
+                let tonic = target.chord(key);
+                let mode = match tonic.quality.intervals()[1] {
+                    3 => KeyMode::Minor,
+                    _ => KeyMode::Major,
+                };
+
+                Key::new(tonic.root.pitch_class(), mode)
+            }
+            None => key,
+        };
+
+        let root = key
+            .degree(self.degree.into())
+            .expect("degrees of numerals are between 1 and 7")
+            .shift(self.alteration);
+
+        Chord::new(
+            Note::new(root.pitch_class(), DEFAULT_CHORD_OCTAVE),
+            self.quality,
+        )
+        .inverted(self.inversion)
+    }
+}
+
+/// Returns chords of numerals separated with whitespace, or the first
+/// numeral, which isn't valid.
+pub fn progression(numerals: &str, key: Key) -> Result<Vec<Chord>, &str> {
+    numerals
+        .split_whitespace()
+        .map(|numeral| {
+            RomanNumeral::parse(numeral)
+                .map(|parsed| parsed.chord(key))
+                .ok_or(numeral)
+        })
+        .collect()
+}
@@ -0,0 +1,63 @@
+//! Naming chords from their notes, e.g. when annotating imported MIDI:
+//! `e3 g3 c4` is `cmaj/e`.
+
+use std::collections::BTreeSet;
+
+use super::{
+    chord::{Chord, ChordQuality},
+    note::{Note, PitchClass},
+};
+
+/// Returns every chord, which consists of the pitch classes of the notes,
+/// from the most likely one. Chords are inverted, so that their lowest
+/// tone is the lowest note. The fifth may be left out of chords of four
+/// or more tones, but such chords come after complete ones, and so do
+/// inversions after chords in root position.
+pub fn recognize(notes: &[Note]) -> Vec<Chord> {
+    let Some(bass) = notes.iter().min().copied() else {
+        return Vec::new();
+    };
+    let pitch_classes: BTreeSet<_> = notes.iter().map(|note| note.pitch_class()).collect();
+
+    let mut candidates = Vec::new();
+    for root in &pitch_classes {
+        for (rank, quality) in ChordQuality::ALL.into_iter().enumerate() {
+            let tones: Vec<_> = quality
+                .intervals()
+                .iter()
+                .map(|interval| PitchClass::new(root.semitones() + interval))
+                .collect();
+
+            let complete = BTreeSet::from_iter(tones.iter().copied()) == pitch_classes;
+            let without_fifth = tones.len() > 3
+                && tones
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| *index != 2)
+                    .map(|(_, tone)| *tone)
+                    .collect::<BTreeSet<_>>()
+                    == pitch_classes;
+            if !complete && !without_fifth {
+                continue;
+            }
+
+            // A ninth in the bass isn't an inversion.
+            let inversion = tones
+                .iter()
+                .position(|tone| *tone == bass.pitch_class())
+                .filter(|inversion| *inversion < 4)
+                .map(|inversion| inversion as u8);
+            let Some(inversion) = inversion else {
+                continue;
+            };
+
+            let interval = (bass.pitch_class().semitones() - root.semitones()).rem_euclid(12);
+            let chord = Chord::new(bass.shift(-interval), quality).inverted(inversion);
+
+            candidates.push(((!complete, inversion > 0, rank), chord));
+        }
+    }
+
+    candidates.sort_by_key(|(score, _)| *score);
+    candidates.into_iter().map(|(_, chord)| chord).collect()
+}
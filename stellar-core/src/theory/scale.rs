@@ -0,0 +1,173 @@
+use std::fmt::Display;
+
+use super::note::{Note, PitchClass};
+
+/// Represents a kind of scale along with the intervals (in semitones above
+/// the root) of its tones within an octave.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ScaleKind {
+    Major,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Minor,
+    Locrian,
+    HarmonicMinor,
+    MelodicMinor,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+    WholeTone,
+    /// Octatonic scale alternating whole and half steps.
+    Diminished,
+    /// Scale defined by its intervals, which are distinct and ordered
+    /// from `0` to `11`.
+    Custom(Vec<i32>),
+}
+
+impl ScaleKind {
+    /// Every named kind with its name. When a kind has several names, the
+    /// first one is used for printing.
+    const NAMES: [(&'static str, Self); 16] = [
+        ("major", Self::Major),
+        ("ionian", Self::Major),
+        ("dorian", Self::Dorian),
+        ("phrygian", Self::Phrygian),
+        ("lydian", Self::Lydian),
+        ("mixolydian", Self::Mixolydian),
+        ("minor", Self::Minor),
+        ("aeolian", Self::Minor),
+        ("locrian", Self::Locrian),
+        ("harmonic_minor", Self::HarmonicMinor),
+        ("melodic_minor", Self::MelodicMinor),
+        ("major_pentatonic", Self::MajorPentatonic),
+        ("minor_pentatonic", Self::MinorPentatonic),
+        ("blues", Self::Blues),
+        ("whole_tone", Self::WholeTone),
+        ("diminished", Self::Diminished),
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(candidate, _)| *candidate == name)
+            .map(|(_, kind)| kind.clone())
+    }
+
+    /// Creates a scale kind from intervals above the root, which are
+    /// wrapped into an octave. Returns `None` if there are none.
+    pub fn custom(intervals: &[i32]) -> Option<Self> {
+        let mut intervals: Vec<_> = intervals
+            .iter()
+            .map(|interval| interval.rem_euclid(12))
+            .collect();
+        intervals.sort();
+        intervals.dedup();
+
+        (!intervals.is_empty()).then_some(Self::Custom(intervals))
+    }
+
+    /// Returns the name of a named kind.
+    pub fn name(&self) -> Option<&'static str> {
+        Self::NAMES
+            .iter()
+            .find(|(_, kind)| kind == self)
+            .map(|(name, _)| *name)
+    }
+
+    /// Returns intervals of the scale tones in semitones above the root.
+    pub fn intervals(&self) -> &[i32] {
+        match self {
+            Self::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Self::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            Self::Phrygian => &[0, 1, 3, 5, 7, 8, 10],
+            Self::Lydian => &[0, 2, 4, 6, 7, 9, 11],
+            Self::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            Self::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Self::Locrian => &[0, 1, 3, 5, 6, 8, 10],
+            Self::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            Self::MelodicMinor => &[0, 2, 3, 5, 7, 9, 11],
+            Self::MajorPentatonic => &[0, 2, 4, 7, 9],
+            Self::MinorPentatonic => &[0, 3, 5, 7, 10],
+            Self::Blues => &[0, 3, 5, 6, 7, 10],
+            Self::WholeTone => &[0, 2, 4, 6, 8, 10],
+            Self::Diminished => &[0, 2, 3, 5, 6, 8, 9, 11],
+            Self::Custom(intervals) => intervals,
+        }
+    }
+}
+
+/// Represents a scale starting at a root note, e.g. `c4` dorian.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Scale {
+    pub root: Note,
+    pub kind: ScaleKind,
+}
+
+impl Scale {
+    pub fn new(root: Note, kind: ScaleKind) -> Self {
+        Self { root, kind }
+    }
+
+    /// Returns the note of a scale degree. Degrees start from `1` (the
+    /// root) and continue into the next octaves, so `8` is the root an
+    /// octave higher in a seven-tone scale. Negative degrees go down from
+    /// the root: `-1` is the tone right below it. Degree `0` doesn't
+    /// exist.
+    pub fn degree(&self, degree: i64) -> Option<Note> {
+        let index = match degree {
+            0 => return None,
+            1.. => degree - 1,
+            _ => degree,
+        };
+
+        let intervals = self.kind.intervals();
+        let count = intervals.len() as i64;
+        let octave = i32::try_from(index.div_euclid(count)).ok()?;
+        let interval = intervals[index.rem_euclid(count) as usize];
+
+        let midi = self
+            .root
+            .midi()
+            .checked_add(octave.checked_mul(12)?)?
+            .checked_add(interval)?;
+
+        Note::from_midi(midi)
+    }
+
+    /// Returns `true` if a pitch class is one of the scale tones.
+    pub fn contains(&self, pitch_class: PitchClass) -> bool {
+        let interval =
+            (pitch_class.semitones() - self.root.pitch_class().semitones()).rem_euclid(12);
+
+        self.kind.intervals().contains(&interval)
+    }
+
+    /// Returns the scale tone nearest to a note. Notes halfway between two
+    /// tones are moved down.
+    pub fn quantize(&self, note: Note) -> Note {
+        (0..=6)
+            .flat_map(|distance| [note.shift(-distance), note.shift(distance)])
+            .find(|candidate| self.contains(candidate.pitch_class()))
+            .unwrap_or(note)
+    }
+}
+
+impl Display for Scale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind.name() {
+            Some(name) => write!(f, "{} {name}", self.root),
+            None => {
+                write!(f, "{} [", self.root)?;
+                for (index, interval) in self.kind.intervals().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{interval}")?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
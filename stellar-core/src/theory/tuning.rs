@@ -0,0 +1,410 @@
+//! Tunings, which give notes their frequencies: equal divisions of the
+//! octave, just intonation, and scales loaded from Scala files.
+//!
+//! A tuning is stored the way Scala stores it. A [`Tuning`] lists the
+//! pitches of scale degrees in cents, the last one being the period the
+//! scale repeats at, and a [`KeyboardMapping`] assigns scale degrees to
+//! MIDI note numbers and tunes one of them to a reference frequency.
+//!
+//! Scale files (`.scl`) start with a description and the number of
+//! pitches, followed by the pitches, which are cents if they contain a
+//! period, and ratios otherwise:
+//!
+//! ```text
+//! ! Lines starting with '!' are comments.
+//! Pythagorean pentatonic
+//! 5
+//! 9/8
+//! 81/64
+//! 3/2
+//! 27/16
+//! 2/1
+//! ```
+//!
+//! Keyboard mapping files (`.kbm`) contain the size of the mapping, the
+//! first and the last mapped note, the note of degree `0`, the reference
+//! note and its frequency, the degree of the period, and then a degree
+//! (or `x`, if the key is unmapped) for every key of the mapping.
+
+use std::{fmt::Display, fs, path::Path};
+
+use super::note::{Note, A4_FREQUENCY};
+
+/// Number of cents in an octave.
+const CENTS_PER_OCTAVE: f64 = 1200.0;
+
+/// Largest number of pitches or keys a tuning may have.
+const MAX_TUNING_SIZE: usize = 1024;
+
+/// Represents the frequencies of notes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tuning {
+    /// Name of the tuning, like `19-edo`.
+    pub name: String,
+    /// Pitches of the scale degrees after the first one in cents above
+    /// it. The last pitch is the period.
+    pub pitches: Vec<f64>,
+    pub mapping: KeyboardMapping,
+}
+
+/// Assigns scale degrees to notes, following the Scala format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyboardMapping {
+    /// Lowest and highest mapped note. Notes outside have no frequency.
+    pub first: Note,
+    pub last: Note,
+    /// Note of the scale degree `0`, where the mapping starts.
+    pub middle: Note,
+    /// Note tuned to the reference frequency.
+    pub reference: Note,
+    /// Frequency of the reference note in hertz.
+    pub frequency: f64,
+    /// Degree the mapping moves by each time its keys run out.
+    pub period: i64,
+    /// Degree of every key of the mapping, or `None` if the key is left
+    /// unmapped. An empty list maps every note to the next degree.
+    pub keys: Vec<Option<i64>>,
+}
+
+impl KeyboardMapping {
+    /// Creates a mapping of every note to the next scale degree, which
+    /// starts at `middle` and tunes it to `frequency`.
+    pub fn linear(middle: Note, frequency: f64) -> Self {
+        Self {
+            first: Note::from_midi(0).expect("MIDI notes are in range"),
+            last: Note::from_midi(127).expect("MIDI notes are in range"),
+            middle,
+            reference: middle,
+            frequency,
+            period: 0,
+            keys: Vec::new(),
+        }
+    }
+
+    /// Parses a keyboard mapping file.
+    pub fn parse(source: &str) -> Result<Self, TuningError> {
+        let mut lines = lines(source);
+
+        let size: i32 = field(&mut lines, "size")?;
+        let first = note_field(&mut lines, "first note")?;
+        let last = note_field(&mut lines, "last note")?;
+        let middle = note_field(&mut lines, "middle note")?;
+        let reference = note_field(&mut lines, "reference note")?;
+        let frequency: f64 = field(&mut lines, "frequency")?;
+        if !(frequency.is_finite() && frequency > 0.0) {
+            return Err(TuningError::InvalidFrequency { frequency });
+        }
+        let period: i32 = field(&mut lines, "period")?;
+
+        let size = usize::try_from(size)
+            .ok()
+            .filter(|size| *size <= MAX_TUNING_SIZE)
+            .ok_or(TuningError::InvalidSize)?;
+        let mut keys = Vec::with_capacity(size);
+        for (line, text) in lines.take(size) {
+            keys.push(match first_word(text) {
+                "x" => None,
+                degree => Some(
+                    degree
+                        .parse()
+                        .map_err(|_| TuningError::InvalidLine { line })?,
+                ),
+            });
+        }
+        // Keys left out of the file are unmapped.
+        keys.resize(size, None);
+
+        Ok(Self {
+            first,
+            last,
+            middle,
+            reference,
+            frequency,
+            period: period.into(),
+            keys,
+        })
+    }
+
+    /// Returns the scale degree of a note, or `None` if it's unmapped.
+    /// `size` is the number of pitches of the scale.
+    fn degree(&self, note: Note, size: usize) -> Option<i64> {
+        if note < self.first || note > self.last {
+            return None;
+        }
+
+        let offset = i64::from(self.middle.interval(note));
+        if self.keys.is_empty() {
+            return Some(offset);
+        }
+
+        let length = self.keys.len() as i64;
+        let key = self.keys[offset.rem_euclid(length) as usize]?;
+        let period = match self.period {
+            0 => size as i64,
+            period => period,
+        };
+
+        Some(offset.div_euclid(length) * period + key)
+    }
+}
+
+impl Tuning {
+    /// Creates a tuning from pitches of the scale degrees and a mapping.
+    /// Fails if a pitch is not a finite number, or if the reference note
+    /// is unmapped.
+    pub fn new(
+        name: String,
+        pitches: Vec<f64>,
+        mapping: KeyboardMapping,
+    ) -> Result<Self, TuningError> {
+        if pitches.is_empty() || pitches.len() > MAX_TUNING_SIZE {
+            return Err(TuningError::InvalidSize);
+        }
+        if let Some(pitch) = pitches.iter().find(|pitch| !pitch.is_finite()) {
+            return Err(TuningError::InvalidPitch { pitch: *pitch });
+        }
+        if !(mapping.frequency.is_finite() && mapping.frequency > 0.0) {
+            return Err(TuningError::InvalidFrequency {
+                frequency: mapping.frequency,
+            });
+        }
+
+        let tuning = Self {
+            name,
+            pitches,
+            mapping,
+        };
+        if tuning.cents(tuning.mapping.reference).is_none() {
+            return Err(TuningError::UnmappedReference);
+        }
+
+        Ok(tuning)
+    }
+
+    /// Creates an equal division of the octave into `steps` steps, which
+    /// tunes `reference` to `frequency`.
+    pub fn equal(steps: usize, reference: Note, frequency: f64) -> Result<Self, TuningError> {
+        if steps == 0 || steps > MAX_TUNING_SIZE {
+            return Err(TuningError::InvalidSize);
+        }
+
+        let step = CENTS_PER_OCTAVE / steps as f64;
+        let pitches = (1..=steps)
+            .map(|step_count| step_count as f64 * step)
+            .collect();
+
+        Self::new(
+            format!("{steps}-edo"),
+            pitches,
+            KeyboardMapping::linear(reference, frequency),
+        )
+    }
+
+    /// Creates a just intonation scale from frequency ratios of its
+    /// degrees to the first one, which starts at `reference` tuned to
+    /// `frequency`. The last ratio is the period, usually `2`.
+    pub fn just(ratios: &[f64], reference: Note, frequency: f64) -> Result<Self, TuningError> {
+        let pitches = ratios
+            .iter()
+            .map(|ratio| ratio_cents(*ratio))
+            .collect::<Result<_, _>>()?;
+
+        Self::new(
+            "just".to_owned(),
+            pitches,
+            KeyboardMapping::linear(reference, frequency),
+        )
+    }
+
+    /// Parses a scale file, whose degree `0` is `c4` at its frequency in
+    /// twelve-tone equal temperament, unless a mapping is given.
+    pub fn parse_scl(source: &str, mapping: Option<KeyboardMapping>) -> Result<Self, TuningError> {
+        // The description may be empty, so it's the only line read as is.
+        let mut lines = source
+            .lines()
+            .enumerate()
+            .map(|(index, text)| (index + 1, text.trim()))
+            .filter(|(_, text)| !text.starts_with('!'));
+
+        let (_, description) = lines.next().ok_or(TuningError::MissingField {
+            name: "description",
+        })?;
+        let mut lines = lines.filter(|(_, text)| !text.is_empty());
+
+        let (line, count) = lines
+            .next()
+            .ok_or(TuningError::MissingField { name: "size" })?;
+        let count: usize = first_word(count)
+            .parse()
+            .map_err(|_| TuningError::InvalidLine { line })?;
+        if count > MAX_TUNING_SIZE {
+            return Err(TuningError::InvalidSize);
+        }
+
+        let mut pitches = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (line, text) = lines
+                .next()
+                .ok_or(TuningError::MissingField { name: "pitch" })?;
+
+            pitches.push(parse_pitch(first_word(text)).ok_or(TuningError::InvalidLine { line })??);
+        }
+
+        let mapping = mapping.unwrap_or_else(|| {
+            let c4 = Note::from_midi(60).expect("MIDI notes are in range");
+            KeyboardMapping::linear(c4, c4.frequency())
+        });
+
+        Self::new(description.to_owned(), pitches, mapping)
+    }
+
+    /// Reads a scale file and optionally a keyboard mapping file.
+    pub fn load(scale: &Path, mapping: Option<&Path>) -> Result<Self, TuningError> {
+        let read = |path: &Path| {
+            fs::read_to_string(path).map_err(|_| TuningError::NotFound {
+                path: path.display().to_string(),
+            })
+        };
+
+        let mapping = match mapping {
+            Some(path) => Some(KeyboardMapping::parse(&read(path)?)?),
+            None => None,
+        };
+
+        Self::parse_scl(&read(scale)?, mapping)
+    }
+
+    /// Returns the frequency of a note in hertz, or `None` if the note
+    /// isn't mapped to a scale degree.
+    pub fn frequency(&self, note: Note) -> Option<f64> {
+        let cents = self.cents(note)? - self.cents(self.mapping.reference)?;
+
+        Some(self.mapping.frequency * 2f64.powf(cents / CENTS_PER_OCTAVE))
+    }
+
+    /// Returns the pitch of a note in cents above the scale degree `0`.
+    fn cents(&self, note: Note) -> Option<f64> {
+        let degree = self.mapping.degree(note, self.pitches.len())?;
+
+        let size = self.pitches.len() as i64;
+        let period = self.pitches[self.pitches.len() - 1];
+        let pitch = match degree.rem_euclid(size) {
+            0 => 0.0,
+            index => self.pitches[index as usize - 1],
+        };
+
+        Some(degree.div_euclid(size) as f64 * period + pitch)
+    }
+}
+
+impl Default for Tuning {
+    /// Twelve-tone equal temperament with `a4` at 440 Hz.
+    fn default() -> Self {
+        let a4 = Note::from_midi(69).expect("MIDI notes are in range");
+
+        Self::equal(12, a4, A4_FREQUENCY).expect("default tuning is valid")
+    }
+}
+
+impl Display for Tuning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}, {} = {} Hz",
+            self.name, self.mapping.reference, self.mapping.frequency
+        )
+    }
+}
+
+/// Returns non-empty lines, which aren't comments, along with their line
+/// numbers.
+fn lines(source: &str) -> impl Iterator<Item = (usize, &str)> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(index, text)| (index + 1, text.trim()))
+        .filter(|(_, text)| !text.is_empty() && !text.starts_with('!'))
+}
+
+/// Parses the first word of the next line of a keyboard mapping file.
+fn field<'s, T: std::str::FromStr>(
+    lines: &mut impl Iterator<Item = (usize, &'s str)>,
+    name: &'static str,
+) -> Result<T, TuningError> {
+    let (line, text) = lines.next().ok_or(TuningError::MissingField { name })?;
+
+    first_word(text)
+        .parse()
+        .map_err(|_| TuningError::InvalidLine { line })
+}
+
+/// Parses a MIDI note number of a keyboard mapping file, which is in range
+/// `0..=127`.
+fn note_field<'s>(
+    lines: &mut impl Iterator<Item = (usize, &'s str)>,
+    name: &'static str,
+) -> Result<Note, TuningError> {
+    let midi: i32 = field(lines, name)?;
+    if !(0..=127).contains(&midi) {
+        return Err(TuningError::InvalidNote { name });
+    }
+
+    Ok(Note::from_midi(midi).expect("MIDI notes are in range"))
+}
+
+/// Returns the first word of a line, ignoring whatever follows it.
+fn first_word(text: &str) -> &str {
+    text.split_whitespace().next().unwrap_or("")
+}
+
+/// Parses a pitch of a scale file: cents (`701.955`), a ratio (`3/2`) or a
+/// whole number (`2`). Returns `None` if it isn't a number.
+fn parse_pitch(text: &str) -> Option<Result<f64, TuningError>> {
+    if text.contains('.') {
+        return text.parse().ok().map(Ok);
+    }
+
+    let (numerator, denominator) = text.split_once('/').unwrap_or((text, "1"));
+    let numerator: u64 = numerator.parse().ok()?;
+    let denominator: u64 = denominator.parse().ok()?;
+
+    Some(ratio_cents(numerator as f64 / denominator as f64))
+}
+
+/// Converts a frequency ratio into cents.
+fn ratio_cents(ratio: f64) -> Result<f64, TuningError> {
+    if !(ratio.is_finite() && ratio > 0.0) {
+        return Err(TuningError::InvalidRatio { ratio });
+    }
+
+    Ok(CENTS_PER_OCTAVE * ratio.log2())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TuningError {
+    NotFound {
+        path: String,
+    },
+    InvalidLine {
+        line: usize,
+    },
+    MissingField {
+        name: &'static str,
+    },
+    /// Number of pitches or keys, which is zero or too large.
+    InvalidSize,
+    InvalidPitch {
+        pitch: f64,
+    },
+    InvalidRatio {
+        ratio: f64,
+    },
+    InvalidFrequency {
+        frequency: f64,
+    },
+    UnmappedReference,
+    /// Note number of a keyboard mapping field, which is out of range.
+    InvalidNote {
+        name: &'static str,
+    },
+}
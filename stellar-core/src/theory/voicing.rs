@@ -0,0 +1,179 @@
+//! Operations on voicings: notes of a chord placed in particular octaves.
+//! Every operation returns the notes ordered from the lowest one.
+
+use super::{
+    chord::Chord,
+    note::{Note, PitchClass},
+};
+
+/// Largest number of voices [`voice_lead`] can move.
+pub const MAX_VOICES: usize = 8;
+
+/// Inverts a voicing `n` times, moving its lowest note an octave up each
+/// time, so the first inversion of `c4 e4 g4` is `e4 g4 c5`. Negative `n`
+/// moves the highest notes an octave down instead. Returns `None` if the
+/// notes are moved outside of the range of notes.
+pub fn invert(notes: &[Note], n: i32) -> Option<Vec<Note>> {
+    rotate(notes, n, Note::transpose)
+}
+
+/// Inverts the tones of a chord, which only has a few inversions, so they
+/// may end up slightly outside of the range of notes.
+pub(crate) fn invert_chord(notes: &[Note], n: u8) -> Vec<Note> {
+    rotate(notes, n.into(), |note, semitones| {
+        Some(note.shift(semitones))
+    })
+    .expect("shifting notes doesn't fail")
+}
+
+/// Inverts a voicing, moving its notes with `transpose`.
+fn rotate(
+    notes: &[Note],
+    n: i32,
+    transpose: impl Fn(Note, i32) -> Option<Note>,
+) -> Option<Vec<Note>> {
+    let mut notes = sorted(notes);
+    if notes.is_empty() {
+        return Some(notes);
+    }
+
+    // Inverting a voicing as many times as it has notes moves it an octave.
+    let count = i32::try_from(notes.len()).ok()?;
+    let octaves = n.div_euclid(count);
+    let moved = n.rem_euclid(count) as usize;
+
+    notes.rotate_left(moved);
+    let start = notes.len() - moved;
+    for note in &mut notes[start..] {
+        *note = transpose(*note, 12)?;
+    }
+
+    let semitones = octaves.checked_mul(12)?;
+
+    notes
+        .into_iter()
+        .map(|note| transpose(note, semitones))
+        .collect()
+}
+
+/// Moves the second highest note of a voicing an octave down, which
+/// spreads close voicings of four-note chords. Returns `None` if the note
+/// is moved below the range of notes.
+pub fn drop2(notes: &[Note]) -> Option<Vec<Note>> {
+    let mut notes = sorted(notes);
+
+    if notes.len() >= 2 {
+        let index = notes.len() - 2;
+        notes[index] = notes[index].transpose(-12)?;
+        notes.sort();
+    }
+
+    Some(notes)
+}
+
+/// Voices chords for a given number of voices between `low` and `high`,
+/// picking inversions and octaves, so that the voices move as little as
+/// possible from one chord to the next. The first chord is placed in the
+/// middle of the range. Returns `None` if a chord can't be voiced.
+pub fn voice_lead(
+    chords: &[Chord],
+    voices: usize,
+    low: Note,
+    high: Note,
+) -> Option<Vec<Vec<Note>>> {
+    let middle = (low.midi() + high.midi()) / 2;
+    let mut previous: Option<Vec<Note>> = None;
+
+    chords
+        .iter()
+        .map(|chord| {
+            let best = voicings(chord, voices, low, high)
+                .into_iter()
+                .min_by_key(|voicing| {
+                    let movement = previous.as_ref().map_or(0, |previous| {
+                        previous
+                            .iter()
+                            .zip(voicing)
+                            .map(|(from, to)| from.interval(*to).abs())
+                            .sum()
+                    });
+                    let spread: i32 = voicing
+                        .iter()
+                        .map(|note| (note.midi() - middle).abs())
+                        .sum();
+
+                    (movement, spread)
+                })?;
+
+            previous = Some(best.clone());
+            Some(best)
+        })
+        .collect()
+}
+
+/// Returns every voicing of a chord with a given number of voices in a
+/// range. Voicings contain as many chord tones as there are voices,
+/// leaving out the fifth first, keep the bass of inverted chords, and
+/// don't leave more than an octave between neighbouring upper voices.
+fn voicings(chord: &Chord, voices: usize, low: Note, high: Note) -> Vec<Vec<Note>> {
+    let mut tones: Vec<PitchClass> = chord
+        .quality
+        .intervals()
+        .iter()
+        .map(|interval| chord.root.shift(*interval).pitch_class())
+        .collect();
+    if tones.len() > 3 {
+        let fifth = tones.remove(2);
+        tones.push(fifth);
+    }
+    tones.truncate(voices);
+
+    let bass = (chord.inversion > 0).then(|| chord.notes()[0].pitch_class());
+
+    let mut result = Vec::new();
+    collect_voicings(
+        &chord.voicing(low, high),
+        voices,
+        &mut Vec::new(),
+        &mut |voicing| {
+            let complete = tones
+                .iter()
+                .all(|tone| voicing.iter().any(|note| note.pitch_class() == *tone));
+            let bass = bass.is_none_or(|bass| voicing[0].pitch_class() == bass);
+
+            if complete && bass {
+                result.push(voicing.to_vec());
+            }
+        },
+    );
+
+    result
+}
+
+fn collect_voicings(
+    notes: &[Note],
+    voices: usize,
+    voicing: &mut Vec<Note>,
+    visit: &mut dyn FnMut(&[Note]),
+) {
+    if voicing.len() == voices {
+        return visit(voicing);
+    }
+
+    for (index, note) in notes.iter().enumerate() {
+        if voicing.len() >= 2 && voicing[voicing.len() - 1].interval(*note) > 12 {
+            break;
+        }
+
+        voicing.push(*note);
+        collect_voicings(&notes[index + 1..], voices, voicing, visit);
+        voicing.pop();
+    }
+}
+
+fn sorted(notes: &[Note]) -> Vec<Note> {
+    let mut notes = notes.to_vec();
+    notes.sort();
+
+    notes
+}
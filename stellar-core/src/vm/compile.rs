@@ -0,0 +1,570 @@
+use std::collections::HashMap;
+
+use crate::{
+    analysis::{
+        fold::{fold, Constants},
+        resolve::{Resolution, ScopeKind, SymbolId, SymbolKind, Target},
+    },
+    ctx::{
+        builtins::Builtin,
+        host::Host,
+        interpreter::constant_value,
+        properties::{is_evaluated, Setting, PARAMS},
+        value::{Sequence, Value},
+    },
+    syntax::{
+        ast::{BinaryOperatorKind, Expression, PrefixOperatorKind, Statement},
+        location::{Location as SourceLocation, Span, Spanned},
+        string_id::StringId,
+        token::{Identifier, Unit},
+    },
+    theory::parse_name,
+};
+
+use super::{
+    Call, Chunk, Index, Instruction, Location, PolyBlock, Program, TempoChange, TempoRamp,
+    Variable, WithBlock,
+};
+
+/// Compiles a resolved program into bytecode.
+pub fn compile(statements: &[Statement], resolution: &Resolution) -> Program {
+    compile_with(statements, resolution, &Host::default())
+}
+
+/// Compiles a program, which calls functions and reads globals of a host.
+/// Values of the globals are read when the program is run.
+pub fn compile_with(statements: &[Statement], resolution: &Resolution, host: &Host) -> Program {
+    let mut compiler = Compiler {
+        resolution,
+        host,
+        constants: fold(statements, resolution),
+        program: Program::default(),
+        variables: HashMap::new(),
+        externals: HashMap::new(),
+        chunk: 0,
+    };
+
+    compiler.new_chunk(None);
+    compiler.declare(statements, 0);
+
+    compiler.statements(statements);
+    compiler.emit(Instruction::Return, end_of(statements));
+
+    compiler.program
+}
+
+/// Returns an empty span at the end of statements, which `return`
+/// instructions are attributed to.
+fn end_of(statements: &[Statement]) -> Span {
+    let end = statements
+        .last()
+        .map_or(SourceLocation::sof(), |statement| statement.span().end());
+
+    Span::new(end, end)
+}
+
+struct Compiler<'r> {
+    resolution: &'r Resolution,
+    host: &'r Host,
+    constants: Constants,
+    program: Program,
+    /// Indices of variables in the program.
+    variables: HashMap<SymbolId, u32>,
+    /// Indices of variables holding globals of the host.
+    externals: HashMap<StringId, u32>,
+    /// Chunk being compiled.
+    chunk: usize,
+}
+
+impl Compiler<'_> {
+    fn new_chunk(&mut self, name: Option<Identifier>) -> usize {
+        self.program.chunks.push(Chunk {
+            name: name.map(|name| name.name()),
+            code: Vec::new(),
+            spans: Vec::new(),
+            locals: 0,
+        });
+
+        self.program.chunks.len() - 1
+    }
+
+    /// Creates chunks of sequences and gives every variable its place
+    /// before any code is compiled, since variables may be used before
+    /// their definitions in the source.
+    fn declare(&mut self, statements: &[Statement], chunk: usize) {
+        for statement in statements {
+            match statement.unattributed() {
+                Statement::Let { name, .. } => {
+                    let Some(id) = self.resolution.definition(name) else {
+                        continue;
+                    };
+
+                    let scope = self.resolution.symbol(id).scope;
+                    let location = match self.resolution.scope(scope).kind {
+                        ScopeKind::Global => {
+                            self.program.globals += 1;
+                            Location::Global(self.program.globals - 1)
+                        }
+                        _ => {
+                            let locals = &mut self.program.chunks[chunk].locals;
+                            *locals += 1;
+                            Location::Local {
+                                chunk,
+                                slot: *locals - 1,
+                            }
+                        }
+                    };
+
+                    self.variables
+                        .insert(id, self.program.variables.len() as u32);
+                    self.program.variables.push(Variable {
+                        name: name.name(),
+                        location,
+                    });
+                }
+                Statement::Sequence { name, block } => {
+                    let sequence = self.new_chunk(Some(*name));
+                    if let Some(id) = self.resolution.definition(name) {
+                        self.program.sequences.insert(id, sequence);
+                    }
+
+                    self.declare(&block.statements, sequence);
+                }
+                Statement::With { block, .. }
+                | Statement::Poly { block, .. }
+                | Statement::Repeat { block, .. } => self.declare(&block.statements, chunk),
+                _ => {}
+            }
+        }
+    }
+
+    /// Adds an instruction to the current chunk and returns its position.
+    fn emit(&mut self, instruction: Instruction, span: Span) -> u32 {
+        let chunk = &mut self.program.chunks[self.chunk];
+        chunk.code.push(instruction);
+        chunk.spans.push(span);
+
+        chunk.code.len() as u32 - 1
+    }
+
+    /// Returns the position of the next instruction of the current chunk.
+    fn position(&self) -> u32 {
+        self.program.chunks[self.chunk].code.len() as u32
+    }
+
+    /// Points a jump emitted earlier to the next instruction.
+    fn patch(&mut self, jump: u32) {
+        let target = self.position();
+
+        match &mut self.program.chunks[self.chunk].code[jump as usize] {
+            Instruction::Loop(position) | Instruction::Jump(position) => *position = target,
+            instruction => unreachable!("{instruction:?} is not a jump"),
+        }
+    }
+
+    fn constant(&mut self, value: Value, span: Span) {
+        self.program.constants.push(value);
+        let index = self.program.constants.len() as u32 - 1;
+
+        self.emit(Instruction::Constant(index), span);
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Wait { expression } => {
+                self.expression(expression);
+                self.emit(Instruction::Wait, expression.span());
+            }
+            Statement::Play {
+                expression,
+                parallel,
+            } => {
+                self.expression(expression);
+
+                let instruction = match parallel {
+                    true => Instruction::Spawn,
+                    false => Instruction::Play,
+                };
+                self.emit(instruction, expression.span());
+            }
+            Statement::Sequence { name, block } => {
+                let Some(id) = self.resolution.definition(name) else {
+                    return;
+                };
+
+                let enclosing = self.chunk;
+                self.chunk = self.program.sequences[&id];
+
+                self.statements(&block.statements);
+                self.emit(Instruction::Return, end_of(&block.statements));
+
+                self.chunk = enclosing;
+            }
+            Statement::With { properties, block } => {
+                // Blocks setting a property twice fail before any value is
+                // evaluated.
+                let settings = Setting::check_unique(properties).and_then(|()| {
+                    properties
+                        .iter()
+                        .filter(|property| !is_evaluated(&property.name.name().resolve()))
+                        .map(|property| Ok((Setting::parse(property)?, property.value.span())))
+                        .collect()
+                });
+
+                let mut evaluated = Vec::new();
+                if settings.is_ok() {
+                    for property in properties {
+                        if is_evaluated(&property.name.name().resolve()) {
+                            self.expression(&property.value);
+                            evaluated.push((property.name, property.value.span()));
+                        }
+                    }
+                }
+
+                let mut params = Vec::new();
+                for property in properties {
+                    let name = property.name.name();
+                    if !PARAMS.contains(&name.resolve().as_str()) {
+                        continue;
+                    }
+
+                    match &property.value {
+                        // Names, such as `synth: pad`, are not variables.
+                        Expression::Identifier(identifier) => {
+                            let value = Value::String {
+                                string: identifier.name().resolve(),
+                            };
+                            self.constant(value, identifier.span());
+                        }
+                        value => self.expression(value),
+                    }
+
+                    params.push((name, property.span()));
+                }
+
+                self.program.with_blocks.push(WithBlock {
+                    settings,
+                    evaluated,
+                    params,
+                });
+                let index = self.program.with_blocks.len() as u32 - 1;
+
+                self.emit(Instruction::EnterWith(index), statement.span());
+                self.statements(&block.statements);
+                self.emit(Instruction::ExitWith(index), end_of(&block.statements));
+            }
+            Statement::Let { name, value } => {
+                self.expression(value);
+
+                if let Some(id) = self.resolution.definition(name) {
+                    self.emit(Instruction::Define(self.variables[&id]), name.span());
+                } else {
+                    self.emit(Instruction::Pop, name.span());
+                }
+            }
+            Statement::Poly { arguments, block } => {
+                for argument in arguments {
+                    self.expression(&argument.value);
+                    self.emit(Instruction::Count, argument.value.span());
+                }
+
+                // Voices are rendered by the `poly` instruction, so they are
+                // jumped over.
+                let jump = self.emit(Instruction::Jump(0), statement.span());
+
+                let mut voices = Vec::new();
+                for voice in &block.statements {
+                    voices.push(self.position());
+
+                    self.statement(voice);
+                    self.emit(Instruction::Return, voice.span());
+                }
+
+                self.patch(jump);
+
+                self.program.poly_blocks.push(PolyBlock {
+                    voices,
+                    counts: arguments.len(),
+                });
+                let index = self.program.poly_blocks.len() as u32 - 1;
+                self.emit(Instruction::Poly(index), statement.span());
+            }
+            Statement::Tempo { bpm, ramp } => {
+                self.expression(bpm);
+
+                if let Some(ramp) = ramp {
+                    self.expression(&ramp.target);
+                    self.expression(&ramp.length);
+                }
+
+                self.program.tempo_changes.push(TempoChange {
+                    bpm: bpm.span(),
+                    ramp: ramp.as_ref().map(|ramp| TempoRamp {
+                        target: ramp.target.span(),
+                        length: ramp.length.span(),
+                        unit: ramp.unit,
+                        curve: ramp.curve,
+                    }),
+                });
+                let index = self.program.tempo_changes.len() as u32 - 1;
+                self.emit(Instruction::Tempo(index), statement.span());
+            }
+            Statement::Tuning { tuning } => {
+                self.expression(tuning);
+                self.emit(Instruction::Tuning, statement.span());
+            }
+            Statement::Repeat {
+                count: Some(count),
+                block,
+            } => {
+                self.expression(count);
+                self.emit(Instruction::Count, count.span());
+
+                let start = self.emit(Instruction::Loop(0), statement.span());
+                self.statements(&block.statements);
+                self.emit(Instruction::Jump(start), statement.span());
+
+                self.patch(start);
+            }
+            Statement::Repeat { count: None, block } => {
+                self.emit(Instruction::Mark, statement.span());
+
+                let start = self.position();
+                self.statements(&block.statements);
+                self.emit(Instruction::Endless(start), statement.span());
+            }
+            Statement::Expression(expression) => {
+                self.expression(expression);
+                self.emit(Instruction::Pop, expression.span());
+            }
+            Statement::Attributed { statement, .. } => self.statement(statement),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        let span = expression.span();
+
+        // Pure expressions are evaluated at compile time.
+        if let Some(constant) = self.constants.get(expression) {
+            return self.constant(constant_value(constant), span);
+        }
+
+        match expression {
+            Expression::Integer { value, .. } => {
+                self.constant(Value::Integer { value: *value }, span)
+            }
+            Expression::Float { value, .. } => self.constant(Value::Float { float: *value }, span),
+            Expression::Quantity { value, unit, .. } => {
+                let value = match unit {
+                    Unit::Percent => Value::Float {
+                        float: value / 100.0,
+                    },
+                    Unit::Milliseconds => Value::Duration {
+                        seconds: value / 1000.0,
+                    },
+                    Unit::Seconds => Value::Duration { seconds: *value },
+                };
+
+                self.constant(value, span);
+            }
+            Expression::String { value, .. } => {
+                let value = Value::String {
+                    string: value.resolve(),
+                };
+
+                self.constant(value, span);
+            }
+            Expression::Bool { value, .. } => self.constant(Value::Bool { value: *value }, span),
+            Expression::Identifier(identifier) => self.identifier(identifier),
+            Expression::Degree { degree, .. } => {
+                let degree = i32::try_from(*degree).unwrap_or(i32::MAX);
+                self.emit(Instruction::Degree(degree), span);
+            }
+            Expression::List { expressions, .. } => {
+                for expression in expressions {
+                    self.expression(expression);
+                }
+
+                self.emit(Instruction::List(expressions.len() as u32), span);
+            }
+            Expression::LoadSample { sample, .. } => {
+                self.expression(sample);
+                self.emit(Instruction::LoadSample, sample.span());
+            }
+            Expression::Prefix { operator, operand } => {
+                self.expression(operand);
+
+                let instruction = match operator.kind {
+                    PrefixOperatorKind::Minus => Instruction::Negate,
+                    PrefixOperatorKind::Exclamation => Instruction::Not,
+                };
+                self.emit(instruction, span);
+            }
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } if operator.kind == BinaryOperatorKind::Assign => {
+                let variable = match left.as_ref() {
+                    Expression::Identifier(identifier) => self
+                        .resolution
+                        .symbol_of(identifier)
+                        .and_then(|id| self.variables.get(&id).copied()),
+                    _ => None,
+                };
+
+                match variable {
+                    Some(variable) => {
+                        self.expression(right);
+                        self.emit(Instruction::Assign(variable), left.span());
+                    }
+                    None => {
+                        self.emit(Instruction::InvalidAssignment, left.span());
+                    }
+                }
+            }
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                self.expression(left);
+                self.expression(right);
+                self.emit(Instruction::Binary(operator.kind), span);
+            }
+            Expression::Call {
+                callee, arguments, ..
+            } => {
+                self.expression(callee);
+
+                for argument in arguments {
+                    self.expression(&argument.value);
+                }
+
+                self.program.calls.push(Call {
+                    names: arguments.iter().map(|argument| argument.name).collect(),
+                    spans: arguments.iter().map(|argument| argument.span()).collect(),
+                    callee: callee.span(),
+                });
+                let index = self.program.calls.len() as u32 - 1;
+                self.emit(Instruction::Call(index), span);
+            }
+            Expression::Index { target, index, .. } => {
+                self.expression(target);
+                self.expression(index);
+
+                self.program.indices.push(Index {
+                    target: target.span(),
+                    index: index.span(),
+                });
+                let index = self.program.indices.len() as u32 - 1;
+                self.emit(Instruction::Index(index), span);
+            }
+        }
+    }
+
+    fn identifier(&mut self, identifier: &Identifier) {
+        let span = identifier.span();
+
+        let value = match self.resolution.target(identifier) {
+            Some(Target::Symbol(id)) => match self.resolution.symbol(id).kind {
+                SymbolKind::Sequence => Some(Value::Sequence {
+                    sequence: Sequence::Defined(id),
+                }),
+                SymbolKind::Let => {
+                    self.emit(Instruction::Load(self.variables[&id]), span);
+                    return;
+                }
+            },
+            Some(Target::Builtin) => {
+                let name = identifier.name().resolve();
+
+                if let Some(function) = Builtin::parse(&name) {
+                    Some(Value::Function { function })
+                } else if let Some(name) = parse_name(&name) {
+                    Some(Value::name(name))
+                } else if self.host.function(identifier.name()).is_some() {
+                    Some(Value::Native {
+                        name: identifier.name(),
+                    })
+                } else if self.host.global(identifier.name()).is_some() {
+                    let index = self.external(identifier.name());
+                    self.emit(Instruction::Load(index), span);
+                    return;
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+
+        match value {
+            Some(value) => self.constant(value, span),
+            None => {
+                self.program.identifiers.push(*identifier);
+                let index = self.program.identifiers.len() as u32 - 1;
+                self.emit(Instruction::Undefined(index), span);
+            }
+        }
+    }
+
+    /// Returns the variable holding a global of the host, giving it a
+    /// global slot when it is used for the first time.
+    fn external(&mut self, name: StringId) -> u32 {
+        if let Some(index) = self.externals.get(&name) {
+            return *index;
+        }
+
+        let slot = self.program.globals;
+        self.program.globals += 1;
+        self.program.externals.push((name, slot));
+
+        let index = self.program.variables.len() as u32;
+        self.program.variables.push(Variable {
+            name,
+            location: Location::Global(slot),
+        });
+        self.externals.insert(name, index);
+
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::resolve::resolve,
+        syntax::{parse::parse, scan::scan},
+        test_snapshots,
+    };
+
+    use super::compile;
+
+    fn render(source: &str) -> String {
+        let statements = parse(scan(source).unwrap()).unwrap();
+
+        compile(&statements, &resolve(&statements)).to_string()
+    }
+
+    test_snapshots!(
+        (
+            channels,
+            "let root = c4\nsequence arp {\n repeat 3 {\n  play root\n  wait 1\n }\n}\nplay! arp\nwith synth: pad {\n play! [e4, g4]\n}\nrepeat 2 {\n wait 1/2\n root = root + 2\n wait 1/2\n}"
+        ),
+        (
+            locals,
+            "sequence a {\n let n = c4\n repeat {\n  let m = n + 1\n  play fast(2, m)\n  n = m\n  wait 1\n }\n}\nplay a"
+        ),
+        (
+            blocks,
+            "with key: d_minor, transpose: -5 {\n poly(3, 4) {\n  play ^1\n  play [^3, ^5]\n }\n}\ntempo 90 -> 120 over 2 bars"
+        ),
+        (errors, "c4 = 1\nplay missing"),
+    );
+}
@@ -0,0 +1,981 @@
+use std::{collections::HashMap, path::PathBuf, rc::Rc};
+
+use crate::{
+    audio::{
+        ctx::DEFAULT_BPM,
+        tempo::{Curve, Ramp, TempoMap},
+    },
+    ctx::{
+        host::Host,
+        interpreter::{
+            self, advance, binary, bind, expect_count, expect_f64, expect_rational, into_pattern,
+            notes, param_change, prefix, transform, wait, Options, Output, RuntimeError, State,
+            MAX_RENDER_DEPTH,
+        },
+        limits::Budget,
+        properties::Setting,
+        scheduler::Scheduler,
+        stdlib,
+        value::{SampleHandle, Sequence, Sound, Value},
+    },
+    ir::{Channel, EventKind, Ir},
+    pattern::{
+        self,
+        rhythm::{poly, DEFAULT_POLY_SPAN},
+        Pattern, BEATS_PER_BAR,
+    },
+    random::Rng,
+    rational::Rational,
+    syntax::{
+        ast::{PrefixOperatorKind, RampCurve, TimeUnit},
+        location::Span,
+        string_id::StringId,
+        token::Identifier,
+    },
+    theory::tuning::Tuning,
+};
+
+use super::{Call, Instruction, Location, Program};
+
+/// Runs a compiled program and returns the events it plays.
+pub fn run(program: &Program, options: &Options) -> Result<Ir, RuntimeError> {
+    run_with(program, options, &Host::default())
+}
+
+/// Runs a program compiled with [`compile_with`](super::compile::compile_with)
+/// for the same host.
+pub fn run_with(program: &Program, options: &Options, host: &Host) -> Result<Ir, RuntimeError> {
+    let mut globals = vec![None; program.globals];
+    for (name, slot) in &program.externals {
+        globals[*slot] = host.global(*name).cloned();
+    }
+
+    let mut machine = Machine {
+        program,
+        host,
+        horizon: options.horizon,
+        globals,
+        samples: HashMap::new(),
+        spawned: Vec::new(),
+        tempo: TempoMap::new(DEFAULT_BPM).expect("default tempo is valid"),
+        tuning: Tuning::default(),
+        rng: Rng::new(options.seed),
+        budget: Budget::new(options.limits),
+        files: options.files.clone(),
+    };
+
+    let state = Rc::new(State::default());
+    let mut main = Fiber::new(
+        Rational::ZERO,
+        Vec::new(),
+        state,
+        Output::Events(Vec::new()),
+        0,
+    );
+    machine.call(&mut main, 0);
+
+    Ok(Ir {
+        channels: machine.schedule(main)?,
+    })
+}
+
+/// Position in a chunk being run.
+struct Frame {
+    chunk: usize,
+    ip: usize,
+    /// Whether the chunk has its own scope, which ends with it. Voices of
+    /// `poly` blocks use the scope of their chunk.
+    scoped: bool,
+}
+
+/// Local variables of a chunk being played.
+#[derive(Clone)]
+struct Scope {
+    chunk: usize,
+    locals: Vec<Option<Value>>,
+}
+
+/// Runs the code of one channel, or renders a sequence into a pattern.
+struct Fiber {
+    cursor: Rational,
+    frames: Vec<Frame>,
+    stack: Vec<Value>,
+    scopes: Vec<Scope>,
+    /// Settings of the `with` blocks being run, innermost last.
+    states: Vec<Rc<State>>,
+    /// Beats, at which the endless `repeat`s being run started their
+    /// current iteration.
+    marks: Vec<Rational>,
+    output: Output,
+    /// Number of sequences rendered into patterns around the fiber.
+    depth: usize,
+}
+
+impl Fiber {
+    fn new(
+        cursor: Rational,
+        scopes: Vec<Scope>,
+        state: Rc<State>,
+        output: Output,
+        depth: usize,
+    ) -> Self {
+        Self {
+            cursor,
+            frames: Vec::new(),
+            stack: Vec::new(),
+            scopes,
+            states: vec![state],
+            marks: Vec::new(),
+            output,
+            depth,
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("stack is not empty")
+    }
+
+    fn state(&self) -> &Rc<State> {
+        self.states.last().expect("fiber has a state")
+    }
+}
+
+struct Machine<'p> {
+    program: &'p Program,
+    host: &'p Host,
+    horizon: Rational,
+    globals: Vec<Option<Value>>,
+    samples: HashMap<StringId, SampleHandle>,
+    /// Channels started by the fiber being run.
+    spawned: Vec<Fiber>,
+    tempo: TempoMap,
+    /// Tuning set by the last `tuning` statement, which `freq` uses.
+    tuning: Tuning,
+    rng: Rng,
+    budget: Budget,
+    /// Directory files are read from, see [`Options::files`].
+    files: Option<PathBuf>,
+}
+
+impl Machine<'_> {
+    /// Runs channels, resuming the one waiting for the earliest beat
+    /// first, until all of them end or reach the horizon.
+    fn schedule(&mut self, main: Fiber) -> Result<Vec<Channel>, RuntimeError> {
+        let mut scheduler = Scheduler::default();
+        let mut channels = Vec::new();
+
+        scheduler.spawn(main.cursor, main);
+        channels.push(Channel::default());
+
+        while let Some((id, mut fiber)) = scheduler.resume() {
+            let wake = self.resume(&mut fiber)?;
+
+            for spawned in std::mem::take(&mut self.spawned) {
+                scheduler.spawn(spawned.cursor, spawned);
+                channels.push(Channel::default());
+            }
+
+            match wake {
+                Some(beat) => scheduler.schedule(id, beat, fiber),
+                None => channels[id.index()] = fiber.output.into_channel(),
+            }
+        }
+
+        Ok(channels)
+    }
+
+    /// Runs a fiber until it waits or ends. Returns the beat to resume it
+    /// at, or `None` if it has ended.
+    fn resume(&mut self, fiber: &mut Fiber) -> Result<Option<Rational>, RuntimeError> {
+        let start = fiber.cursor;
+
+        loop {
+            if fiber.cursor >= self.horizon {
+                return Ok(None);
+            }
+
+            if fiber.cursor > start {
+                return Ok(Some(fiber.cursor));
+            }
+
+            let Some(frame) = fiber.frames.last_mut() else {
+                return Ok(None);
+            };
+
+            let chunk = &self.program.chunks[frame.chunk];
+            let instruction = chunk.code[frame.ip];
+            let span = chunk.spans[frame.ip];
+            frame.ip += 1;
+
+            self.budget.step(span)?;
+            let played = fiber.output.events().len();
+
+            self.execute(instruction, span, fiber)?;
+            self.budget
+                .events(&fiber.output.events()[played..], &self.tempo)?;
+        }
+    }
+
+    /// Runs a fiber, which doesn't play on its own channel, to the end.
+    fn complete(&mut self, fiber: &mut Fiber) -> Result<(), RuntimeError> {
+        while self.resume(fiber)?.is_some() {}
+
+        Ok(())
+    }
+
+    /// Starts running a chunk with a scope of its own.
+    fn call(&mut self, fiber: &mut Fiber, chunk: usize) {
+        fiber.scopes.push(Scope {
+            chunk,
+            locals: vec![None; self.program.chunks[chunk].locals],
+        });
+        fiber.frames.push(Frame {
+            chunk,
+            ip: 0,
+            scoped: true,
+        });
+    }
+
+    fn jump(fiber: &mut Fiber, target: u32) {
+        fiber.frames.last_mut().expect("fiber has a frame").ip = target as usize;
+    }
+
+    fn execute(
+        &mut self,
+        instruction: Instruction,
+        span: Span,
+        fiber: &mut Fiber,
+    ) -> Result<(), RuntimeError> {
+        match instruction {
+            Instruction::Constant(index) => {
+                let value = self.program.constants[index as usize].clone();
+                self.budget.value(&value, span)?;
+
+                fiber.stack.push(value);
+            }
+            Instruction::Load(index) => {
+                let value = self.load(index, fiber, span)?;
+                fiber.stack.push(value);
+            }
+            Instruction::Define(index) => {
+                let value = fiber.pop();
+
+                match self.program.variables[index as usize].location {
+                    Location::Global(slot) => self.globals[slot] = Some(value),
+                    Location::Local { slot, .. } => {
+                        let scope = fiber.scopes.last_mut().expect("fiber has a scope");
+                        scope.locals[slot] = Some(value);
+                    }
+                }
+            }
+            Instruction::Assign(index) => {
+                let value = fiber.stack.last().expect("stack is not empty").clone();
+                let variable = self.program.variables[index as usize];
+
+                let slot = match variable.location {
+                    Location::Global(slot) => self.globals[slot].as_mut(),
+                    Location::Local { chunk, slot } => fiber
+                        .scopes
+                        .iter_mut()
+                        .rev()
+                        .filter(|scope| scope.chunk == chunk)
+                        .find_map(|scope| scope.locals[slot].as_mut()),
+                };
+
+                match slot {
+                    Some(slot) => *slot = value,
+                    None => {
+                        return Err(RuntimeError::UninitializedVariable {
+                            identifier: Identifier::new(variable.name, span),
+                        })
+                    }
+                }
+            }
+            Instruction::InvalidAssignment => {
+                return Err(RuntimeError::InvalidAssignment { span });
+            }
+            Instruction::Undefined(index) => {
+                return Err(RuntimeError::UndefinedName {
+                    identifier: self.program.identifiers[index as usize],
+                });
+            }
+            Instruction::Pop => {
+                fiber.pop();
+            }
+            Instruction::Negate | Instruction::Not => {
+                let operator = match instruction {
+                    Instruction::Negate => PrefixOperatorKind::Minus,
+                    _ => PrefixOperatorKind::Exclamation,
+                };
+
+                let value = prefix(operator, fiber.pop(), span)?;
+                fiber.stack.push(value);
+            }
+            Instruction::Binary(operator) => {
+                let right = fiber.pop();
+                let left = fiber.pop();
+
+                let value = binary(operator, left, right, span)?;
+                self.budget.value(&value, span)?;
+
+                fiber.stack.push(value);
+            }
+            Instruction::List(length) => {
+                let values = fiber.stack.split_off(fiber.stack.len() - length as usize);
+                let value = Value::List { values };
+                self.budget.value(&value, span)?;
+
+                fiber.stack.push(value);
+            }
+            Instruction::LoadSample => match fiber.pop() {
+                Value::String { string } => {
+                    let path = StringId::new(string);
+                    let id = self.samples.len();
+                    let sample = *self
+                        .samples
+                        .entry(path)
+                        .or_insert(SampleHandle { id, path });
+
+                    fiber.stack.push(Value::Sample { sample });
+                }
+                value => {
+                    return Err(RuntimeError::TypeMismatch {
+                        expected: "string",
+                        found: value.type_name(),
+                        span,
+                    })
+                }
+            },
+            Instruction::Degree(degree) => {
+                let note = fiber
+                    .state()
+                    .properties
+                    .key
+                    .and_then(|key| key.degree(degree.into()))
+                    .ok_or(RuntimeError::InvalidDegree { span })?;
+
+                fiber.stack.push(Value::Note { note });
+            }
+            Instruction::Index(index) => {
+                let spans = self.program.indices[index as usize];
+                let position = fiber.pop();
+                let target = fiber.pop();
+
+                fiber.stack.push(interpreter::index(
+                    &target,
+                    &position,
+                    spans.target,
+                    spans.index,
+                    span,
+                )?);
+            }
+            Instruction::Call(index) => {
+                let call = &self.program.calls[index as usize];
+
+                let values = fiber.stack.split_off(fiber.stack.len() - call.names.len());
+                let function = match fiber.pop() {
+                    Value::Function { function } => function,
+                    Value::Native { name } => {
+                        let value = self.call_native(name, call, &values, span)?;
+                        self.budget.value(&value, span)?;
+
+                        fiber.stack.push(value);
+
+                        return Ok(());
+                    }
+                    value => {
+                        return Err(RuntimeError::NotCallable {
+                            found: value.type_name(),
+                            span: call.callee,
+                        })
+                    }
+                };
+
+                let arguments = call
+                    .names
+                    .iter()
+                    .zip(values)
+                    .zip(&call.spans)
+                    .map(|((name, value), span)| (*name, value, *span))
+                    .collect();
+                let mut arguments = bind(function, arguments, span)?;
+                if !function.is_sequence_function() {
+                    let value =
+                        stdlib::call(function, &arguments, &self.tuning, self.files.as_deref())?;
+                    self.budget.value(&value, span)?;
+                    fiber.stack.push(value);
+
+                    return Ok(());
+                }
+
+                let (sequence, sequence_span) =
+                    arguments.pop().expect("sequence is the last parameter");
+                let pattern = self.pattern(&sequence, fiber, sequence_span)?;
+                let pattern = transform(
+                    function,
+                    pattern,
+                    &arguments,
+                    &mut self.rng,
+                    &self.tempo,
+                    &self.budget,
+                    span,
+                )?;
+                self.budget.pattern(&pattern, span)?;
+
+                fiber.stack.push(Value::Sequence {
+                    sequence: Sequence::Pattern(pattern),
+                });
+            }
+            Instruction::Count => {
+                let count = expect_count(&fiber.pop(), span)?;
+                fiber.stack.push(Value::Integer {
+                    value: count as i64,
+                });
+            }
+            Instruction::Wait => {
+                let value = fiber.pop();
+                fiber.cursor = wait(&value, fiber.cursor, &self.tempo, span)?;
+            }
+            Instruction::Play => {
+                let value = fiber.pop();
+                self.play(&value, fiber, span)?;
+            }
+            Instruction::Spawn => {
+                let value = fiber.pop();
+                self.spawn(&value, fiber, span)?;
+            }
+            Instruction::EnterWith(index) => {
+                let block = &self.program.with_blocks[index as usize];
+                let params = fiber
+                    .stack
+                    .split_off(fiber.stack.len() - block.params.len());
+                let values = fiber
+                    .stack
+                    .split_off(fiber.stack.len() - block.evaluated.len());
+
+                let mut settings = block.settings.clone().map_err(RuntimeError::Property)?;
+                for ((property, span), value) in block.evaluated.iter().zip(values) {
+                    let setting = Setting::from_value(*property, &value, *span)
+                        .map_err(RuntimeError::Property)?;
+                    settings.push((setting, *span));
+                }
+                let mut nested = fiber.state().with(&settings, self.files.as_deref())?;
+
+                for ((name, span), value) in block.params.iter().zip(params) {
+                    let change = param_change(*name, Some(&value));
+                    fiber.output.control(fiber.cursor, change, *span);
+
+                    nested.params.insert(*name, value);
+                }
+
+                fiber.states.push(Rc::new(nested));
+            }
+            Instruction::ExitWith(index) => {
+                fiber.states.pop();
+                let previous = fiber.state().clone();
+
+                for (name, span) in &self.program.with_blocks[index as usize].params {
+                    let change = param_change(*name, previous.params.get(name));
+                    fiber.output.control(fiber.cursor, change, *span);
+                }
+            }
+            Instruction::Loop(end) => match fiber.stack.last_mut() {
+                Some(Value::Integer { value }) if *value > 0 => *value -= 1,
+                _ => {
+                    fiber.pop();
+                    Self::jump(fiber, end);
+                }
+            },
+            Instruction::Mark => fiber.marks.push(fiber.cursor),
+            Instruction::Endless(start) => {
+                let mark = fiber.marks.last_mut().expect("endless repeat is marked");
+                if *mark == fiber.cursor {
+                    return Err(RuntimeError::EmptyLoop { span });
+                }
+
+                *mark = fiber.cursor;
+                Self::jump(fiber, start);
+            }
+            Instruction::Jump(target) => Self::jump(fiber, target),
+            Instruction::Poly(index) => {
+                let block = &self.program.poly_blocks[index as usize];
+                let chunk = fiber.frames.last().expect("fiber has a frame").chunk;
+
+                let counts = fiber
+                    .stack
+                    .split_off(fiber.stack.len() - block.counts)
+                    .iter()
+                    .map(|count| expect_count(count, span))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let mut patterns = Vec::new();
+                for voice in &block.voices {
+                    let (events, length) = self.render(fiber, span, |_, rendered| {
+                        rendered.frames.push(Frame {
+                            chunk,
+                            ip: *voice as usize,
+                            scoped: false,
+                        });
+
+                        Ok(())
+                    })?;
+
+                    patterns.push(into_pattern(events, length, &fiber.state().properties));
+                }
+
+                self.budget.poly(&patterns, &counts, span)?;
+                let stretched = poly(patterns, &counts, DEFAULT_POLY_SPAN)
+                    .map_err(|error| RuntimeError::Pattern { error, span })?;
+
+                for event in stretched.into_events() {
+                    fiber
+                        .output
+                        .sound(fiber.cursor, event, fiber.state().properties.key, span);
+                }
+
+                fiber.cursor = advance(fiber.cursor, DEFAULT_POLY_SPAN, span)?;
+            }
+            Instruction::Tempo(index) => {
+                let change = self.program.tempo_changes[index as usize];
+
+                let ramp = match change.ramp {
+                    Some(ramp) => {
+                        let length = fiber.pop();
+                        let target = fiber.pop();
+                        let unit = match ramp.unit {
+                            TimeUnit::Beats => Rational::ONE,
+                            TimeUnit::Bars => BEATS_PER_BAR,
+                        };
+
+                        Some(Ramp {
+                            target: expect_f64(&target, ramp.target)?,
+                            length: expect_rational(&length, "number", ramp.length)? * unit,
+                            curve: match ramp.curve {
+                                RampCurve::Linear => Curve::Linear,
+                                RampCurve::Exponential => Curve::Exponential,
+                            },
+                        })
+                    }
+                    None => None,
+                };
+                let bpm = expect_f64(&fiber.pop(), change.bpm)?;
+
+                match ramp {
+                    Some(ramp) => self.tempo.ramp(fiber.cursor, bpm, ramp),
+                    None => self.tempo.set(fiber.cursor, bpm),
+                }
+                .map_err(|error| RuntimeError::Tempo { error, span })?;
+
+                fiber
+                    .output
+                    .control(fiber.cursor, EventKind::TempoChange { bpm, ramp }, span);
+            }
+            Instruction::Tuning => {
+                let tuning = match fiber.pop() {
+                    Value::Tuning { tuning } => tuning,
+                    value => {
+                        return Err(RuntimeError::TypeMismatch {
+                            expected: "tuning",
+                            found: value.type_name(),
+                            span,
+                        })
+                    }
+                };
+
+                self.tuning = (*tuning).clone();
+                fiber
+                    .output
+                    .control(fiber.cursor, EventKind::TuningChange { tuning }, span);
+            }
+            Instruction::Return => {
+                let frame = fiber.frames.pop().expect("fiber has a frame");
+
+                if frame.scoped {
+                    fiber.scopes.pop();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn call_native(
+        &self,
+        name: StringId,
+        call: &Call,
+        values: &[Value],
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        let native = |message: String| RuntimeError::Native {
+            function: name,
+            message,
+            span,
+        };
+
+        if call.names.iter().any(Option::is_some) {
+            return Err(native(
+                "arguments of host functions can't be named".to_string(),
+            ));
+        }
+
+        let function = self
+            .host
+            .function(name)
+            .ok_or_else(|| native("function is not registered".to_string()))?;
+
+        function(values).map_err(native)
+    }
+
+    fn load(&self, index: u32, fiber: &Fiber, span: Span) -> Result<Value, RuntimeError> {
+        let variable = self.program.variables[index as usize];
+
+        let value = match variable.location {
+            Location::Global(slot) => self.globals[slot].as_ref(),
+            // Recursive sequences look for the variable in the scopes of
+            // the sequences playing them, if they haven't defined it yet.
+            Location::Local { chunk, slot } => fiber
+                .scopes
+                .iter()
+                .rev()
+                .filter(|scope| scope.chunk == chunk)
+                .find_map(|scope| scope.locals[slot].as_ref()),
+        };
+
+        value.cloned().ok_or(RuntimeError::UninitializedVariable {
+            identifier: Identifier::new(variable.name, span),
+        })
+    }
+
+    /// Plays a value at the cursor of a fiber. Sequences move the cursor
+    /// to their end, notes and samples don't wait.
+    fn play(&mut self, value: &Value, fiber: &mut Fiber, span: Span) -> Result<(), RuntimeError> {
+        if let Some(notes) = notes(value) {
+            let properties = &fiber.state().properties;
+            let notes = notes
+                .into_iter()
+                .map(|note| {
+                    properties
+                        .transpose_note(note)
+                        .ok_or(RuntimeError::NoteOutOfRange { span })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let pattern = properties
+                .expand_chord(&notes, properties.length, &mut self.rng)
+                .map_err(|error| RuntimeError::Pattern { error, span })?;
+
+            let state = fiber.state().clone();
+            for event in pattern.into_events() {
+                let note = event.value;
+                let event =
+                    state.groove(event.with_value(Sound::Note(note)), fiber.cursor, span)?;
+                fiber
+                    .output
+                    .sound(fiber.cursor, event, fiber.state().properties.key, span);
+            }
+
+            return Ok(());
+        }
+
+        match value {
+            Value::Sample { sample } => {
+                let event = pattern::Event::new(
+                    Rational::ZERO,
+                    fiber.state().properties.length,
+                    Sound::Sample(*sample),
+                );
+                let event = fiber.state().groove(event, fiber.cursor, span)?;
+                fiber
+                    .output
+                    .sound(fiber.cursor, event, fiber.state().properties.key, span);
+            }
+            Value::Sequence {
+                sequence: Sequence::Defined(id),
+            } => {
+                if let Some(chunk) = self.program.sequences.get(id).copied() {
+                    self.budget.depth(fiber.frames.len(), span)?;
+
+                    self.call(fiber, chunk);
+                }
+            }
+            Value::Sequence {
+                sequence: Sequence::Pattern(pattern),
+            } => {
+                for event in pattern.events() {
+                    fiber.output.sound(
+                        fiber.cursor,
+                        event.clone(),
+                        fiber.state().properties.key,
+                        span,
+                    );
+                }
+
+                fiber.cursor = advance(fiber.cursor, pattern.length(), span)?;
+            }
+            // Lists of samples and sequences play every element at once.
+            Value::List { values } => {
+                let mut length = Rational::ZERO;
+                for value in values {
+                    let (events, played) = self.render_value(value, fiber, span)?;
+
+                    for event in events {
+                        fiber
+                            .output
+                            .sound(fiber.cursor, event, fiber.state().properties.key, span);
+                    }
+                    length = length.max(played);
+                }
+
+                fiber.cursor = advance(fiber.cursor, length, span)?;
+            }
+            _ => {
+                return Err(RuntimeError::TypeMismatch {
+                    expected: "playable value",
+                    found: value.type_name(),
+                    span,
+                })
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Plays a value on a new channel: `play! drums`. Inside patterns, it
+    /// is played without moving the cursor instead.
+    fn spawn(&mut self, value: &Value, fiber: &mut Fiber, span: Span) -> Result<(), RuntimeError> {
+        if let Output::Pattern(_) = fiber.output {
+            let (events, _) = self.render_value(value, fiber, span)?;
+
+            for event in events {
+                fiber
+                    .output
+                    .sound(fiber.cursor, event, fiber.state().properties.key, span);
+            }
+
+            return Ok(());
+        }
+
+        // The new channel starts with the parameters of the block it is
+        // started from, and sees its local variables.
+        let state = fiber.state().clone();
+        let mut params: Vec<_> = state.params.iter().collect();
+        params.sort_by_key(|(name, _)| name.resolve());
+
+        let mut output = Output::Events(Vec::new());
+        for (name, value) in params {
+            output.control(fiber.cursor, param_change(*name, Some(value)), span);
+        }
+
+        self.budget.channel(span)?;
+
+        let mut channel = Fiber::new(fiber.cursor, fiber.scopes.clone(), state, output, 0);
+        self.play(value, &mut channel, span)?;
+        self.budget.events(channel.output.events(), &self.tempo)?;
+        self.spawned.push(channel);
+
+        Ok(())
+    }
+
+    /// Runs a fiber rendering sounds into a pattern, which starts with
+    /// whatever `start` sets up. Returns the sounds and the number of beats
+    /// the fiber waited.
+    fn render(
+        &mut self,
+        fiber: &mut Fiber,
+        span: Span,
+        start: impl FnOnce(&mut Self, &mut Fiber) -> Result<(), RuntimeError>,
+    ) -> Result<(Vec<pattern::Event<Sound>>, Rational), RuntimeError> {
+        if fiber.depth >= MAX_RENDER_DEPTH {
+            return Err(RuntimeError::StackOverflow { span });
+        }
+
+        let scopes = std::mem::take(&mut fiber.scopes);
+        let depth = scopes.len();
+        let output = Output::Pattern(Vec::new());
+        let state = fiber.state().clone();
+        let mut rendered = Fiber::new(Rational::ZERO, scopes, state, output, fiber.depth + 1);
+
+        let result = start(self, &mut rendered).and_then(|()| self.complete(&mut rendered));
+
+        // Sequences cut off by the horizon don't end their scopes.
+        rendered.scopes.truncate(depth);
+        fiber.scopes = rendered.scopes;
+        result?;
+
+        match rendered.output {
+            Output::Pattern(events) => Ok((events, rendered.cursor)),
+            Output::Events(_) => unreachable!("rendered fiber plays into a pattern"),
+        }
+    }
+
+    fn render_value(
+        &mut self,
+        value: &Value,
+        fiber: &mut Fiber,
+        span: Span,
+    ) -> Result<(Vec<pattern::Event<Sound>>, Rational), RuntimeError> {
+        self.render(fiber, span, |machine, rendered| {
+            machine.play(value, rendered, span)
+        })
+    }
+
+    /// Turns a value into a pattern, which builtin functions transform.
+    fn pattern(
+        &mut self,
+        value: &Value,
+        fiber: &mut Fiber,
+        span: Span,
+    ) -> Result<Pattern<Sound>, RuntimeError> {
+        if let Value::Sequence {
+            sequence: Sequence::Pattern(pattern),
+        } = value
+        {
+            return Ok(pattern.clone());
+        }
+
+        let (events, length) = self.render_value(value, fiber, span)?;
+
+        Ok(into_pattern(events, length, &fiber.state().properties))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::resolve::resolve,
+        ctx::interpreter::{interpret, Options},
+        syntax::{macros::render_error, parse::parse, scan::scan},
+        test_snapshots,
+        vm::compile::compile,
+    };
+
+    use super::run;
+
+    fn render_result(result: Result<crate::ir::Ir, super::RuntimeError>) -> String {
+        match result {
+            Ok(ir) => ir.to_string(),
+            Err(error) => render_error(&error),
+        }
+    }
+
+    /// Runs the source with the bytecode machine, checking that the
+    /// interpreter plays the same.
+    fn render(source: &str) -> String {
+        let statements = parse(scan(source).unwrap()).unwrap();
+        let resolution = resolve(&statements);
+        let options = Options {
+            seed: 7,
+            ..Options::default()
+        };
+
+        let program = compile(&statements, &resolution);
+        let rendered = render_result(run(&program, &options));
+
+        assert_eq!(
+            rendered,
+            render_result(interpret(&statements, &resolution, &options))
+        );
+
+        rendered
+    }
+
+    test_snapshots!(
+        (
+            variables,
+            "let root = c4\nlet step = 2\nrepeat 3 {\n play root\n root = root + step\n wait 1/2\n}"
+        ),
+        (
+            sequences,
+            "sequence riff {\n play [c4, e4]\n wait 1\n play g4\n wait 1\n}\nplay! riff\nplay fast(2, riff)\nplay rev(riff)"
+        ),
+        (
+            samples,
+            "let kick = load_sample \"kick.wav\"\nplay euclid(3, 8, kick)\nplay [kick, load_sample \"hat.wav\"]"
+        ),
+        (euclid_rotation, "play euclid(3, 8, 2, c4)"),
+        (euclid_rhythm, "play euclid(3, 8)\nwait 2\nplay euclid(2, 4, rotation: -1)"),
+        (
+            every,
+            "sequence a {\n play c4\n wait 1\n play d4\n wait 1\n}\nplay every(2, rev, a)"
+        ),
+        (endless, "repeat {\n play c4\n wait 256\n}"),
+        (
+            local_variables,
+            "sequence a {\n let n = c4\n repeat 2 {\n  let m = n + 1\n  play m\n  n = m\n  wait 1\n }\n}\nplay a\nplay a"
+        ),
+        (
+            properties,
+            "with key: d_major, synth: pad, transpose: 2 {\n play ^3\n wait 1\n with synth: lead {\n  play ^5\n  wait 1/2\n }\n play! ^1\n}"
+        ),
+        (
+            poly,
+            "poly(3, 2) {\n play c4\n play [e4, g4]\n}\nwait 1\nplay c5"
+        ),
+        (
+            tempo,
+            "tempo 90\nplay c4\nwait 1\ntempo 90 -> 120 over 2 bars\nwait 500ms\nplay d4"
+        ),
+        (
+            deterministic,
+            "sequence hats {\n repeat 8 {\n  play degrade(0.5, [c4, e4])\n  wait 1/2\n }\n}\nplay! hats\nplay! humanize(20ms, 0.2, hats)\nplay hats"
+        ),
+        (
+            channels,
+            "let root = c4\nsequence arp {\n repeat 3 {\n  play root\n  wait 1\n }\n}\nplay! arp\nwith synth: pad {\n play! [e4, g4]\n}\nrepeat 2 {\n wait 1/2\n root = root + 2\n wait 1/2\n}"
+        ),
+        (degree_without_key, "play ^1"),
+        (empty_loop, "repeat {\n play c4\n}"),
+        (duplicate_argument, "play fast(factor: 2, factor: 3, c4)"),
+        (
+            computed_properties,
+            "let n = 7\nlet home = d_minor\nwith transpose: n - 2, key: home, length: 1 / (2 * 4), swing: 0.6 {\n play [^1, ^3]\n wait 1\n}"
+        ),
+        (duplicate_property, "with transpose: -2, transpose: 7 {\n play c4\n}"),
+        (division_by_zero, "let a = 0\nwait 1 / a"),
+        (
+            exact_fractions,
+            "let n = 3\nrepeat 3 {\n play c4\n wait 1 / n\n}\nplay d4\nwait 1 / n * 1.5\nplay e4"
+        ),
+        (recursion, "sequence a {\n play a\n}\nplay a"),
+        (render_recursion, "sequence a {\n play fast(2, a)\n}\nplay a"),
+        (not_callable, "let a = 1\nplay a(c4)"),
+        (
+            theory,
+            "play invert(cmaj7, 1)\nwait midi(c4) - 59\nplay voicing(chord: am, low: g3, high: c5)\nplay transpose(freq(a4), 2)"
+        ),
+        (
+            scales,
+            "let s = scale(c4, [0, 3, 7])\nrepeat 4 {\n play s[-2]\n s = scale(s[2], \"blues\")\n wait 1\n}\nplay quantize(c4, s)"
+        ),
+        (not_indexable, "play c4[1]"),
+        (
+            recognition,
+            "play recognize(voice_lead([g7], 3)[0])\nwait 1\nplay recognize(c4)"
+        ),
+        (
+            voice_leading,
+            "let voicings = voice_lead([cmaj, fmaj, gmaj, am], 3, low: c3, high: c5)\nplay voicings[1]\nplay [c4, e4][-3]"
+        ),
+        (
+            progression,
+            "let key = c_major\nrepeat 2 {\n play prog(\"I vi IV V7\", key)\n wait 1\n key = g_major\n}"
+        ),
+        (invalid_assignment, "c4 = 1"),
+        (
+            tunings,
+            "let tunings = [edo(31), just([5/4, 3/2, 2], c4, 260)]\nrepeat 2 {\n tuning tunings[0]\n wait freq(c5) / freq(c4)\n play c4\n tunings = [tunings[-1]]\n}\ntuning 12"
+        ),
+        (invalid_edo, "tuning edo(-1)"),
+        (overflow, "let a = 9223372036854775807\nwait 1\nwait a"),
+        (note_out_of_range, "play transpose([c4, e4], 1000000)"),
+        (
+            transpose_out_of_range,
+            "with transpose: 2147483647 {\n with transpose: 1 {\n  play c4\n }\n}"
+        ),
+        (
+            groove,
+            "with swing: 60%, groove: \"mpc_8\" {\n repeat 4 {\n  play c4\n  wait 1/4\n }\n play! [e4, g4]\n}"
+        ),
+        (unknown_groove, "with groove: \"funk\" {\n play c4\n}"),
+    );
+}
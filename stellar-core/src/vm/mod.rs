@@ -0,0 +1,328 @@
+//! Bytecode virtual machine: a faster alternative to the
+//! [tree-walking interpreter](crate::ctx::interpreter) for large
+//! generative pieces.
+//!
+//! A program is [compiled](compile::compile) into a [`Program`] of
+//! [`Chunk`]s, one for the main block and one for every sequence, which
+//! the stack [machine](machine::run) executes. Both produce the same
+//! [event timeline](crate::ir).
+
+pub mod compile;
+pub mod machine;
+
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    analysis::resolve::SymbolId,
+    ctx::{
+        properties::{PropertyError, Setting},
+        value::{Sequence, Value},
+    },
+    syntax::{
+        ast::{BinaryOperatorKind, RampCurve, TimeUnit},
+        location::Span,
+        string_id::StringId,
+        token::Identifier,
+    },
+};
+
+/// Single operation of the machine. Operands are indices into the tables
+/// of the [`Program`], or into the code of the current chunk for jumps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    /// Pushes a value of the constant pool.
+    Constant(u32),
+    /// Pushes the value of a variable.
+    Load(u32),
+    /// Pops the value of a variable defined with `let`.
+    Define(u32),
+    /// Changes a variable to the value on top of the stack, which is kept,
+    /// because assignments are expressions.
+    Assign(u32),
+    /// Fails with an error about an assignment to something, which is not
+    /// a variable.
+    InvalidAssignment,
+    /// Fails with an error about an undefined identifier.
+    Undefined(u32),
+    Pop,
+    Negate,
+    Not,
+    Binary(BinaryOperatorKind),
+    /// Replaces values on top of the stack with a list of them.
+    List(u32),
+    LoadSample,
+    /// Pushes the note of a scale degree in the current key.
+    Degree(i32),
+    /// Replaces a scale or a list and an index on top of the stack with the
+    /// degree or element, as described by an index expression of the
+    /// program.
+    Index(u32),
+    /// Calls the function below its arguments, which are described by a
+    /// call of the program.
+    Call(u32),
+    /// Checks that the value on top of the stack is a number of
+    /// repetitions.
+    Count,
+    Wait,
+    Play,
+    /// Plays the value on top of the stack on a new channel: `play!`.
+    Spawn,
+    /// Enters a `with` block of the program, popping values of the
+    /// parameters it changes.
+    EnterWith(u32),
+    ExitWith(u32),
+    /// Jumps to the end of a `repeat` if its count on top of the stack is
+    /// zero, and decrements it otherwise.
+    Loop(u32),
+    /// Remembers the beat an endless `repeat` starts at.
+    Mark,
+    /// Jumps back to the start of an endless `repeat`, unless its body
+    /// didn't wait.
+    Endless(u32),
+    Jump(u32),
+    /// Plays voices of a `poly` block of the program, popping their
+    /// counts.
+    Poly(u32),
+    /// Changes tempo as described by a tempo change of the program,
+    /// popping the tempo, and the target and length of the ramp.
+    Tempo(u32),
+    /// Changes the tuning to the one on top of the stack.
+    Tuning,
+    /// Ends the chunk, or a voice of a `poly` block.
+    Return,
+}
+
+/// Compiled code of the main block or of a sequence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    /// Name of the sequence, or `None` for the main block.
+    pub name: Option<StringId>,
+    pub code: Vec<Instruction>,
+    /// Span of the source code every instruction was compiled from.
+    pub spans: Vec<Span>,
+    /// Number of local variables.
+    pub locals: usize,
+}
+
+/// Where the value of a variable is kept.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Location {
+    /// Variable of the global scope, shared by all channels.
+    Global(usize),
+    /// Variable of a block inside of a chunk, kept in the frame of the
+    /// chunk being played.
+    Local { chunk: usize, slot: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Variable {
+    pub name: StringId,
+    pub location: Location,
+}
+
+/// Arguments of a call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    /// Names of the arguments, in the order they are pushed.
+    pub names: Vec<Option<Identifier>>,
+    pub spans: Vec<Span>,
+    pub callee: Span,
+}
+
+/// Spans of an index expression: `s[5]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Index {
+    pub target: Span,
+    pub index: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithBlock {
+    /// Settings of the block, which values are names, with spans of their
+    /// values, or the error of an invalid property, which is reported when
+    /// the block is entered.
+    pub settings: Result<Vec<(Setting, Span)>, PropertyError>,
+    /// Properties, which values are evaluated, with spans of the values, in
+    /// the order the values are pushed before the parameters.
+    pub evaluated: Vec<(Identifier, Span)>,
+    /// Parameters passed on to backends, in the order their values are
+    /// pushed.
+    pub params: Vec<(StringId, Span)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolyBlock {
+    /// Positions of the voices in the chunk. Every voice ends with
+    /// [`Instruction::Return`].
+    pub voices: Vec<u32>,
+    /// Number of counts on the stack.
+    pub counts: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoChange {
+    pub bpm: Span,
+    pub ramp: Option<TempoRamp>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoRamp {
+    pub target: Span,
+    pub length: Span,
+    pub unit: TimeUnit,
+    pub curve: RampCurve,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Program {
+    /// Compiled blocks. The first one is the main block.
+    pub chunks: Vec<Chunk>,
+    pub constants: Vec<Value>,
+    pub variables: Vec<Variable>,
+    pub identifiers: Vec<Identifier>,
+    pub calls: Vec<Call>,
+    pub indices: Vec<Index>,
+    pub with_blocks: Vec<WithBlock>,
+    pub poly_blocks: Vec<PolyBlock>,
+    pub tempo_changes: Vec<TempoChange>,
+    /// Chunks of defined sequences.
+    pub sequences: HashMap<SymbolId, usize>,
+    /// Number of global variables.
+    pub globals: usize,
+    /// Global slots of the globals of the host, which are set before the
+    /// program is run.
+    pub externals: Vec<(StringId, usize)>,
+}
+
+impl Program {
+    fn write_instruction(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        instruction: Instruction,
+    ) -> std::fmt::Result {
+        let variable = |index: u32| self.variables[index as usize].name.resolve();
+
+        match instruction {
+            Instruction::Constant(index) => match &self.constants[index as usize] {
+                Value::Sequence {
+                    sequence: Sequence::Defined(id),
+                } => {
+                    let name = self
+                        .sequences
+                        .get(id)
+                        .and_then(|chunk| self.chunks[*chunk].name);
+                    let name = name.map_or(String::new(), |name| name.resolve());
+                    write!(f, "constant sequence {name}")
+                }
+                value => write!(f, "constant {value}"),
+            },
+            Instruction::Load(index) => write!(f, "load {}", variable(index)),
+            Instruction::Define(index) => write!(f, "define {}", variable(index)),
+            Instruction::Assign(index) => write!(f, "assign {}", variable(index)),
+            Instruction::InvalidAssignment => write!(f, "invalid_assignment"),
+            Instruction::Undefined(index) => write!(
+                f,
+                "undefined {}",
+                self.identifiers[index as usize].name().resolve()
+            ),
+            Instruction::Pop => write!(f, "pop"),
+            Instruction::Negate => write!(f, "negate"),
+            Instruction::Not => write!(f, "not"),
+            Instruction::Binary(operator) => {
+                let operator = match operator {
+                    BinaryOperatorKind::Plus => "add",
+                    BinaryOperatorKind::Minus => "subtract",
+                    BinaryOperatorKind::Star => "multiply",
+                    BinaryOperatorKind::Slash => "divide",
+                    BinaryOperatorKind::Assign => "assign",
+                };
+                write!(f, "{operator}")
+            }
+            Instruction::List(length) => write!(f, "list {length}"),
+            Instruction::LoadSample => write!(f, "load_sample"),
+            Instruction::Degree(degree) => write!(f, "degree {degree}"),
+            Instruction::Index(_) => write!(f, "index"),
+            Instruction::Call(index) => {
+                let call = &self.calls[index as usize];
+                write!(f, "call {}", call.names.len())?;
+
+                for name in call.names.iter().flatten() {
+                    write!(f, " {}:", name.name().resolve())?;
+                }
+
+                Ok(())
+            }
+            Instruction::Count => write!(f, "count"),
+            Instruction::Wait => write!(f, "wait"),
+            Instruction::Play => write!(f, "play"),
+            Instruction::Spawn => write!(f, "spawn"),
+            Instruction::EnterWith(index) | Instruction::ExitWith(index) => {
+                let block = &self.with_blocks[index as usize];
+                match instruction {
+                    Instruction::EnterWith(_) => write!(f, "enter_with")?,
+                    _ => write!(f, "exit_with")?,
+                }
+
+                // Values of evaluated properties are only read when the
+                // block is entered.
+                if let Instruction::EnterWith(_) = instruction {
+                    for (property, _) in &block.evaluated {
+                        write!(f, " {}", property.name().resolve())?;
+                    }
+                }
+                for (name, _) in &block.params {
+                    write!(f, " {}", name.resolve())?;
+                }
+
+                Ok(())
+            }
+            Instruction::Loop(target) => write!(f, "loop -> {target:04}"),
+            Instruction::Mark => write!(f, "mark"),
+            Instruction::Endless(target) => write!(f, "endless -> {target:04}"),
+            Instruction::Jump(target) => write!(f, "jump -> {target:04}"),
+            Instruction::Poly(index) => {
+                let block = &self.poly_blocks[index as usize];
+                write!(f, "poly {}", block.counts)?;
+
+                for voice in &block.voices {
+                    write!(f, " {voice:04}")?;
+                }
+
+                Ok(())
+            }
+            Instruction::Tempo(index) => match self.tempo_changes[index as usize].ramp {
+                Some(_) => write!(f, "tempo_ramp"),
+                None => write!(f, "tempo"),
+            },
+            Instruction::Tuning => write!(f, "tuning"),
+            Instruction::Return => write!(f, "return"),
+        }
+    }
+}
+
+impl Display for Program {
+    /// Disassembles every chunk, one instruction per line:
+    /// `0003  2:7  load root`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+
+            match chunk.name {
+                Some(name) => writeln!(f, "sequence {}:", name.resolve())?,
+                None => writeln!(f, "main:")?,
+            }
+
+            for (position, (instruction, span)) in chunk.code.iter().zip(&chunk.spans).enumerate() {
+                let start = span.start();
+                let location = format!("{}:{}", start.line(), start.column());
+                write!(f, "  {position:04}  {location:<7}  ")?;
+                self.write_instruction(f, *instruction)?;
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}